@@ -7,14 +7,14 @@ use llm_utl::{Config, FileFilterConfig, FilterConfig};
 
 fn main() -> anyhow::Result<()> {
     // Configure code filtering
-    let filter_config = FilterConfig {
-        remove_tests: true,
-        remove_doc_comments: false,    // Keep documentation
-        remove_comments: true,          // Remove regular comments
-        remove_blank_lines: true,
-        preserve_headers: true,         // Keep copyright/license headers
-        remove_debug_prints: true,      // Remove println!, dbg!, etc.
-    };
+    let filter_config = FilterConfig::default()
+        .remove_tests(true)
+        .remove_doc_comments(false)    // Keep documentation
+        .remove_comments(true)          // Remove regular comments
+        .preserve_headers(true)         // Keep copyright/license headers
+        .collapse_headers(false)        // Keep headers whole, not just the SPDX line
+        .remove_debug_prints(true)      // Remove println!, dbg!, etc.
+        .debug_print_names(["log::debug!"]); // Also remove these calls
 
     // Configure file filtering
     let file_filter = FileFilterConfig::default()
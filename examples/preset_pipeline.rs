@@ -34,14 +34,9 @@ fn generate_code_review_prompts() -> Result<()> {
         .preset(PresetKind::CodeReview)
         .format(OutputFormat::Markdown)
         .max_tokens(150_000)
-        .filter_config(FilterConfig {
-            remove_tests: true,
-            remove_doc_comments: false, // Keep docs for review
-            remove_comments: false,     // Keep comments for context
-            remove_blank_lines: true,
-            preserve_headers: true,
-            remove_debug_prints: true,
-        })
+        .filter_config(FilterConfig::production()
+            .remove_doc_comments(false) // Keep docs for review
+            .remove_comments(false))    // Keep comments for context
         .build()?;
 
     println!("Configuration:");
@@ -99,14 +94,7 @@ fn generate_test_generation_prompts() -> Result<()> {
         .preset(PresetKind::TestGeneration)
         .format(OutputFormat::Json) // JSON format for easier parsing
         .max_tokens(150_000)
-        .filter_config(FilterConfig {
-            remove_tests: true, // Remove existing tests
-            remove_doc_comments: false,
-            remove_comments: false,
-            remove_blank_lines: true,
-            preserve_headers: true,
-            remove_debug_prints: true,
-        })
+        .filter_config(FilterConfig::production().remove_tests(true)) // Remove existing tests
         .build()?;
 
     println!("Configuration:");
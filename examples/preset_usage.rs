@@ -34,7 +34,7 @@ fn code_review_example() -> Result<()> {
         .build()?;
 
     println!("Config created for code review");
-    println!("- Preset: {:?}", config.preset);
+    println!("- Preset: {:?}", config.preset.as_ref().map(|p| &p.id));
     println!("- Max tokens: {}", config.max_tokens);
     println!("- Output: {}\n", config.output_dir.display());
 
@@ -56,7 +56,7 @@ fn documentation_example() -> Result<()> {
         .build()?;
 
     println!("Config created for documentation");
-    println!("- Preset: {:?}", config.preset);
+    println!("- Preset: {:?}", config.preset.as_ref().map(|p| &p.id));
     println!("- Keeps doc comments: {:?}", config.filter_config.remove_doc_comments);
     println!("- Output: {}\n", config.output_dir.display());
 
@@ -75,7 +75,7 @@ fn security_audit_example() -> Result<()> {
         .build()?;
 
     println!("Config created for security audit");
-    println!("- Preset: {:?}", config.preset);
+    println!("- Preset: {:?}", config.preset.as_ref().map(|p| &p.id));
     println!("- Max tokens: {}", config.max_tokens);
     println!("- Output: {}\n", config.output_dir.display());
 
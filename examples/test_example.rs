@@ -12,14 +12,7 @@ fn main() -> anyhow::Result<()> {
         .format(OutputFormat::Markdown)
         .max_tokens(300_000)
         .overlap_tokens(2_000)
-        .filter_config(FilterConfig {
-            remove_tests: true,
-            remove_doc_comments: true,
-            remove_comments: true,
-            remove_blank_lines: true,
-            preserve_headers: true,
-            remove_debug_prints: true,
-        })
+        .filter_config(FilterConfig::minimal().preserve_headers(true))
         .file_filter_config(
             FileFilterConfig::default()
                 .allow_only(vec!["**/*.rs".to_string()])
@@ -31,7 +31,7 @@
 //! # Ok::<(), llm_utl::Error>(())
 //! ```
 
-use crate::{Config, FileFilterConfig, FilterConfig, OutputFormat, Pipeline, PipelineStats, PresetKind, Result, TokenizerKind};
+use crate::{preset::{user_presets_dir, PresetRegistry}, Config, ContentMode, FileFilterConfig, FilterConfig, GitRevSource, GitSelection, LLMPreset, OutputFormat, PackingStrategy, Pipeline, PipelineStats, PresetKind, Result, TokenizerKind, Watcher};
 use std::path::{Path, PathBuf};
 
 // ============================================================================
@@ -65,11 +65,21 @@ pub struct Scan {
     format: OutputFormat,
     max_tokens: usize,
     overlap: usize,
+    tokenizer: TokenizerKind,
+    packing: PackingStrategy,
+    line_numbers: bool,
+    cache: bool,
     preset: Option<PresetKind>,
+    preset_id: Option<String>,
+    preset_files: Vec<PathBuf>,
     filters: FilterOptions,
     allow_files: Vec<String>,
     excludes: Vec<String>,
     exclude_files: Vec<String>,
+    selection: Option<GitSelection>,
+    git_rev: Option<String>,
+    content_mode: Option<ContentMode>,
+    diff_base: Option<String>,
     template_path: Option<PathBuf>,
     custom_format_name: Option<String>,
     custom_extension: Option<String>,
@@ -83,6 +93,8 @@ struct FilterOptions {
     comments: FilterMode,
     doc_comments: FilterMode,
     debug_prints: FilterMode,
+    debug_print_names: Vec<String>,
+    collapse_headers: bool,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -99,11 +111,21 @@ impl Default for Scan {
             format: OutputFormat::Markdown,
             max_tokens: 100_000,
             overlap: 1_000,
+            tokenizer: TokenizerKind::Enhanced,
+            packing: PackingStrategy::Sequential,
+            line_numbers: false,
+            cache: false,
             preset: None,
+            preset_id: None,
+            preset_files: vec![],
             filters: FilterOptions::default(),
             excludes: default_excludes(),
             exclude_files: vec![],
             allow_files: vec![],
+            selection: None,
+            git_rev: None,
+            content_mode: None,
+            diff_base: None,
             template_path: None,
             custom_format_name: None,
             custom_extension: None,
@@ -119,6 +141,8 @@ impl Default for FilterOptions {
             comments: FilterMode::Remove,
             doc_comments: FilterMode::Remove,
             debug_prints: FilterMode::Remove,
+            debug_print_names: vec![],
+            collapse_headers: false,
         }
     }
 }
@@ -200,6 +224,77 @@ impl Scan {
         self
     }
 
+    /// Set how files are assigned to output files.
+    ///
+    /// Default: [`PackingStrategy::Sequential`]
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::{api::*, PackingStrategy};
+    ///
+    /// Scan::dir("./src")
+    ///     .packing(PackingStrategy::DirectoryAffinity)
+    ///     .run()?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub const fn packing(mut self, strategy: PackingStrategy) -> Self {
+        self.packing = strategy;
+        self
+    }
+
+    /// Prefix each line of file contents with its original line number.
+    ///
+    /// Numbers stay correct after comments and tests are stripped and when
+    /// large files are split, so the model can cite source lines.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::api::*;
+    ///
+    /// Scan::dir("./src")
+    ///     .line_numbers()
+    ///     .bug_analysis()
+    ///     .run()?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub const fn line_numbers(mut self) -> Self {
+        self.line_numbers = true;
+        self
+    }
+
+    /// Reuse results from the previous run for unchanged files.
+    ///
+    /// The cache lives in `.llm-utl-cache/` in the output directory. Output
+    /// files whose content is unchanged are not rewritten.
+    pub const fn cache(mut self) -> Self {
+        self.cache = true;
+        self
+    }
+
+    /// Count tokens exactly with a BPE vocabulary file.
+    ///
+    /// Accepts a tiktoken rank file (e.g. `cl100k_base.tiktoken`) or a
+    /// Hugging Face `tokenizer.json`. Default: enhanced heuristic estimation.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::api::*;
+    ///
+    /// Scan::dir("./src")
+    ///     .bpe_tokenizer("./cl100k_base.tiktoken")
+    ///     .run()?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub fn bpe_tokenizer(mut self, vocab_path: impl Into<PathBuf>) -> Self {
+        self.tokenizer = TokenizerKind::Bpe {
+            vocab_path: vocab_path.into(),
+        };
+        self
+    }
+
     /// Use a preset configuration for common tasks.
     ///
     /// Presets override filter settings with optimized defaults for specific use cases.
@@ -222,6 +317,38 @@ impl Scan {
     /// ```
     pub fn preset(mut self, preset: Preset) -> Self {
         self.preset = Some(preset.into());
+        self.preset_id = None;
+        self
+    }
+
+    /// Use a preset by id, including user-defined presets.
+    ///
+    /// The id is looked up among the built-in presets, presets in
+    /// `~/.config/llm-utl/presets/` and `<dir>/.llm-utl/presets/`, and files
+    /// added with `.preset_file()`. Unknown ids fail when the scan runs.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::api::*;
+    ///
+    /// Scan::dir("./src")
+    ///     .preset_file("./team-presets.toml")
+    ///     .preset_by_id("my-team-review")
+    ///     .run()?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub fn preset_by_id(mut self, id: impl Into<String>) -> Self {
+        self.preset_id = Some(id.into());
+        self.preset = None;
+        self
+    }
+
+    /// Load additional presets from a TOML or JSON file.
+    ///
+    /// Presets from the file override built-in presets with the same id.
+    pub fn preset_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.preset_files.push(path.into());
         self
     }
 
@@ -368,6 +495,37 @@ impl Scan {
         self
     }
 
+    /// Add function or macro names whose calls are removed as debug prints.
+    ///
+    /// A name starting with `.` matches a method call on any receiver.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::api::*;
+    ///
+    /// Scan::dir("./project")
+    ///     .debug_print_names(["log::debug!", "logger.debug"])
+    ///     .run()?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub fn debug_print_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.filters.debug_print_names.extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Shorten license headers to their `SPDX-License-Identifier` line.
+    ///
+    /// By default, license headers are kept whole.
+    pub const fn collapse_headers(mut self) -> Self {
+        self.filters.collapse_headers = true;
+        self
+    }
+
     /// Add directories to exclude from scanning.
     ///
     /// Supports glob patterns (e.g., `**/node_modules`, `target/**`).
@@ -400,7 +558,7 @@ impl Scan {
         I: IntoIterator<Item = S>,
         S: Into<String>,
     {
-        self.excludes.extend(patterns.into_iter().map(Into::into));
+        self.exclude_files.extend(patterns.into_iter().map(Into::into));
         self
     }
 
@@ -417,6 +575,97 @@ impl Scan {
         self
     }
 
+    /// Only scan files changed since the merge base of `rev` and `HEAD`,
+    /// including uncommitted changes.
+    ///
+    /// Uses the local repository only; deleted files are skipped.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::api::*;
+    ///
+    /// Scan::dir(".")
+    ///     .since("main")
+    ///     .code_review()
+    ///     .run()?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub fn since(mut self, rev: impl Into<String>) -> Self {
+        self.selection = Some(GitSelection::Since(rev.into()));
+        self
+    }
+
+    /// Only scan files with staged changes.
+    pub fn staged(mut self) -> Self {
+        self.selection = Some(GitSelection::Staged);
+        self
+    }
+
+    /// Only scan files with staged or unstaged changes, plus untracked files.
+    pub fn uncommitted(mut self) -> Self {
+        self.selection = Some(GitSelection::Uncommitted);
+        self
+    }
+
+    /// Only scan the files listed in `path`, one per line.
+    ///
+    /// Relative paths are resolved against the scan directory.
+    pub fn files_from(mut self, path: impl Into<PathBuf>) -> Self {
+        self.selection = Some(GitSelection::FilesFrom(path.into()));
+        self
+    }
+
+    /// Read the files at `rev` from the local repository instead of the
+    /// working tree, without checking it out.
+    ///
+    /// Hidden files and files matched by the `.gitignore` files of that
+    /// revision are skipped. Cannot be combined with git selections or diffs.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::api::*;
+    ///
+    /// Scan::dir(".")
+    ///     .git_rev("v1.0.0")
+    ///     .documentation()
+    ///     .run()?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub fn git_rev(mut self, rev: impl Into<String>) -> Self {
+        self.git_rev = Some(rev.into());
+        self
+    }
+
+    /// Render each file as a unified diff against the merge base of `rev`
+    /// and `HEAD`.
+    ///
+    /// Only files that changed since `rev` are included.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::api::*;
+    ///
+    /// Scan::dir(".")
+    ///     .diff_against("main")
+    ///     .bug_analysis()
+    ///     .run()?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub fn diff_against(mut self, rev: impl Into<String>) -> Self {
+        self.content_mode = Some(ContentMode::Diff);
+        self.diff_base = Some(rev.into());
+        self
+    }
+
+    /// Render whole files even when the preset defaults to diffs.
+    pub const fn full_files(mut self) -> Self {
+        self.content_mode = Some(ContentMode::Full);
+        self
+    }
+
     /// Execute the scan and return statistics.
     ///
     /// This is a terminal operation that consumes the builder.
@@ -447,31 +696,104 @@ impl Scan {
         Pipeline::new(config)?.run()
     }
 
+    /// Run the scan, then run it again whenever watched files change.
+    ///
+    /// `on_run` receives the statistics of every run and returns whether to
+    /// keep watching. See [`Watcher`] for details.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the configuration is invalid or the pipeline
+    /// cannot be created.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::api::*;
+    ///
+    /// Scan::dir("./src").watch(|stats| {
+    ///     println!("Rewrote {:?}", stats.changed_chunks);
+    ///     true
+    /// })?;
+    /// # Ok::<(), llm_utl::Error>(())
+    /// ```
+    pub fn watch(self, on_run: impl FnMut(&PipelineStats) -> bool) -> Result<()> {
+        let config = self.build_config()?;
+        Watcher::new(config)?.run(on_run)
+    }
+
+    /// Resolves the selected preset, with presets discovered in `user_dir`
+    /// and the project, then the preset files.
+    fn resolve_preset(&self, user_dir: Option<&Path>) -> Result<Option<LLMPreset>> {
+        // Built-in presets go through the registry too, so project files can override them
+        let Some(id) = self
+            .preset_id
+            .clone()
+            .or_else(|| self.preset.map(|kind| kind.id().to_string()))
+        else {
+            return Ok(None);
+        };
+
+        let mut registry = PresetRegistry::discover_in(&self.dir, user_dir)?;
+        for file in &self.preset_files {
+            registry.load_file(file)?;
+        }
+        registry.resolve(&id).map(Some)
+    }
+
     fn build_config(self) -> Result<Config> {
+        let preset = self.resolve_preset(user_presets_dir().as_deref())?;
+
+        let source = match &self.git_rev {
+            Some(rev) => Some(GitRevSource::new(&self.dir, rev)?),
+            None => None,
+        };
+
         let mut builder = Config::builder()
             .root_dir(self.dir)
             .output_dir(self.output)
             .format(self.format)
             .max_tokens(self.max_tokens)
             .overlap_tokens(self.overlap)
-            .tokenizer(TokenizerKind::Enhanced)
+            .tokenizer(self.tokenizer)
+            .packing(self.packing)
+            .line_numbers(self.line_numbers)
+            .cache(self.cache)
             .filter_config(FilterConfig {
                 remove_tests: matches!(self.filters.tests, FilterMode::Remove),
                 remove_doc_comments: matches!(self.filters.doc_comments, FilterMode::Remove),
                 remove_comments: matches!(self.filters.comments, FilterMode::Remove),
                 remove_blank_lines: true,
                 preserve_headers: true,
+                collapse_headers: self.filters.collapse_headers,
                 remove_debug_prints: matches!(self.filters.debug_prints, FilterMode::Remove),
+                debug_print_names: self.filters.debug_print_names,
             })
             .file_filter_config(FileFilterConfig::default()
                 .allow_only(self.allow_files)
                 .exclude_files(self.exclude_files)
                 .exclude_directories(self.excludes));
 
-        if let Some(preset) = self.preset {
+        if let Some(preset) = preset {
             builder = builder.preset(preset);
         }
 
+        if let Some(selection) = self.selection {
+            builder = builder.git_selection(selection);
+        }
+
+        if let Some(source) = source {
+            builder = builder.source(source);
+        }
+
+        if let Some(mode) = self.content_mode {
+            builder = builder.content_mode(mode);
+        }
+
+        if let Some(base) = self.diff_base {
+            builder = builder.diff_base(base);
+        }
+
         // Add template configuration
         if let Some(template_path) = self.template_path {
             builder = builder.template_path(template_path);
@@ -721,6 +1043,43 @@ mod tests {
         assert_eq!(scan.preset, Some(PresetKind::Documentation));
     }
 
+    #[test]
+    fn preset_by_id_uses_preset_files() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let preset_file = temp.path().join("team.toml");
+        std::fs::write(
+            &preset_file,
+            "id = \"team-review\"\nname = \"Team Review\"\nuser_prompt_template = \"Review {file_count} files\"\n",
+        )
+        .unwrap();
+
+        let user = assert_fs::TempDir::new().unwrap();
+
+        let preset = Scan::dir(temp.path())
+            .preset_file(&preset_file)
+            .preset_by_id("team-review")
+            .resolve_preset(Some(user.path()))
+            .unwrap();
+        assert_eq!(preset.unwrap().name, "Team Review");
+
+        let err = Scan::dir(temp.path())
+            .preset_by_id("missing")
+            .resolve_preset(Some(user.path()))
+            .unwrap_err();
+        assert!(err.to_string().contains("code-review"));
+    }
+
+    #[test]
+    fn bpe_tokenizer_sets_vocabulary() {
+        let scan = Scan::dir("./src").bpe_tokenizer("./vocab.tiktoken");
+        assert_eq!(
+            scan.tokenizer,
+            TokenizerKind::Bpe {
+                vocab_path: PathBuf::from("./vocab.tiktoken")
+            }
+        );
+    }
+
     #[test]
     fn exclude_patterns_are_additive() {
         let scan = Scan::dir("./src")
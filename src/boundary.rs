@@ -0,0 +1,259 @@
+//! Split points for oversized files.
+//!
+//! When a file has to be split, cutting between two top-level items keeps
+//! each function, impl block or class whole. Items are recognized with a few
+//! per-language heuristics on unindented lines, for Rust, Python,
+//! JavaScript/TypeScript, Go, Java/Kotlin, C/C++, C#, Swift, Scala, PHP,
+//! Ruby, Lua, shell scripts and SQL.
+
+use std::path::Path;
+
+/// How good a place is for splitting, from worst to best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Boundary {
+    /// Any line break
+    Line,
+    /// After a blank line
+    Blank,
+    /// Before a top-level item, including its attributes and doc comments
+    Item,
+}
+
+/// Languages with item detection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    Go,
+    Java,
+    C,
+    CSharp,
+    Swift,
+    Scala,
+    Php,
+    Ruby,
+    Lua,
+    Shell,
+    Sql,
+    /// A unified diff, split between hunks
+    Diff,
+    Other,
+}
+
+impl Language {
+    fn detect(path: &Path, is_diff: bool) -> Self {
+        if is_diff {
+            return Self::Diff;
+        }
+        match path.extension().and_then(|e| e.to_str()).unwrap_or("") {
+            "rs" => Self::Rust,
+            "py" => Self::Python,
+            "js" | "ts" | "jsx" | "tsx" => Self::JavaScript,
+            "go" => Self::Go,
+            "java" | "kt" | "kts" => Self::Java,
+            "c" | "cpp" | "cc" | "h" | "hpp" => Self::C,
+            "cs" => Self::CSharp,
+            "swift" => Self::Swift,
+            "scala" => Self::Scala,
+            "php" => Self::Php,
+            "rb" => Self::Ruby,
+            "lua" => Self::Lua,
+            "sh" | "bash" | "zsh" | "bats" => Self::Shell,
+            "sql" => Self::Sql,
+            _ => Self::Other,
+        }
+    }
+
+    /// Prefixes of unindented lines that start an item.
+    const fn item_starts(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &[
+                "fn ", "pub ", "pub(", "impl ", "impl<", "struct ", "enum ", "trait ", "mod ",
+                "const ", "static ", "type ", "unsafe ", "async ", "extern ", "union ",
+                "macro_rules!",
+            ],
+            Self::Python => &["def ", "async def ", "class "],
+            Self::JavaScript => &[
+                "function ", "async function ", "class ", "abstract class ", "export ", "const ",
+                "let ", "var ", "interface ", "type ", "enum ", "declare ",
+            ],
+            Self::Go => &["func ", "type ", "var ", "const "],
+            Self::Java => &[
+                "public ", "private ", "protected ", "internal ", "class ", "interface ", "enum ",
+                "record ", "abstract ", "final ", "sealed ", "open ", "data class ", "value class ",
+                "object ", "fun ",
+            ],
+            Self::CSharp => &[
+                "public ", "private ", "protected ", "internal ", "class ", "interface ", "enum ",
+                "record ", "struct ", "abstract ", "sealed ", "static ", "partial ", "namespace ",
+            ],
+            Self::Swift => &[
+                "func ", "class ", "struct ", "enum ", "protocol ", "extension ", "actor ",
+                "public ", "private ", "fileprivate ", "internal ", "open ", "final ", "let ",
+                "var ", "typealias ",
+            ],
+            Self::Scala => &[
+                "def ", "class ", "object ", "trait ", "case ", "abstract ", "sealed ", "final ",
+                "implicit ", "private ", "protected ", "val ", "var ", "type ", "enum ", "given ",
+                "extension ",
+            ],
+            Self::Php => &[
+                "function ", "class ", "abstract ", "final ", "interface ", "trait ", "enum ",
+                "readonly ",
+            ],
+            Self::Ruby => &["def ", "class ", "module "],
+            Self::Lua => &["function ", "local function ", "local "],
+            Self::Shell => &["function "],
+            Self::Sql => &[
+                "CREATE ", "ALTER ", "DROP ", "INSERT ", "UPDATE ", "DELETE ", "SELECT ", "WITH ",
+                "create ", "alter ", "drop ", "insert ", "update ", "delete ", "select ", "with ",
+            ],
+            Self::Diff => &["@@ "],
+            Self::C | Self::Other => &[],
+        }
+    }
+
+    /// Prefixes of lines that belong to the item below them.
+    const fn attached(self) -> &'static [&'static str] {
+        match self {
+            Self::Rust => &["#[", "//", "/*", " *", "*/"],
+            Self::Python => &["@", "#"],
+            Self::JavaScript | Self::Java | Self::Swift | Self::Scala => {
+                &["@", "//", "/*", " *", "*/"]
+            }
+            Self::CSharp => &["[", "//", "/*", " *", "*/"],
+            Self::Php => &["#[", "#", "//", "/*", " *", "*/"],
+            Self::Go | Self::C => &["//", "/*", " *", "*/"],
+            Self::Ruby | Self::Shell => &["#"],
+            Self::Lua => &["--"],
+            Self::Sql => &["--", "/*", " *", "*/"],
+            Self::Diff | Self::Other => &[],
+        }
+    }
+
+    /// Whether a top-level block closes with an unindented `}`.
+    const fn uses_braces(self) -> bool {
+        matches!(
+            self,
+            Self::Rust
+                | Self::JavaScript
+                | Self::Go
+                | Self::Java
+                | Self::C
+                | Self::CSharp
+                | Self::Swift
+                | Self::Scala
+                | Self::Php
+                | Self::Shell
+        )
+    }
+}
+
+/// Returns the split quality before each line of a file.
+///
+/// Entry `i` rates a split between `lines[i - 1]` and `lines[i]`; the result
+/// has `lines.len() + 1` entries, and the start and end of the file are
+/// [`Boundary::Item`]. `is_diff` marks content rendered as a unified diff,
+/// which is split between hunks instead.
+pub(crate) fn boundaries(lines: &[&str], path: &Path, is_diff: bool) -> Vec<Boundary> {
+    let language = Language::detect(path, is_diff);
+    let mut result = vec![Boundary::Line; lines.len() + 1];
+    result[0] = Boundary::Item;
+    result[lines.len()] = Boundary::Item;
+
+    let mut after_block = false;
+    for (i, line) in lines.iter().enumerate() {
+        if line.trim().is_empty() {
+            result[i + 1] = result[i + 1].max(Boundary::Blank);
+            continue;
+        }
+
+        let unindented = !line.starts_with(char::is_whitespace);
+        let starts_item = unindented
+            && (language.item_starts().iter().any(|p| line.starts_with(p))
+                || (after_block && !line.starts_with('}')));
+        if starts_item {
+            // Keep attributes, decorators and doc comments with their item
+            let mut start = i;
+            while start > 0 && language.attached().iter().any(|p| lines[start - 1].starts_with(p)) {
+                start -= 1;
+            }
+            result[start] = Boundary::Item;
+        }
+
+        after_block = language.uses_braces() && unindented && line.starts_with('}');
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item_lines(content: &str, path: &str) -> Vec<usize> {
+        let lines: Vec<&str> = content.lines().collect();
+        boundaries(&lines, Path::new(path), false)
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| **b == Boundary::Item)
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn test_rust_items_keep_attributes() {
+        let content = "use std::fmt;\n/// Docs\n#[derive(Debug)]\npub struct A;\nimpl A {\n    fn a() {}\n}\nfn b() {}";
+        assert_eq!(item_lines(content, "lib.rs"), [0, 1, 4, 7, 8]);
+    }
+
+    #[test]
+    fn test_python_decorators_and_blank_lines() {
+        let content = "import os\n\n@cache\ndef a():\n    pass\n\n    return 1\nclass B:\n    pass";
+        let lines: Vec<&str> = content.lines().collect();
+        let result = boundaries(&lines, Path::new("a.py"), false);
+
+        assert_eq!(result[2], Boundary::Item);
+        assert_eq!(result[6], Boundary::Blank);
+        assert_eq!(result[7], Boundary::Item);
+        assert_eq!(result[5], Boundary::Line);
+    }
+
+    #[test]
+    fn test_c_items_after_closing_brace() {
+        let content = "int a(void)\n{\n    return 1;\n}\n/* b */\nstatic int b(void)\n{\n}";
+        assert_eq!(item_lines(content, "a.c"), [0, 4, 8]);
+    }
+
+    #[test]
+    fn test_items_in_more_languages() {
+        let csharp = "using System;\n[Serializable]\npublic class A\n{\n}\nnamespace B;";
+        assert_eq!(item_lines(csharp, "A.cs"), [0, 1, 5, 6]);
+
+        let kotlin = "package a\n@JvmInline\nvalue class Id(val v: Int)\n\nfun main() {\n}";
+        assert_eq!(item_lines(kotlin, "a.kts"), [0, 1, 4, 6]);
+
+        let swift = "import UIKit\n@MainActor\nfinal class View {\n}\nextension View {}";
+        assert_eq!(item_lines(swift, "View.swift"), [0, 1, 4, 5]);
+
+        let ruby = "require 'x'\n# Docs\nclass A\n  def a; end\nend\nmodule B; end";
+        assert_eq!(item_lines(ruby, "a.rb"), [0, 1, 5, 6]);
+
+        let sql = "-- Users\nCREATE TABLE users (\n  id INT\n);\ninsert into users values (1);";
+        assert_eq!(item_lines(sql, "schema.sql"), [0, 4, 5]);
+
+        let lua = "local M = {}\n--- Adds\nfunction M.add(a, b)\n  return a + b\nend\nreturn M";
+        assert_eq!(item_lines(lua, "m.lua"), [0, 1, 6]);
+    }
+
+    #[test]
+    fn test_diff_hunks_and_unknown_languages() {
+        let lines = ["--- a/a.rs", "+++ b/a.rs", "@@ -1 +1 @@", "-a", "+b", "@@ -9 +9 @@", " c"];
+        let result = boundaries(&lines, Path::new("a.rs"), true);
+        assert_eq!(result[2], Boundary::Item);
+        assert_eq!(result[5], Boundary::Item);
+
+        assert_eq!(item_lines("fn a() {}\nfn b() {}", "notes.txt"), [0, 2]);
+    }
+}
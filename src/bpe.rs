@@ -0,0 +1,370 @@
+//! Byte-pair encoding tokenizer backed by a vocabulary file.
+//!
+//! Supports two on-disk formats:
+//! - tiktoken rank files (`<base64 token> <rank>` per line), as shipped for
+//!   `cl100k_base` and `o200k_base`
+//! - Hugging Face `tokenizer.json` files containing a byte-level BPE model
+
+use crate::error::{Error, Result};
+use crate::token::TokenEstimator;
+use base64::Engine;
+use fancy_regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Pre-tokenization pattern of `cl100k_base`.
+const CL100K_PATTERN: &str = r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+";
+
+/// Pre-tokenization pattern of `o200k_base`.
+const O200K_PATTERN: &str = concat!(
+    r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+",
+);
+
+/// Pre-tokenization pattern of GPT-2 style byte-level models.
+const GPT2_PATTERN: &str =
+    r"'s|'t|'re|'ve|'m|'ll|'d| ?\p{L}+| ?\p{N}+| ?[^\s\p{L}\p{N}]+|\s+(?!\S)|\s+";
+
+/// tiktoken vocabularies at least this large use the o200k split pattern.
+const O200K_MIN_VOCAB: usize = 150_000;
+
+/// Pieces longer than this are merged in windows to bound the quadratic merge loop.
+const MAX_PIECE_BYTES: usize = 1024;
+
+/// Exact token counter using byte-pair encoding.
+///
+/// Merge priorities are stored as ranks keyed by the merged byte sequence,
+/// following the tiktoken algorithm: the adjacent pair whose concatenation
+/// has the lowest rank is merged first.
+pub(crate) struct BpeTokenizer {
+    ranks: HashMap<Vec<u8>, u32>,
+    pattern: Regex,
+}
+
+impl BpeTokenizer {
+    /// Loads a vocabulary from a tiktoken rank file or a Hugging Face `tokenizer.json`.
+    ///
+    /// The format is detected from the content: JSON documents are treated as
+    /// Hugging Face tokenizers, everything else as tiktoken rank files.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a supported vocabulary.
+    pub(crate) fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+
+        let parsed = if content.trim_start().starts_with('{') {
+            Self::from_hf_json(&content)
+        } else {
+            Self::from_tiktoken(&content)
+        };
+
+        let tokenizer = parsed.map_err(|message| Error::tokenizer(path, message))?;
+
+        tracing::debug!(
+            "Loaded BPE vocabulary with {} entries from {}",
+            tokenizer.ranks.len(),
+            path.display()
+        );
+
+        Ok(tokenizer)
+    }
+
+    /// Parses a tiktoken rank file.
+    fn from_tiktoken(content: &str) -> std::result::Result<Self, String> {
+        let engine = base64::engine::general_purpose::STANDARD;
+        let mut ranks = HashMap::new();
+
+        for (line_no, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() {
+                continue;
+            }
+
+            let (token, rank) = line
+                .split_once(' ')
+                .ok_or_else(|| format!("line {}: expected '<base64> <rank>'", line_no + 1))?;
+
+            let bytes = engine
+                .decode(token)
+                .map_err(|e| format!("line {}: invalid base64: {e}", line_no + 1))?;
+            let rank: u32 = rank
+                .trim()
+                .parse()
+                .map_err(|e| format!("line {}: invalid rank: {e}", line_no + 1))?;
+
+            ranks.insert(bytes, rank);
+        }
+
+        if ranks.is_empty() {
+            return Err("vocabulary is empty".to_string());
+        }
+
+        let pattern = if ranks.len() >= O200K_MIN_VOCAB {
+            O200K_PATTERN
+        } else {
+            CL100K_PATTERN
+        };
+
+        Ok(Self {
+            ranks,
+            pattern: compile_pattern(pattern)?,
+        })
+    }
+
+    /// Parses a Hugging Face `tokenizer.json` with a byte-level BPE model.
+    fn from_hf_json(content: &str) -> std::result::Result<Self, String> {
+        let doc: Value = serde_json::from_str(content).map_err(|e| e.to_string())?;
+        let model = doc.get("model").ok_or("missing 'model' section")?;
+
+        if let Some(kind) = model.get("type").and_then(Value::as_str) {
+            if kind != "BPE" {
+                return Err(format!("unsupported model type '{kind}', expected 'BPE'"));
+            }
+        }
+
+        let vocab = model
+            .get("vocab")
+            .and_then(Value::as_object)
+            .ok_or("missing 'model.vocab' object")?;
+        let merges = model
+            .get("merges")
+            .and_then(Value::as_array)
+            .ok_or("missing 'model.merges' array")?;
+
+        let decoder = byte_level_decoder();
+        let decode = |token: &str| -> std::result::Result<Vec<u8>, String> {
+            token
+                .chars()
+                .map(|c| decoder.get(&c).copied())
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(|| format!("token '{token}' is not byte-level encoded"))
+        };
+
+        let mut ranks = HashMap::with_capacity(vocab.len());
+
+        for (index, merge) in merges.iter().enumerate() {
+            let (left, right) = match merge {
+                Value::String(s) => s
+                    .split_once(' ')
+                    .ok_or_else(|| format!("merge {index}: expected 'left right'"))?,
+                Value::Array(pair) => match (
+                    pair.first().and_then(Value::as_str),
+                    pair.get(1).and_then(Value::as_str),
+                ) {
+                    (Some(left), Some(right)) => (left, right),
+                    _ => return Err(format!("merge {index}: expected two strings")),
+                },
+                _ => return Err(format!("merge {index}: unsupported format")),
+            };
+
+            let mut token = decode(left)?;
+            token.extend(decode(right)?);
+
+            let rank = u32::try_from(index).map_err(|e| e.to_string())?;
+            ranks.entry(token).or_insert(rank);
+        }
+
+        // Base symbols rank after every merge so they never win a merge round.
+        let base_offset = u32::try_from(merges.len()).map_err(|e| e.to_string())?;
+        for (token, id) in vocab {
+            let id = id
+                .as_u64()
+                .and_then(|id| u32::try_from(id).ok())
+                .ok_or_else(|| format!("token '{token}' has an invalid id"))?;
+            ranks
+                .entry(decode(token)?)
+                .or_insert_with(|| base_offset.saturating_add(id));
+        }
+
+        if ranks.is_empty() {
+            return Err("vocabulary is empty".to_string());
+        }
+
+        let pattern = doc
+            .get("pre_tokenizer")
+            .and_then(find_split_regex)
+            .unwrap_or(GPT2_PATTERN);
+
+        Ok(Self {
+            ranks,
+            pattern: compile_pattern(pattern)?,
+        })
+    }
+
+    /// Counts the tokens of a single pre-tokenized piece.
+    fn count_piece(&self, piece: &[u8]) -> usize {
+        if piece.is_empty() {
+            return 0;
+        }
+
+        if self.ranks.contains_key(piece) {
+            return 1;
+        }
+
+        if piece.len() > MAX_PIECE_BYTES {
+            return piece
+                .chunks(MAX_PIECE_BYTES)
+                .map(|window| self.count_piece(window))
+                .sum();
+        }
+
+        // Boundaries between the current parts of the piece
+        let mut bounds: Vec<usize> = (0..=piece.len()).collect();
+
+        while bounds.len() > 2 {
+            let best = (0..bounds.len() - 2)
+                .filter_map(|i| {
+                    self.ranks
+                        .get(&piece[bounds[i]..bounds[i + 2]])
+                        .map(|&rank| (rank, i))
+                })
+                .min();
+
+            match best {
+                Some((_, i)) => {
+                    bounds.remove(i + 1);
+                }
+                None => break,
+            }
+        }
+
+        bounds.len() - 1
+    }
+}
+
+impl TokenEstimator for BpeTokenizer {
+    fn estimate(&self, text: &str) -> usize {
+        let mut count = 0;
+
+        for piece in self.pattern.find_iter(text) {
+            match piece {
+                Ok(piece) => count += self.count_piece(piece.as_str().as_bytes()),
+                // Backtracking limit hit: one token per byte is a safe upper bound
+                Err(_) => return text.len(),
+            }
+        }
+
+        count
+    }
+}
+
+/// Compiles a pre-tokenization pattern.
+fn compile_pattern(pattern: &str) -> std::result::Result<Regex, String> {
+    Regex::new(pattern).map_err(|e| format!("invalid pre-tokenizer pattern: {e}"))
+}
+
+/// Finds the first `Split` regex in a Hugging Face pre-tokenizer definition.
+fn find_split_regex(value: &Value) -> Option<&str> {
+    match value {
+        Value::Object(map) => map
+            .get("pattern")
+            .and_then(|p| p.get("Regex"))
+            .and_then(Value::as_str)
+            .or_else(|| map.values().find_map(find_split_regex)),
+        Value::Array(items) => items.iter().find_map(find_split_regex),
+        _ => None,
+    }
+}
+
+/// Builds the inverse of the GPT-2 `bytes_to_unicode` table.
+fn byte_level_decoder() -> HashMap<char, u8> {
+    let printable = |b: u8| matches!(b, b'!'..=b'~' | 0xA1..=0xAC | 0xAE..=0xFF);
+
+    let mut decoder = HashMap::with_capacity(256);
+    let mut shifted = 0u32;
+
+    for byte in 0..=u8::MAX {
+        let code = if printable(byte) {
+            u32::from(byte)
+        } else {
+            shifted += 1;
+            255 + shifted
+        };
+
+        if let Some(c) = char::from_u32(code) {
+            decoder.insert(c, byte);
+        }
+    }
+
+    decoder
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+    use std::path::PathBuf;
+
+    fn fixture(name: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/fixtures")
+            .join(name)
+    }
+
+    #[test]
+    fn test_tiktoken_fixture_counts_exact_tokens() {
+        let tokenizer = BpeTokenizer::from_file(&fixture("tiny.tiktoken")).unwrap();
+
+        // "fn" + " main" + "()" + " {" + "}"
+        assert_eq!(tokenizer.estimate("fn main() {}"), 5);
+        assert_eq!(tokenizer.estimate(""), 0);
+    }
+
+    #[test]
+    fn test_tiktoken_unmerged_bytes() {
+        let tokenizer = BpeTokenizer::from_file(&fixture("tiny.tiktoken")).unwrap();
+
+        // No merges apply, so every byte is a token
+        assert_eq!(tokenizer.estimate("xyz"), 3);
+    }
+
+    #[test]
+    fn test_hf_fixture_counts_exact_tokens() {
+        let tokenizer = BpeTokenizer::from_file(&fixture("tiny_tokenizer.json")).unwrap();
+
+        // "fn" + " main" + "(" ")" + " " "{" "}"
+        assert_eq!(tokenizer.estimate("fn main() {}"), 7);
+    }
+
+    #[test]
+    fn test_long_piece_is_bounded() {
+        let tokenizer = BpeTokenizer::from_file(&fixture("tiny.tiktoken")).unwrap();
+        let text = "-".repeat(5000);
+
+        assert_eq!(tokenizer.estimate(&text), 5000);
+    }
+
+    #[test]
+    fn test_invalid_vocabulary() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let file = temp.child("broken.tiktoken");
+        file.write_str("not-a-rank-line\n").unwrap();
+
+        let result = BpeTokenizer::from_file(file.path());
+        assert!(result.is_err());
+        assert!(result.err().unwrap().to_string().contains("line 1"));
+    }
+
+    #[test]
+    fn test_unsupported_hf_model() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let file = temp.child("tokenizer.json");
+        file.write_str(r#"{"model": {"type": "WordPiece", "vocab": {}}}"#)
+            .unwrap();
+
+        let result = BpeTokenizer::from_file(file.path());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_byte_level_decoder_is_complete() {
+        let decoder = byte_level_decoder();
+        assert_eq!(decoder.len(), 256);
+        assert_eq!(decoder.get(&'Ġ'), Some(&b' '));
+        assert_eq!(decoder.get(&'a'), Some(&b'a'));
+    }
+}
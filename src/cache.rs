@@ -0,0 +1,369 @@
+//! On-disk cache for incremental runs.
+//!
+//! The cache lives in `.llm-utl-cache/` inside the output directory and has
+//! two parts:
+//!
+//! - [`FileCache`] keeps the filtered content and token count of every text
+//!   file, keyed by relative path. An entry is reused when the file's size
+//!   and modification time are unchanged, or when its content hash is, and
+//!   only while the filter and tokenizer settings stay the same.
+//! - [`ChunkCache`] keeps a hash of every rendered output file, so the writer
+//!   can leave unchanged output files alone.
+
+use crate::{
+    config::Config,
+    error::{Error, Result},
+    file::FileData,
+    token::TokenizerKind,
+};
+use serde::{Deserialize, Serialize};
+use siphasher::sip128::{Hasher128, SipHasher13};
+use std::collections::HashMap;
+use std::fs::{self, Metadata};
+use std::hash::Hasher;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+use tracing::{debug, warn};
+
+/// Directory holding the cache, inside the output directory.
+pub(crate) const CACHE_DIR: &str = ".llm-utl-cache";
+
+const FILES_CACHE: &str = "files.json";
+const CHUNKS_CACHE: &str = "chunks.json";
+
+/// Returns a stable 128-bit hash of `content` as a hex string.
+pub(crate) fn content_hash(content: &str) -> String {
+    bytes_hash(content.as_bytes())
+}
+
+fn bytes_hash(bytes: &[u8]) -> String {
+    let mut hasher = SipHasher13::new();
+    hasher.write(bytes);
+    format!("{:032x}", hasher.finish128().as_u128())
+}
+
+/// Returns a hash of the BPE vocabulary file, so that replacing the file at
+/// the same path invalidates cached token counts.
+fn vocabulary_hash(tokenizer: &TokenizerKind) -> String {
+    match tokenizer {
+        TokenizerKind::Bpe { vocab_path } => fs::read(vocab_path)
+            .map(|bytes| bytes_hash(&bytes))
+            .unwrap_or_default(),
+        TokenizerKind::Simple | TokenizerKind::Enhanced => String::new(),
+    }
+}
+
+/// Cached scan result for one file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedFile {
+    size: u64,
+    modified_ns: u64,
+    hash: String,
+    content: String,
+    source_lines: Option<Vec<usize>>,
+    token_count: usize,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct FilesOnDisk {
+    settings: String,
+    files: HashMap<String, CachedFile>,
+}
+
+/// Filtered contents and token counts from the previous run.
+pub(crate) struct FileCache {
+    path: PathBuf,
+    settings: String,
+    previous: HashMap<String, CachedFile>,
+    current: Mutex<HashMap<String, CachedFile>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl FileCache {
+    /// Loads the file cache for `config`.
+    ///
+    /// Entries written with other filter or tokenizer settings, another BPE
+    /// vocabulary, or by another version, are discarded. An unreadable cache
+    /// is treated as empty.
+    pub(crate) fn load(config: &Config) -> Self {
+        let path = config.output_dir.join(CACHE_DIR).join(FILES_CACHE);
+        let settings = content_hash(&format!(
+            "{} {:?} {:?} {}",
+            env!("CARGO_PKG_VERSION"),
+            config.filter_config,
+            config.tokenizer,
+            vocabulary_hash(&config.tokenizer)
+        ));
+
+        let previous = match read_json::<FilesOnDisk>(&path) {
+            Some(cache) if cache.settings == settings => cache.files,
+            Some(_) => {
+                debug!("Filter or tokenizer settings changed, discarding file cache");
+                HashMap::new()
+            }
+            None => HashMap::new(),
+        };
+        debug!("Loaded {} cached files from {}", previous.len(), path.display());
+
+        Self {
+            path,
+            settings,
+            previous,
+            current: Mutex::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns the cached file data when the file has the same size and
+    /// modification time as in the previous run.
+    pub(crate) fn get_unchanged(
+        &self,
+        relative_path: &str,
+        absolute_path: &Path,
+        metadata: &Metadata,
+    ) -> Option<FileData> {
+        let entry = self.previous.get(relative_path)?;
+        if entry.size != metadata.len() || entry.modified_ns != modified_ns(metadata) {
+            return None;
+        }
+        Some(self.hit(relative_path, absolute_path, entry.clone()))
+    }
+
+    /// Returns the cached file data when `content` hashes the same as in the
+    /// previous run, e.g. after the file was only touched.
+    pub(crate) fn get_same_content(
+        &self,
+        relative_path: &str,
+        absolute_path: &Path,
+        metadata: &Metadata,
+        hash: &str,
+    ) -> Option<FileData> {
+        let entry = self.previous.get(relative_path).filter(|entry| entry.hash == hash)?;
+        let entry = CachedFile {
+            size: metadata.len(),
+            modified_ns: modified_ns(metadata),
+            ..entry.clone()
+        };
+        Some(self.hit(relative_path, absolute_path, entry))
+    }
+
+    /// Records a freshly processed file.
+    pub(crate) fn insert(&self, file: &FileData, metadata: &Metadata, hash: String) {
+        let Some(content) = file.content_str() else {
+            return;
+        };
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        self.current.lock().unwrap().insert(
+            file.relative_path.clone(),
+            CachedFile {
+                size: metadata.len(),
+                modified_ns: modified_ns(metadata),
+                hash,
+                content: content.to_string(),
+                source_lines: file.source_lines.clone(),
+                token_count: file.token_count,
+            },
+        );
+    }
+
+    /// Returns the number of files reused from the cache.
+    pub(crate) fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of files that had to be processed.
+    pub(crate) fn misses(&self) -> usize {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Writes the files seen in this run back to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache file cannot be written.
+    pub(crate) fn save(&self) -> Result<()> {
+        let cache = FilesOnDisk {
+            settings: self.settings.clone(),
+            files: self.current.lock().unwrap().clone(),
+        };
+        write_json(&self.path, &cache)
+    }
+
+    fn hit(&self, relative_path: &str, absolute_path: &Path, entry: CachedFile) -> FileData {
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        let file = FileData {
+            source_lines: entry.source_lines.clone(),
+            ..FileData::new_text(
+                absolute_path.to_path_buf(),
+                relative_path.to_string(),
+                entry.content.clone(),
+                entry.token_count,
+            )
+        };
+        self.current.lock().unwrap().insert(relative_path.to_string(), entry);
+        file
+    }
+}
+
+/// Hashes of the output files written by the previous run.
+pub(crate) struct ChunkCache {
+    path: PathBuf,
+    previous: HashMap<String, String>,
+    current: Mutex<HashMap<String, String>>,
+}
+
+impl ChunkCache {
+    /// Loads the chunk cache from the output directory.
+    pub(crate) fn load(output_dir: &Path) -> Self {
+        let path = output_dir.join(CACHE_DIR).join(CHUNKS_CACHE);
+        let previous = read_json(&path).unwrap_or_default();
+
+        Self {
+            path,
+            previous,
+            current: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Records the hash of an output file and returns whether it matches
+    /// the previous run.
+    pub(crate) fn is_unchanged(&self, filename: &str, hash: String) -> bool {
+        let unchanged = self.previous.get(filename) == Some(&hash);
+        self.current.lock().unwrap().insert(filename.to_string(), hash);
+        unchanged
+    }
+
+    /// Writes the hashes recorded in this run back to disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the cache file cannot be written.
+    pub(crate) fn save(&self) -> Result<()> {
+        write_json(&self.path, &*self.current.lock().unwrap())
+    }
+}
+
+fn modified_ns(metadata: &Metadata) -> u64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| u64::try_from(elapsed.as_nanos()).unwrap_or(u64::MAX))
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Option<T> {
+    let json = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&json)
+        .map_err(|e| warn!("Ignoring unreadable cache {}: {}", path.display(), e))
+        .ok()
+}
+
+fn write_json(path: &Path, value: &impl Serialize) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| Error::io(dir, e))?;
+    }
+    let file = fs::File::create(path).map_err(|e| Error::io(path, e))?;
+    serde_json::to_writer(file, value).map_err(Error::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    fn config(root: &Path) -> Config {
+        Config::builder().root_dir(root).output_dir(root.join("out")).build().unwrap()
+    }
+
+    #[test]
+    fn test_content_hash_is_stable() {
+        assert_eq!(content_hash("fn main() {}"), content_hash("fn main() {}"));
+        assert_ne!(content_hash("fn main() {}"), content_hash("fn main() { }"));
+        assert_eq!(content_hash("").len(), 32);
+    }
+
+    #[test]
+    fn test_file_cache_round_trip() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let source = temp.child("lib.rs");
+        source.write_str("// comment\nfn lib() {}").unwrap();
+        let metadata = fs::metadata(source.path()).unwrap();
+        let file = FileData {
+            source_lines: Some(vec![2]),
+            ..FileData::new_text(source.to_path_buf(), "lib.rs".to_string(), "fn lib() {}".to_string(), 4)
+        };
+
+        let cache = FileCache::load(&config(temp.path()));
+        assert!(cache.get_unchanged("lib.rs", source.path(), &metadata).is_none());
+        cache.insert(&file, &metadata, content_hash("// comment\nfn lib() {}"));
+        cache.save().unwrap();
+
+        let cache = FileCache::load(&config(temp.path()));
+        let cached = cache.get_unchanged("lib.rs", source.path(), &metadata).unwrap();
+        assert_eq!(cached.content_str(), Some("fn lib() {}"));
+        assert_eq!(cached.source_lines, Some(vec![2]));
+        assert_eq!(cached.token_count, 4);
+        assert_eq!((cache.hits(), cache.misses()), (1, 0));
+        assert!(cache
+            .get_same_content("lib.rs", source.path(), &metadata, &content_hash("fn other() {}"))
+            .is_none());
+
+        // Other filter settings invalidate the cache
+        let keep_tests = Config::builder()
+            .root_dir(temp.path())
+            .output_dir(temp.path().join("out"))
+            .filter_config(crate::FilterConfig { remove_tests: false, ..Default::default() })
+            .build()
+            .unwrap();
+        let cache = FileCache::load(&keep_tests);
+        assert!(cache.get_unchanged("lib.rs", source.path(), &metadata).is_none());
+    }
+
+    #[test]
+    fn test_file_cache_tracks_vocabulary_content() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let vocab = temp.child("vocab.tiktoken");
+        vocab.write_str("YQ== 0\n").unwrap();
+        let source = temp.child("lib.rs");
+        source.write_str("fn lib() {}").unwrap();
+        let metadata = fs::metadata(source.path()).unwrap();
+
+        let bpe_config = || {
+            Config::builder()
+                .root_dir(temp.path())
+                .output_dir(temp.path().join("out"))
+                .tokenizer(TokenizerKind::Bpe {
+                    vocab_path: vocab.to_path_buf(),
+                })
+                .build()
+                .unwrap()
+        };
+
+        let cache = FileCache::load(&bpe_config());
+        let file = FileData::new_text(source.to_path_buf(), "lib.rs".to_string(), "fn lib() {}".to_string(), 4);
+        cache.insert(&file, &metadata, content_hash("fn lib() {}"));
+        cache.save().unwrap();
+        assert!(FileCache::load(&bpe_config()).get_unchanged("lib.rs", source.path(), &metadata).is_some());
+
+        // Replacing the vocabulary at the same path invalidates the cache
+        vocab.write_str("Yg== 0\n").unwrap();
+        assert!(FileCache::load(&bpe_config()).get_unchanged("lib.rs", source.path(), &metadata).is_none());
+    }
+
+    #[test]
+    fn test_chunk_cache() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let cache = ChunkCache::load(temp.path());
+        assert!(!cache.is_unchanged("prompt_001.md", content_hash("a")));
+        cache.save().unwrap();
+
+        let cache = ChunkCache::load(temp.path());
+        assert!(cache.is_unchanged("prompt_001.md", content_hash("a")));
+        assert!(!cache.is_unchanged("prompt_002.md", content_hash("a")));
+    }
+}
@@ -1,14 +1,20 @@
+use crate::diff::{ContentMode, DiffContext};
 use crate::error::{Error, Result};
 use crate::filter::{FileFilterConfig, FilterConfig};
-use crate::preset::PresetKind;
+use crate::git::GitSelection;
+use crate::preset::LLMPreset;
+use crate::source::FileSource;
+use crate::splitter::PackingStrategy;
 use crate::token::TokenizerKind;
 use std::collections::HashMap;
 use std::path::PathBuf;
+use std::sync::Arc;
 
-const DEFAULT_MAX_TOKENS: usize = 100_000;
-const DEFAULT_OVERLAP_TOKENS: usize = 1_000;
-const DEFAULT_CHUNK_SAFETY_MARGIN: usize = 2_000;
-const DEFAULT_OUTPUT_PATTERN: &str = "prompt_{index:03}.{ext}";
+pub(crate) const DEFAULT_MAX_TOKENS: usize = 100_000;
+pub(crate) const DEFAULT_OVERLAP_TOKENS: usize = 1_000;
+pub(crate) const DEFAULT_CHUNK_SAFETY_MARGIN: usize = 2_000;
+pub(crate) const DEFAULT_OUTPUT_PATTERN: &str = "prompt_{index:03}.{ext}";
+pub(crate) const DEFAULT_CHUNK_SEPARATOR: &str = "\n\n";
 
 /// Output format for generated prompts.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -62,6 +68,9 @@ pub struct Config {
     /// Root directory to scan for files
     pub root_dir: PathBuf,
 
+    /// Where files are read from instead of walking `root_dir`
+    pub source: Option<Arc<dyn FileSource>>,
+
     /// Output directory for generated prompts
     pub output_dir: PathBuf,
 
@@ -83,17 +92,39 @@ pub struct Config {
     /// Tokenizer implementation to use
     pub tokenizer: TokenizerKind,
 
-    /// Whether to prefer splitting at line boundaries
+    /// Whether oversized files are split between top-level items and at
+    /// blank lines where possible, rather than filling each part to the limit
     pub prefer_line_boundaries: bool,
 
+    /// How files are assigned to chunks
+    pub packing: PackingStrategy,
+
     /// Code filtering configuration
     pub filter_config: FilterConfig,
 
     /// Code filtering configuration
     pub file_filter_config: FileFilterConfig,
 
+    /// Restricts the scan to files selected from git or a file list
+    pub git_selection: Option<GitSelection>,
+
+    /// Full files or diffs; `None` uses the preset's default
+    pub content_mode: Option<ContentMode>,
+
+    /// Base ref for diff mode (defaults to the `--since` ref, then `HEAD`)
+    pub diff_base: Option<String>,
+
+    /// Context around each change in diff mode
+    pub diff_context: DiffContext,
+
     /// LLM preset for specialized output
-    pub preset: Option<PresetKind>,
+    pub preset: Option<LLMPreset>,
+
+    /// Show per-directory file and token totals in the project structure tree
+    pub structure_directory_totals: bool,
+
+    /// Prefix each line of file contents with its line number in the source file
+    pub line_numbers: bool,
 
     /// Dry run mode (no file writes)
     pub dry_run: bool,
@@ -104,6 +135,16 @@ pub struct Config {
     /// Create backups of existing files
     pub backup_existing: bool,
 
+    /// Reuse filtered files and skip rewriting unchanged output files, using
+    /// the cache in the output directory
+    pub cache: bool,
+
+    /// Text written between chunks when rendering to a stream
+    pub chunk_separator: String,
+
+    /// Allow rendering to a stream when the output needs more than one chunk
+    pub allow_multi: bool,
+
     /// Path to external template file
     pub template_path: Option<PathBuf>,
 
@@ -146,18 +187,16 @@ impl Config {
     /// - Output pattern is invalid
     pub fn validate(&self) -> Result<()> {
         // Validate root directory
-        if !self.root_dir.exists() {
-            return Err(Error::config(format!(
-                "Root directory does not exist: {}",
-                self.root_dir.display()
-            )));
-        }
-
-        if !self.root_dir.is_dir() {
-            return Err(Error::config(format!(
-                "Root path is not a directory: {}",
-                self.root_dir.display()
-            )));
+        let root_dir = self
+            .source
+            .as_ref()
+            .map_or(Some(self.root_dir.as_path()), |source| source.local_root());
+        if let Some(root_dir) = root_dir {
+            Self::validate_root_dir(root_dir)?;
+        } else if self.git_selection.is_some() || self.content_mode == Some(ContentMode::Diff) {
+            return Err(Error::config(
+                "Git selections and diff mode need files from a directory on disk",
+            ));
         }
 
         // Validate token limits
@@ -179,6 +218,19 @@ impl Config {
             )));
         }
 
+        // Validate tokenizer vocabulary
+        if let TokenizerKind::Bpe { ref vocab_path } = self.tokenizer {
+            if !vocab_path.is_file() {
+                return Err(Error::config(format!(
+                    "Tokenizer vocabulary file does not exist: {}",
+                    vocab_path.display()
+                )));
+            }
+        }
+
+        // Validate file filter globs
+        self.file_filter_config.validate()?;
+
         // Validate output pattern
         if !self.output_pattern.contains("{index") {
             return Err(Error::invalid_pattern(
@@ -251,6 +303,24 @@ impl Config {
         Ok(())
     }
 
+    fn validate_root_dir(root_dir: &std::path::Path) -> Result<()> {
+        if !root_dir.exists() {
+            return Err(Error::config(format!(
+                "Root directory does not exist: {}",
+                root_dir.display()
+            )));
+        }
+
+        if !root_dir.is_dir() {
+            return Err(Error::config(format!(
+                "Root path is not a directory: {}",
+                root_dir.display()
+            )));
+        }
+
+        Ok(())
+    }
+
     /// Returns the effective chunk size after applying safety margin.
     #[must_use]
     pub const fn effective_chunk_size(&self) -> usize {
@@ -262,6 +332,7 @@ impl Default for Config {
     fn default() -> Self {
         Self {
             root_dir: PathBuf::from("."),
+            source: None,
             output_dir: PathBuf::from("out"),
             output_pattern: DEFAULT_OUTPUT_PATTERN.to_string(),
             format: OutputFormat::Markdown,
@@ -270,12 +341,22 @@ impl Default for Config {
             chunk_safety_margin: DEFAULT_CHUNK_SAFETY_MARGIN,
             tokenizer: TokenizerKind::Simple,
             prefer_line_boundaries: true,
+            packing: PackingStrategy::default(),
             filter_config: FilterConfig::default(),
             file_filter_config: FileFilterConfig::default(),
+            git_selection: None,
+            content_mode: None,
+            diff_base: None,
+            diff_context: DiffContext::default(),
             preset: None,
+            structure_directory_totals: false,
+            line_numbers: false,
             dry_run: false,
             include_binary_files: false,
             backup_existing: true,
+            cache: false,
+            chunk_separator: DEFAULT_CHUNK_SEPARATOR.to_string(),
+            allow_multi: false,
             template_path: None,
             custom_format_name: None,
             custom_extension: None,
@@ -286,8 +367,11 @@ impl Default for Config {
 
 /// Builder for creating a [`Config`].
 #[derive(Debug, Default)]
+// Mirrors the on/off settings of `Config`
+#[allow(clippy::struct_excessive_bools)]
 pub struct ConfigBuilder {
     root_dir: Option<PathBuf>,
+    source: Option<Arc<dyn FileSource>>,
     output_dir: Option<PathBuf>,
     output_pattern: Option<String>,
     format: Option<OutputFormat>,
@@ -296,12 +380,22 @@ pub struct ConfigBuilder {
     chunk_safety_margin: Option<usize>,
     tokenizer: Option<TokenizerKind>,
     prefer_line_boundaries: Option<bool>,
+    packing: Option<PackingStrategy>,
     filter_config: Option<FilterConfig>,
     file_filter_config: Option<FileFilterConfig>,
-    preset: Option<PresetKind>,
+    git_selection: Option<GitSelection>,
+    content_mode: Option<ContentMode>,
+    diff_base: Option<String>,
+    diff_context: Option<DiffContext>,
+    preset: Option<LLMPreset>,
+    structure_directory_totals: bool,
+    line_numbers: bool,
     dry_run: bool,
     include_binary_files: bool,
     backup_existing: Option<bool>,
+    cache: bool,
+    chunk_separator: Option<String>,
+    allow_multi: bool,
     template_path: Option<PathBuf>,
     custom_format_name: Option<String>,
     custom_extension: Option<String>,
@@ -316,6 +410,15 @@ impl ConfigBuilder {
         self
     }
 
+    /// Reads files from `source` instead of walking the root directory.
+    ///
+    /// Git selections and diff mode need a [`FilesystemSource`](crate::FilesystemSource).
+    #[must_use]
+    pub fn source(mut self, source: impl FileSource + 'static) -> Self {
+        self.source = Some(Arc::new(source));
+        self
+    }
+
     /// Sets the output directory for generated files.
     #[must_use]
     pub fn output_dir(mut self, path: impl Into<PathBuf>) -> Self {
@@ -367,13 +470,21 @@ impl ConfigBuilder {
         self
     }
 
-    /// Enables or disables line boundary preference.
+    /// Enables or disables splitting oversized files at item and blank-line
+    /// boundaries.
     #[must_use]
     pub fn prefer_line_boundaries(mut self, enabled: bool) -> Self {
         self.prefer_line_boundaries = Some(enabled);
         self
     }
 
+    /// Sets how files are assigned to chunks.
+    #[must_use]
+    pub const fn packing(mut self, strategy: PackingStrategy) -> Self {
+        self.packing = Some(strategy);
+        self
+    }
+
     /// Enables dry run mode (no file writes).
     #[must_use]
     pub fn dry_run(mut self, enabled: bool) -> Self {
@@ -395,6 +506,30 @@ impl ConfigBuilder {
         self
     }
 
+    /// Enables the incremental cache in `<output_dir>/.llm-utl-cache/`.
+    ///
+    /// Unchanged files are not filtered and tokenized again, and output
+    /// files whose rendered content is unchanged are not rewritten.
+    #[must_use]
+    pub const fn cache(mut self, enabled: bool) -> Self {
+        self.cache = enabled;
+        self
+    }
+
+    /// Sets the text written between chunks when rendering to a stream.
+    #[must_use]
+    pub fn chunk_separator(mut self, separator: impl Into<String>) -> Self {
+        self.chunk_separator = Some(separator.into());
+        self
+    }
+
+    /// Allows rendering to a stream when the output needs more than one chunk.
+    #[must_use]
+    pub const fn allow_multi(mut self, enabled: bool) -> Self {
+        self.allow_multi = enabled;
+        self
+    }
+
     /// Sets the code filtering configuration.
     #[must_use]
     pub fn filter_config(mut self, config: FilterConfig) -> Self {
@@ -409,10 +544,80 @@ impl ConfigBuilder {
         self
     }
 
+    /// Restricts the scan to files selected from the local git repository
+    /// or a file list.
+    ///
+    /// Selected files still pass through the file and code filters.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::{Config, GitSelection};
+    ///
+    /// let config = Config::builder()
+    ///     .root_dir(".")
+    ///     .git_selection(GitSelection::Since("main".to_string()))
+    ///     .build()
+    ///     .expect("valid config");
+    /// ```
+    #[must_use]
+    pub fn git_selection(mut self, selection: GitSelection) -> Self {
+        self.git_selection = Some(selection);
+        self
+    }
+
+    /// Chooses between full files and diffs.
+    ///
+    /// Without this, presets such as code review render diffs whenever a
+    /// diff base or a `Since` selection is set, and full files otherwise.
+    #[must_use]
+    pub const fn content_mode(mut self, mode: ContentMode) -> Self {
+        self.content_mode = Some(mode);
+        self
+    }
+
+    /// Sets the ref that diff mode compares against.
+    ///
+    /// Files are compared with the merge base of this ref and `HEAD`.
+    #[must_use]
+    pub fn diff_base(mut self, rev: impl Into<String>) -> Self {
+        self.diff_base = Some(rev.into());
+        self
+    }
+
+    /// Sets how much unchanged code surrounds each change in diff mode.
+    #[must_use]
+    pub const fn diff_context(mut self, context: DiffContext) -> Self {
+        self.diff_context = Some(context);
+        self
+    }
+
     /// Sets the LLM preset.
+    ///
+    /// Accepts a built-in [`PresetKind`](crate::PresetKind) or any [`LLMPreset`],
+    /// e.g. one loaded through [`PresetRegistry`](crate::preset::PresetRegistry).
+    #[must_use]
+    pub fn preset(mut self, preset: impl Into<LLMPreset>) -> Self {
+        self.preset = Some(preset.into());
+        self
+    }
+
+    /// Enables per-directory totals in the project structure tree.
+    ///
+    /// The tree is rendered for presets that include the project structure.
     #[must_use]
-    pub fn preset(mut self, preset: PresetKind) -> Self {
-        self.preset = Some(preset);
+    pub const fn structure_directory_totals(mut self, enabled: bool) -> Self {
+        self.structure_directory_totals = enabled;
+        self
+    }
+
+    /// Enables source line numbers in rendered file contents.
+    ///
+    /// Numbers refer to the original file, so they stay correct after code
+    /// filtering and splitting. Diffs are rendered without them.
+    #[must_use]
+    pub const fn line_numbers(mut self, enabled: bool) -> Self {
+        self.line_numbers = enabled;
         self
     }
 
@@ -496,6 +701,7 @@ impl ConfigBuilder {
     pub fn build(self) -> Result<Config> {
         let config = Config {
             root_dir: self.root_dir.unwrap_or_else(|| PathBuf::from(".")),
+            source: self.source,
             output_dir: self.output_dir.unwrap_or_else(|| PathBuf::from("out")),
             output_pattern: self
                 .output_pattern
@@ -508,12 +714,24 @@ impl ConfigBuilder {
                 .unwrap_or(DEFAULT_CHUNK_SAFETY_MARGIN),
             tokenizer: self.tokenizer.unwrap_or(TokenizerKind::Simple),
             prefer_line_boundaries: self.prefer_line_boundaries.unwrap_or(true),
+            packing: self.packing.unwrap_or_default(),
             filter_config: self.filter_config.unwrap_or_default(),
             file_filter_config: self.file_filter_config.unwrap_or_default(),
+            git_selection: self.git_selection,
+            content_mode: self.content_mode,
+            diff_base: self.diff_base,
+            diff_context: self.diff_context.unwrap_or_default(),
             preset: self.preset,
+            structure_directory_totals: self.structure_directory_totals,
+            line_numbers: self.line_numbers,
             dry_run: self.dry_run,
             include_binary_files: self.include_binary_files,
             backup_existing: self.backup_existing.unwrap_or(true),
+            cache: self.cache,
+            chunk_separator: self
+                .chunk_separator
+                .unwrap_or_else(|| DEFAULT_CHUNK_SEPARATOR.to_string()),
+            allow_multi: self.allow_multi,
             template_path: self.template_path,
             custom_format_name: self.custom_format_name,
             custom_extension: self.custom_extension,
@@ -563,6 +781,32 @@ mod tests {
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_missing_bpe_vocabulary() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let result = Config::builder()
+            .root_dir(temp.path())
+            .tokenizer(TokenizerKind::Bpe {
+                vocab_path: temp.path().join("missing.tiktoken"),
+            })
+            .build();
+
+        assert!(result.unwrap_err().is_config());
+    }
+
+    #[test]
+    fn test_invalid_file_filter_glob() {
+        let temp = assert_fs::TempDir::new().unwrap();
+
+        let result = Config::builder()
+            .root_dir(temp.path())
+            .file_filter_config(FileFilterConfig::new().exclude_directories(vec!["src/[".to_string()]))
+            .build();
+
+        assert!(result.unwrap_err().to_string().contains("src/["));
+    }
+
     #[test]
     fn test_invalid_pattern() {
         let temp = assert_fs::TempDir::new().unwrap();
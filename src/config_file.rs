@@ -0,0 +1,916 @@
+//! Layered configuration from `.llm-utl.toml` files and the environment.
+//!
+//! Settings are merged with increasing precedence: built-in defaults, the user
+//! file (`~/.config/llm-utl/config.toml`), the project file (`.llm-utl.toml`),
+//! `LLM_UTL_*` environment variables and finally values set by the caller,
+//! usually from command-line flags.
+//!
+//! ```toml
+//! output_dir = "prompts"
+//! max_tokens = 50000
+//! preset = "code-review"
+//!
+//! [filter]
+//! remove_comments = false
+//!
+//! [files]
+//! exclude_directories = ["**/target", "**/node_modules"]
+//! ```
+
+use crate::config::{
+    Config, ConfigBuilder, OutputFormat, DEFAULT_CHUNK_SAFETY_MARGIN, DEFAULT_CHUNK_SEPARATOR,
+    DEFAULT_MAX_TOKENS, DEFAULT_OUTPUT_PATTERN, DEFAULT_OVERLAP_TOKENS,
+};
+use crate::error::{Error, Result};
+use crate::filter::{FileFilterConfig, FilterConfig};
+use crate::preset::PresetRegistry;
+use crate::splitter::PackingStrategy;
+use crate::token::TokenizerKind;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// File name of the project configuration file.
+pub const PROJECT_CONFIG_FILE: &str = ".llm-utl.toml";
+
+const USER_CONFIG_FILE: &str = "config.toml";
+const ENV_PREFIX: &str = "LLM_UTL_";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Bool,
+    Integer,
+    String,
+    Path,
+    List,
+    PathList,
+}
+
+impl Kind {
+    const fn describe(self) -> &'static str {
+        match self {
+            Self::Bool => "a boolean",
+            Self::Integer => "a non-negative integer",
+            Self::String => "a string",
+            Self::Path => "a path",
+            Self::List => "a list of strings",
+            Self::PathList => "a list of paths",
+        }
+    }
+}
+
+/// Every supported key, in the order `--print-config` lists them.
+const KEYS: &[(&str, Kind)] = &[
+    ("root_dir", Kind::Path),
+    ("output_dir", Kind::Path),
+    ("output_pattern", Kind::String),
+    ("format", Kind::String),
+    ("max_tokens", Kind::Integer),
+    ("overlap_tokens", Kind::Integer),
+    ("chunk_safety_margin", Kind::Integer),
+    ("tokenizer", Kind::String),
+    ("tokenizer_file", Kind::Path),
+    ("preset", Kind::String),
+    ("preset_files", Kind::PathList),
+    ("prefer_line_boundaries", Kind::Bool),
+    ("packing", Kind::String),
+    ("include_binary_files", Kind::Bool),
+    ("backup_existing", Kind::Bool),
+    ("cache", Kind::Bool),
+    ("chunk_separator", Kind::String),
+    ("allow_multi", Kind::Bool),
+    ("structure_directory_totals", Kind::Bool),
+    ("line_numbers", Kind::Bool),
+    ("template_path", Kind::Path),
+    ("custom_format_name", Kind::String),
+    ("custom_extension", Kind::String),
+    ("filter.remove_tests", Kind::Bool),
+    ("filter.remove_doc_comments", Kind::Bool),
+    ("filter.remove_comments", Kind::Bool),
+    ("filter.remove_blank_lines", Kind::Bool),
+    ("filter.preserve_headers", Kind::Bool),
+    ("filter.collapse_headers", Kind::Bool),
+    ("filter.remove_debug_prints", Kind::Bool),
+    ("filter.debug_print_names", Kind::List),
+    ("files.exclude_files", Kind::List),
+    ("files.exclude_directories", Kind::List),
+    ("files.allow_only", Kind::List),
+];
+
+fn lookup(key: &str) -> Option<(&'static str, Kind)> {
+    KEYS.iter().copied().find(|(name, _)| *name == key)
+}
+
+/// Returns the environment variable that overrides a key.
+fn env_var(key: &str) -> String {
+    format!("{ENV_PREFIX}{}", key.replace('.', "_").to_ascii_uppercase())
+}
+
+/// A configuration value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigValue {
+    /// Boolean flag
+    Bool(bool),
+    /// Non-negative integer
+    Integer(usize),
+    /// String or path
+    String(String),
+    /// List of strings or paths
+    List(Vec<String>),
+}
+
+impl From<bool> for ConfigValue {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+impl From<usize> for ConfigValue {
+    fn from(value: usize) -> Self {
+        Self::Integer(value)
+    }
+}
+
+impl From<String> for ConfigValue {
+    fn from(value: String) -> Self {
+        Self::String(value)
+    }
+}
+
+impl From<&str> for ConfigValue {
+    fn from(value: &str) -> Self {
+        Self::String(value.to_string())
+    }
+}
+
+impl From<PathBuf> for ConfigValue {
+    fn from(value: PathBuf) -> Self {
+        Self::String(value.display().to_string())
+    }
+}
+
+impl From<Vec<String>> for ConfigValue {
+    fn from(value: Vec<String>) -> Self {
+        Self::List(value)
+    }
+}
+
+impl From<Vec<PathBuf>> for ConfigValue {
+    fn from(value: Vec<PathBuf>) -> Self {
+        Self::List(value.iter().map(|path| path.display().to_string()).collect())
+    }
+}
+
+impl fmt::Display for ConfigValue {
+    /// Formats the value as TOML.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // JSON string escapes are valid TOML basic strings
+        let quote = |s: &str| serde_json::Value::from(s).to_string();
+
+        match self {
+            Self::Bool(value) => write!(f, "{value}"),
+            Self::Integer(value) => write!(f, "{value}"),
+            Self::String(value) => f.write_str(&quote(value)),
+            Self::List(values) => {
+                let items: Vec<String> = values.iter().map(|value| quote(value)).collect();
+                write!(f, "[{}]", items.join(", "))
+            }
+        }
+    }
+}
+
+/// Where a configuration value came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigSource {
+    /// Built-in default
+    Default,
+    /// User configuration file
+    UserFile(PathBuf),
+    /// Project `.llm-utl.toml`
+    ProjectFile(PathBuf),
+    /// Environment variable
+    Env(String),
+    /// Command-line flag
+    Cli,
+}
+
+impl fmt::Display for ConfigSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("default"),
+            Self::UserFile(path) => write!(f, "user file {}", path.display()),
+            Self::ProjectFile(path) => write!(f, "project file {}", path.display()),
+            Self::Env(name) => write!(f, "env {name}"),
+            Self::Cli => f.write_str("command line"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Setting {
+    value: ConfigValue,
+    source: ConfigSource,
+}
+
+/// Configuration merged from defaults, config files, the environment and the CLI.
+///
+/// Each value remembers which layer set it. [`LayeredConfig::to_builder`]
+/// turns the merged values into a [`ConfigBuilder`]; formatting with `{}`
+/// prints them as TOML annotated with their sources.
+///
+/// # Examples
+///
+/// ```no_run
+/// use llm_utl::{ConfigSource, LayeredConfig};
+///
+/// let mut layers = LayeredConfig::load(None)?;
+/// layers.set("max_tokens", 50_000_usize, ConfigSource::Cli)?;
+///
+/// println!("{layers}");
+/// let config = layers.to_builder()?.build()?;
+/// # Ok::<(), llm_utl::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct LayeredConfig {
+    settings: HashMap<&'static str, Setting>,
+}
+
+impl Default for LayeredConfig {
+    fn default() -> Self {
+        Self::defaults()
+    }
+}
+
+impl LayeredConfig {
+    /// Returns the built-in defaults.
+    #[must_use]
+    pub fn defaults() -> Self {
+        let defaults: [(&'static str, ConfigValue); 28] = [
+            ("root_dir", ".".into()),
+            ("output_dir", "out".into()),
+            ("output_pattern", DEFAULT_OUTPUT_PATTERN.into()),
+            ("format", "markdown".into()),
+            ("max_tokens", DEFAULT_MAX_TOKENS.into()),
+            ("overlap_tokens", DEFAULT_OVERLAP_TOKENS.into()),
+            ("chunk_safety_margin", DEFAULT_CHUNK_SAFETY_MARGIN.into()),
+            ("tokenizer", "enhanced".into()),
+            ("preset_files", ConfigValue::List(Vec::new())),
+            ("prefer_line_boundaries", true.into()),
+            ("packing", "sequential".into()),
+            ("include_binary_files", false.into()),
+            ("backup_existing", true.into()),
+            ("cache", false.into()),
+            ("chunk_separator", DEFAULT_CHUNK_SEPARATOR.into()),
+            ("allow_multi", false.into()),
+            ("structure_directory_totals", false.into()),
+            ("line_numbers", false.into()),
+            ("filter.remove_tests", true.into()),
+            ("filter.remove_doc_comments", true.into()),
+            ("filter.remove_comments", true.into()),
+            ("filter.remove_blank_lines", true.into()),
+            ("filter.preserve_headers", true.into()),
+            ("filter.collapse_headers", false.into()),
+            ("filter.remove_debug_prints", true.into()),
+            ("filter.debug_print_names", ConfigValue::List(Vec::new())),
+            ("files.exclude_files", ConfigValue::List(Vec::new())),
+            (
+                "files.exclude_directories",
+                ConfigValue::List(vec![
+                    "**/templates".to_string(),
+                    "**/out".to_string(),
+                    "**/target".to_string(),
+                ]),
+            ),
+        ];
+
+        let settings = defaults
+            .into_iter()
+            .map(|(key, value)| {
+                let source = ConfigSource::Default;
+                (key, Setting { value, source })
+            })
+            .collect();
+
+        Self { settings }
+    }
+
+    /// Loads the defaults, the user file, the project file and `LLM_UTL_*`
+    /// environment variables.
+    ///
+    /// The project file is looked up in `root_dir` (falling back to
+    /// `LLM_UTL_ROOT_DIR`, then the current directory) and its parents, up to
+    /// the enclosing git repository root.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a config file cannot be read or parsed, or if it
+    /// or an environment variable holds an unknown key or a mistyped value.
+    pub fn load(root_dir: Option<&Path>) -> Result<Self> {
+        let mut config = Self::defaults();
+
+        if let Some(path) = user_config_dir()
+            .map(|dir| dir.join(USER_CONFIG_FILE))
+            .filter(|path| path.is_file())
+        {
+            config.merge_file(&path, &ConfigSource::UserFile(path.clone()))?;
+        }
+
+        let start = root_dir.map_or_else(
+            || {
+                std::env::var_os(env_var("root_dir"))
+                    .map_or_else(|| PathBuf::from("."), PathBuf::from)
+            },
+            Path::to_path_buf,
+        );
+        if let Some(path) = find_project_file(&start) {
+            config.merge_file(&path, &ConfigSource::ProjectFile(path.clone()))?;
+        }
+
+        config.merge_env(std::env::vars())?;
+        Ok(config)
+    }
+
+    /// Sets a value, overriding any value from a lower layer.
+    ///
+    /// Keys in the `[filter]` and `[files]` tables are written as
+    /// `filter.remove_tests`, `files.exclude_directories` and so on.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is unknown or the value has the wrong type.
+    pub fn set(
+        &mut self,
+        key: &str,
+        value: impl Into<ConfigValue>,
+        source: ConfigSource,
+    ) -> Result<()> {
+        let (name, kind) =
+            lookup(key).ok_or_else(|| Error::config(format!("Unknown configuration key `{key}`")))?;
+        let value = value.into();
+
+        let matches = matches!(
+            (kind, &value),
+            (Kind::Bool, ConfigValue::Bool(_))
+                | (Kind::Integer, ConfigValue::Integer(_))
+                | (Kind::String | Kind::Path, ConfigValue::String(_))
+                | (Kind::List | Kind::PathList, ConfigValue::List(_))
+        );
+        if !matches {
+            return Err(Error::config(format!(
+                "`{key}` expects {}, got {value}",
+                kind.describe()
+            )));
+        }
+
+        self.settings.insert(name, Setting { value, source });
+        Ok(())
+    }
+
+    /// Appends items to a list value, keeping the items from lower layers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the key is unknown or does not hold a list.
+    pub fn extend<I, S>(&mut self, key: &str, items: I, source: ConfigSource) -> Result<()>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut values = self.list(key).to_vec();
+        values.extend(items.into_iter().map(Into::into));
+        self.set(key, values, source)
+    }
+
+    /// Returns the effective value of a key, if set.
+    #[must_use]
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        self.settings.get(key).map(|setting| &setting.value)
+    }
+
+    /// Returns the layer that set a key, if set.
+    #[must_use]
+    pub fn source(&self, key: &str) -> Option<&ConfigSource> {
+        self.settings.get(key).map(|setting| &setting.source)
+    }
+
+    /// Converts the merged values into a [`ConfigBuilder`].
+    ///
+    /// A `preset` id is resolved against built-in presets, presets discovered
+    /// for `root_dir` and `preset_files`. Without `preset`, preset files that
+    /// define exactly one preset select it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error for an unknown format, tokenizer, packing strategy or
+    /// preset, a BPE tokenizer without `tokenizer_file`, or an unreadable
+    /// preset file.
+    pub fn to_builder(&self) -> Result<ConfigBuilder> {
+        let root_dir = self.path("root_dir").unwrap_or_else(|| PathBuf::from("."));
+
+        let mut builder = Config::builder()
+            .root_dir(&root_dir)
+            .format(self.format()?)
+            .tokenizer(self.tokenizer()?)
+            .packing(self.packing()?)
+            .filter_config(self.filter_config())
+            .file_filter_config(self.file_filter_config())
+            .structure_directory_totals(self.bool("structure_directory_totals").unwrap_or(false))
+            .line_numbers(self.bool("line_numbers").unwrap_or(false))
+            .cache(self.bool("cache").unwrap_or(false))
+            .allow_multi(self.bool("allow_multi").unwrap_or(false))
+            .include_binary_files(self.bool("include_binary_files").unwrap_or(false));
+
+        if let Some(dir) = self.path("output_dir") {
+            builder = builder.output_dir(dir);
+        }
+        if let Some(pattern) = self.string("output_pattern") {
+            builder = builder.output_pattern(pattern);
+        }
+        if let Some(separator) = self.string("chunk_separator") {
+            builder = builder.chunk_separator(separator);
+        }
+        if let Some(tokens) = self.integer("max_tokens") {
+            builder = builder.max_tokens(tokens);
+        }
+        if let Some(tokens) = self.integer("overlap_tokens") {
+            builder = builder.overlap_tokens(tokens);
+        }
+        if let Some(margin) = self.integer("chunk_safety_margin") {
+            builder = builder.chunk_safety_margin(margin);
+        }
+        if let Some(enabled) = self.bool("prefer_line_boundaries") {
+            builder = builder.prefer_line_boundaries(enabled);
+        }
+        if let Some(enabled) = self.bool("backup_existing") {
+            builder = builder.backup_existing(enabled);
+        }
+        if let Some(path) = self.path("template_path") {
+            builder = builder.template_path(path);
+        }
+        if let Some(name) = self.string("custom_format_name") {
+            builder = builder.custom_format_name(name);
+        }
+        if let Some(ext) = self.string("custom_extension") {
+            builder = builder.custom_extension(ext);
+        }
+
+        let preset_files = self.list("preset_files");
+        if self.get("preset").is_some() || !preset_files.is_empty() {
+            let mut registry = PresetRegistry::discover(&root_dir)?;
+            let mut loaded = Vec::new();
+            for file in preset_files {
+                loaded.extend(registry.load_file(file)?);
+            }
+
+            let id = match (self.string("preset"), loaded.as_slice()) {
+                (Some(id), _) => id,
+                (None, [id]) => id.as_str(),
+                (None, _) => {
+                    return Err(Error::config(format!(
+                        "Preset files define {} presets; select one with `preset`",
+                        loaded.len()
+                    )));
+                }
+            };
+            builder = builder.preset(registry.resolve(id)?);
+        }
+
+        Ok(builder)
+    }
+
+    fn bool(&self, key: &str) -> Option<bool> {
+        match self.get(key)? {
+            ConfigValue::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn integer(&self, key: &str) -> Option<usize> {
+        match self.get(key)? {
+            ConfigValue::Integer(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    fn string(&self, key: &str) -> Option<&str> {
+        match self.get(key)? {
+            ConfigValue::String(value) => Some(value),
+            _ => None,
+        }
+    }
+
+    fn path(&self, key: &str) -> Option<PathBuf> {
+        self.string(key).map(PathBuf::from)
+    }
+
+    fn list(&self, key: &str) -> &[String] {
+        match self.get(key) {
+            Some(ConfigValue::List(values)) => values,
+            _ => &[],
+        }
+    }
+
+    fn format(&self) -> Result<OutputFormat> {
+        let Some(name) = self.string("format") else {
+            return Ok(OutputFormat::Markdown);
+        };
+
+        [
+            OutputFormat::Markdown,
+            OutputFormat::Xml,
+            OutputFormat::Json,
+            OutputFormat::Custom,
+        ]
+        .into_iter()
+        .find(|format| format.template_name() == name)
+        .ok_or_else(|| {
+            Error::config(format!(
+                "Unknown format `{name}`; expected markdown, xml, json or custom"
+            ))
+        })
+    }
+
+    fn tokenizer(&self) -> Result<TokenizerKind> {
+        match self.string("tokenizer").unwrap_or("enhanced") {
+            "simple" => Ok(TokenizerKind::Simple),
+            "enhanced" => Ok(TokenizerKind::Enhanced),
+            "bpe" => {
+                let vocab_path = self.path("tokenizer_file").ok_or_else(|| {
+                    Error::config("tokenizer = \"bpe\" requires tokenizer_file")
+                })?;
+                Ok(TokenizerKind::Bpe { vocab_path })
+            }
+            other => Err(Error::config(format!(
+                "Unknown tokenizer `{other}`; expected simple, enhanced or bpe"
+            ))),
+        }
+    }
+
+    fn packing(&self) -> Result<PackingStrategy> {
+        let id = self.string("packing").unwrap_or("sequential");
+        PackingStrategy::from_id(id).ok_or_else(|| {
+            Error::config(format!(
+                "Unknown packing strategy `{id}`; expected sequential, first-fit-decreasing or directory-affinity"
+            ))
+        })
+    }
+
+    fn filter_config(&self) -> FilterConfig {
+        let defaults = FilterConfig::default();
+        let flag = |key: &str, default: bool| self.bool(key).unwrap_or(default);
+
+        FilterConfig {
+            remove_tests: flag("filter.remove_tests", defaults.remove_tests),
+            remove_doc_comments: flag("filter.remove_doc_comments", defaults.remove_doc_comments),
+            remove_comments: flag("filter.remove_comments", defaults.remove_comments),
+            remove_blank_lines: flag("filter.remove_blank_lines", defaults.remove_blank_lines),
+            preserve_headers: flag("filter.preserve_headers", defaults.preserve_headers),
+            collapse_headers: flag("filter.collapse_headers", defaults.collapse_headers),
+            remove_debug_prints: flag("filter.remove_debug_prints", defaults.remove_debug_prints),
+            debug_print_names: self.list("filter.debug_print_names").to_vec(),
+        }
+    }
+
+    fn file_filter_config(&self) -> FileFilterConfig {
+        FileFilterConfig::new()
+            .exclude_files(self.list("files.exclude_files").to_vec())
+            .exclude_directories(self.list("files.exclude_directories").to_vec())
+            .allow_only(self.list("files.allow_only").to_vec())
+    }
+
+    /// Merges a TOML config file; relative paths resolve against its directory.
+    fn merge_file(&mut self, path: &Path, source: &ConfigSource) -> Result<()> {
+        let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+        let table: toml::Table =
+            toml::from_str(&content).map_err(|e| Error::config_file(path, e.to_string()))?;
+        let base = path.parent().unwrap_or_else(|| Path::new("."));
+
+        let mut entries = Vec::new();
+        flatten(table, "", &mut entries);
+
+        for (key, value) in entries {
+            let (name, kind) = lookup(&key)
+                .ok_or_else(|| Error::config_file(path, format!("unknown key `{key}`")))?;
+            let value = from_toml(kind, value)
+                .map_err(|e| Error::config_file(path, format!("`{key}`: {e}")))?;
+            let value = match (kind, value) {
+                (Kind::Path, ConfigValue::String(value)) => {
+                    ConfigValue::String(resolve_path(base, &value))
+                }
+                (Kind::PathList, ConfigValue::List(values)) => ConfigValue::List(
+                    values.iter().map(|value| resolve_path(base, value)).collect(),
+                ),
+                (_, value) => value,
+            };
+            self.set(name, value, source.clone())?;
+        }
+
+        Ok(())
+    }
+
+    /// Merges `LLM_UTL_*` variables; other variables are ignored.
+    fn merge_env(&mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<()> {
+        let env: HashMap<String, String> = vars
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+
+        for &(key, kind) in KEYS {
+            let name = env_var(key);
+            if let Some(raw) = env.get(&name) {
+                let value = from_env(kind, raw)
+                    .map_err(|e| Error::config(format!("{name}: {e}")))?;
+                self.set(key, value, ConfigSource::Env(name))?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Display for LayeredConfig {
+    /// Formats the effective configuration as TOML, each value annotated
+    /// with its source.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let lines: Vec<(Option<&str>, String, &ConfigSource)> = KEYS
+            .iter()
+            .filter_map(|(key, _)| {
+                let setting = self.settings.get(key)?;
+                let (table, name) = match key.split_once('.') {
+                    Some((table, name)) => (Some(table), name),
+                    None => (None, *key),
+                };
+                Some((table, format!("{name} = {}", setting.value), &setting.source))
+            })
+            .collect();
+        let width = lines.iter().map(|(_, line, _)| line.len()).max().unwrap_or(0);
+
+        let mut current = None;
+        for (table, line, source) in &lines {
+            if *table != current {
+                if let Some(table) = table {
+                    writeln!(f, "\n[{table}]")?;
+                }
+                current = *table;
+            }
+            writeln!(f, "{line:width$}  # {source}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Returns `$XDG_CONFIG_HOME/llm-utl`, falling back to `~/.config/llm-utl`.
+pub(crate) fn user_config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+        .map(|config| config.join("llm-utl"))
+}
+
+/// Searches `start` and its parents for `.llm-utl.toml`, stopping at the
+/// first directory that contains `.git`.
+fn find_project_file(start: &Path) -> Option<PathBuf> {
+    let start = start.canonicalize().ok()?;
+
+    for dir in start.ancestors() {
+        let candidate = dir.join(PROJECT_CONFIG_FILE);
+        if candidate.is_file() {
+            return Some(candidate);
+        }
+        if dir.join(".git").exists() {
+            break;
+        }
+    }
+
+    None
+}
+
+/// Flattens `[filter]` and `[files]` tables into dotted keys.
+fn flatten(table: toml::Table, prefix: &str, entries: &mut Vec<(String, toml::Value)>) {
+    for (name, value) in table {
+        let key = if prefix.is_empty() {
+            name
+        } else {
+            format!("{prefix}.{name}")
+        };
+
+        match value {
+            toml::Value::Table(nested) if prefix.is_empty() => flatten(nested, &key, entries),
+            value => entries.push((key, value)),
+        }
+    }
+}
+
+fn from_toml(kind: Kind, value: toml::Value) -> std::result::Result<ConfigValue, String> {
+    let expected = || format!("expected {}", kind.describe());
+
+    match (kind, value) {
+        (Kind::Bool, toml::Value::Boolean(value)) => Ok(ConfigValue::Bool(value)),
+        (Kind::Integer, toml::Value::Integer(value)) => usize::try_from(value)
+            .map(ConfigValue::Integer)
+            .map_err(|_| expected()),
+        (Kind::String | Kind::Path, toml::Value::String(value)) => Ok(ConfigValue::String(value)),
+        (Kind::List | Kind::PathList, toml::Value::Array(values)) => values
+            .into_iter()
+            .map(|value| match value {
+                toml::Value::String(value) => Ok(value),
+                _ => Err(expected()),
+            })
+            .collect::<std::result::Result<_, _>>()
+            .map(ConfigValue::List),
+        _ => Err(expected()),
+    }
+}
+
+/// Parses an environment variable; lists are comma-separated.
+fn from_env(kind: Kind, raw: &str) -> std::result::Result<ConfigValue, String> {
+    let expected = || format!("expected {}, got `{raw}`", kind.describe());
+
+    match kind {
+        Kind::Bool => match raw.trim().to_ascii_lowercase().as_str() {
+            "true" | "1" | "yes" | "on" => Ok(ConfigValue::Bool(true)),
+            "false" | "0" | "no" | "off" => Ok(ConfigValue::Bool(false)),
+            _ => Err(expected()),
+        },
+        Kind::Integer => raw
+            .trim()
+            .replace('_', "")
+            .parse()
+            .map(ConfigValue::Integer)
+            .map_err(|_| expected()),
+        Kind::String | Kind::Path => Ok(ConfigValue::String(raw.to_string())),
+        Kind::List | Kind::PathList => Ok(ConfigValue::List(
+            raw.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(str::to_string)
+                .collect(),
+        )),
+    }
+}
+
+fn resolve_path(base: &Path, value: &str) -> String {
+    // `components` drops `.` segments, so "." resolves to `base` itself
+    let path: PathBuf = base.join(value).components().collect();
+    path.display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+
+    fn write_config(dir: &Path, content: &str) -> PathBuf {
+        let path = dir.join(PROJECT_CONFIG_FILE);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_project_file_overrides_defaults() {
+        let temp = TempDir::new().unwrap();
+        let path = write_config(
+            temp.path(),
+            r#"
+output_dir = "prompts"
+max_tokens = 50000
+
+[filter]
+remove_comments = false
+
+[files]
+exclude_directories = ["**/vendor"]
+"#,
+        );
+
+        let mut config = LayeredConfig::defaults();
+        config
+            .merge_file(&path, &ConfigSource::ProjectFile(path.clone()))
+            .unwrap();
+
+        assert_eq!(config.get("max_tokens"), Some(&ConfigValue::Integer(50_000)));
+        assert_eq!(config.source("max_tokens"), Some(&ConfigSource::ProjectFile(path.clone())));
+        assert_eq!(config.source("overlap_tokens"), Some(&ConfigSource::Default));
+        assert_eq!(
+            config.get("output_dir"),
+            Some(&ConfigValue::String(temp.path().join("prompts").display().to_string()))
+        );
+
+        let filter = config.filter_config();
+        assert!(!filter.remove_comments);
+        assert!(filter.remove_tests);
+        assert_eq!(
+            config.list("files.exclude_directories"),
+            ["**/vendor".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_invalid_file_is_rejected() {
+        let temp = TempDir::new().unwrap();
+        let mut config = LayeredConfig::defaults();
+
+        let path = write_config(temp.path(), "max_token = 10\n");
+        let err = config.merge_file(&path, &ConfigSource::Default).unwrap_err();
+        assert!(err.to_string().contains("unknown key `max_token`"));
+
+        let path = write_config(temp.path(), "[filter]\nremove_tests = \"yes\"\n");
+        let err = config.merge_file(&path, &ConfigSource::Default).unwrap_err();
+        assert!(err.to_string().contains("`filter.remove_tests`: expected a boolean"));
+    }
+
+    #[test]
+    fn test_env_and_cli_precedence() {
+        let mut config = LayeredConfig::defaults();
+        config
+            .merge_env([
+                ("LLM_UTL_MAX_TOKENS".to_string(), "20_000".to_string()),
+                ("LLM_UTL_FORMAT".to_string(), "json".to_string()),
+                ("LLM_UTL_FILTER_REMOVE_TESTS".to_string(), "no".to_string()),
+                ("LLM_UTL_FILES_EXCLUDE_FILES".to_string(), "a.rs, b.rs".to_string()),
+                ("PATH".to_string(), "/usr/bin".to_string()),
+            ])
+            .unwrap();
+        config.set("format", "xml", ConfigSource::Cli).unwrap();
+
+        assert_eq!(config.integer("max_tokens"), Some(20_000));
+        assert_eq!(
+            config.source("max_tokens"),
+            Some(&ConfigSource::Env("LLM_UTL_MAX_TOKENS".to_string()))
+        );
+        assert_eq!(config.bool("filter.remove_tests"), Some(false));
+        assert_eq!(config.list("files.exclude_files"), ["a.rs", "b.rs"]);
+        assert_eq!(config.format().unwrap(), OutputFormat::Xml);
+        assert_eq!(config.source("format"), Some(&ConfigSource::Cli));
+
+        let err = config
+            .merge_env([("LLM_UTL_OVERLAP_TOKENS".to_string(), "many".to_string())])
+            .unwrap_err();
+        assert!(err.to_string().contains("LLM_UTL_OVERLAP_TOKENS"));
+        assert!(config.set("max_tokens", "lots", ConfigSource::Cli).is_err());
+    }
+
+    #[test]
+    fn test_extend_keeps_lower_layers() {
+        let mut config = LayeredConfig::defaults();
+        config
+            .extend("files.exclude_directories", ["**/vendor"], ConfigSource::Cli)
+            .unwrap();
+
+        assert_eq!(
+            config.list("files.exclude_directories"),
+            ["**/templates", "**/out", "**/target", "**/vendor"]
+        );
+        assert!(config.extend("max_tokens", ["1"], ConfigSource::Cli).is_err());
+    }
+
+    #[test]
+    fn test_find_project_file_stops_at_repository_root() {
+        let temp = TempDir::new().unwrap();
+        let repo = temp.path().join("repo");
+        let nested = repo.join("src").join("nested");
+        fs::create_dir_all(&nested).unwrap();
+        write_config(temp.path(), "max_tokens = 1\n");
+        fs::create_dir(repo.join(".git")).unwrap();
+
+        assert_eq!(find_project_file(&nested), None);
+
+        let path = write_config(&repo, "max_tokens = 2\n");
+        assert_eq!(find_project_file(&nested), Some(path.canonicalize().unwrap()));
+    }
+
+    #[test]
+    fn test_display_annotates_sources() {
+        let mut config = LayeredConfig::defaults();
+        config.set("preset", "code-review", ConfigSource::Cli).unwrap();
+
+        let printed = config.to_string();
+        assert!(printed.starts_with("root_dir = \".\""));
+        assert!(printed.contains("preset = \"code-review\""));
+        assert!(printed.lines().any(|line| line.starts_with("preset =") && line.ends_with("# command line")));
+        assert!(printed.contains("\n[filter]\nremove_tests = true"));
+        assert!(printed.contains(
+            "exclude_directories = [\"**/templates\", \"**/out\", \"**/target\"]"
+        ));
+    }
+
+    #[test]
+    fn test_to_builder() {
+        let temp = TempDir::new().unwrap();
+        let mut config = LayeredConfig::defaults();
+        config.set("root_dir", temp.path().to_path_buf(), ConfigSource::Cli).unwrap();
+        config.set("preset", "code-review", ConfigSource::Cli).unwrap();
+        config.set("tokenizer", "simple", ConfigSource::Cli).unwrap();
+
+        let built = config.to_builder().unwrap().build().unwrap();
+        assert_eq!(built.preset.unwrap().id, "code-review");
+        assert!(matches!(built.tokenizer, TokenizerKind::Simple));
+        assert!(built.filter_config.remove_comments);
+
+        config.set("tokenizer", "bpe", ConfigSource::Cli).unwrap();
+        assert!(config.to_builder().unwrap_err().is_config());
+    }
+}
@@ -0,0 +1,249 @@
+//! Diff-mode output.
+//!
+//! In diff mode each file's content is replaced by a unified diff of its
+//! filtered content against a base revision, so the LLM sees only what
+//! changed plus a few lines of context.
+
+use crate::config::Config;
+use crate::git::GitSelection;
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+
+const DEFAULT_CONTEXT_LINES: usize = 3;
+
+/// What each file contributes to the output.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContentMode {
+    /// The whole (filtered) file
+    #[default]
+    Full,
+    /// A unified diff against a base revision
+    Diff,
+}
+
+/// How much unchanged code surrounds each change in a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffContext {
+    /// This many lines before and after each change
+    Lines(usize),
+    /// The whole new file, with changes marked
+    FullFile,
+}
+
+impl Default for DiffContext {
+    fn default() -> Self {
+        Self::Lines(DEFAULT_CONTEXT_LINES)
+    }
+}
+
+impl FromStr for DiffContext {
+    type Err = String;
+
+    /// Parses a line count or `full`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.eq_ignore_ascii_case("full") {
+            return Ok(Self::FullFile);
+        }
+        s.parse()
+            .map(Self::Lines)
+            .map_err(|_| format!("expected a number of lines or 'full', got '{s}'"))
+    }
+}
+
+impl fmt::Display for DiffContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Lines(lines) => write!(f, "{lines}"),
+            Self::FullFile => f.write_str("full"),
+        }
+    }
+}
+
+/// Summary of a file rendered as a diff.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct FileDiff {
+    /// Ref the file was compared against
+    pub base: String,
+    /// Whether the file does not exist at the base
+    pub is_new: bool,
+    /// Whether the file was deleted since the base
+    pub is_deleted: bool,
+    /// Number of added lines
+    pub added: usize,
+    /// Number of removed lines
+    pub removed: usize,
+}
+
+/// Resolved diff-mode settings for a run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct DiffOptions {
+    pub base: String,
+    pub context: DiffContext,
+}
+
+impl DiffOptions {
+    /// Returns the diff settings for a configuration, or `None` for full files.
+    ///
+    /// The base is `diff_base`, else the ref of a `--since` selection. An
+    /// explicit content mode wins; otherwise the preset's default applies,
+    /// but only when a base is known. Explicit diff mode without a base
+    /// compares against `HEAD`.
+    pub(crate) fn from_config(config: &Config) -> Option<Self> {
+        let since = match &config.git_selection {
+            Some(GitSelection::Since(rev)) => Some(rev.clone()),
+            _ => None,
+        };
+        let base = config.diff_base.clone().or(since);
+
+        let preset_mode = match (&base, &config.preset) {
+            (Some(_), Some(preset)) => preset.content_mode,
+            _ => ContentMode::Full,
+        };
+        let mode = config.content_mode.unwrap_or(preset_mode);
+
+        (mode == ContentMode::Diff).then(|| Self {
+            base: base.unwrap_or_else(|| "HEAD".to_string()),
+            context: config.diff_context,
+        })
+    }
+}
+
+/// Builds a unified diff from `old` (`None` for a new file) to `new`
+/// (`None` for a deleted file).
+///
+/// Filtered content has no trailing newline, so both sides are compared as
+/// complete lines and the patch is returned without a trailing newline too.
+/// Returns `None` when the contents are identical.
+pub(crate) fn unified_diff(
+    base: &str,
+    old: Option<&str>,
+    new: Option<&str>,
+    path: &str,
+    context: DiffContext,
+) -> Option<(String, FileDiff)> {
+    let old_text = with_final_newline(old.unwrap_or(""));
+    let new_text = with_final_newline(new.unwrap_or(""));
+    let diff = TextDiff::from_lines(old_text.as_ref(), new_text.as_ref());
+
+    let (mut added, mut removed) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => added += 1,
+            ChangeTag::Delete => removed += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+    if added == 0 && removed == 0 && old.is_some() && new.is_some() {
+        return None;
+    }
+
+    let radius = match context {
+        DiffContext::Lines(lines) => lines,
+        DiffContext::FullFile => old_text.lines().count().max(new_text.lines().count()),
+    };
+    let header = |text: Option<&str>, side: &str| {
+        text.map_or_else(|| "/dev/null".to_string(), |_| format!("{side}/{path}"))
+    };
+    let rendered = diff
+        .unified_diff()
+        .context_radius(radius)
+        .header(&header(old, "a"), &header(new, "b"))
+        .to_string()
+        .trim_end_matches('\n')
+        .to_string();
+
+    let summary = FileDiff {
+        base: base.to_string(),
+        is_new: old.is_none(),
+        is_deleted: new.is_none(),
+        added,
+        removed,
+    };
+    Some((rendered, summary))
+}
+
+fn with_final_newline(text: &str) -> Cow<'_, str> {
+    if text.is_empty() || text.ends_with('\n') {
+        Cow::Borrowed(text)
+    } else {
+        Cow::Owned(format!("{text}\n"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PresetKind;
+
+    const OLD: &str = "fn a() {}\nfn b() {}\nfn c() {}\nfn d() {}\nfn e() {}\n";
+    const NEW: &str = "fn a() {}\nfn b() {}\nfn c() { 1 }\nfn d() {}\nfn e() {}\n";
+
+    #[test]
+    fn test_unified_diff_with_context() {
+        let (patch, summary) =
+            unified_diff("main", Some(OLD), Some(NEW), "src/lib.rs", DiffContext::Lines(1)).unwrap();
+
+        assert_eq!(
+            patch,
+            "--- a/src/lib.rs\n+++ b/src/lib.rs\n@@ -2,3 +2,3 @@\n fn b() {}\n-fn c() {}\n+fn c() { 1 }\n fn d() {}"
+        );
+        assert_eq!((summary.added, summary.removed, summary.is_new), (1, 1, false));
+
+        let (full, _) =
+            unified_diff("main", Some(OLD), Some(NEW), "src/lib.rs", DiffContext::FullFile).unwrap();
+        assert!(full.contains("@@ -1,5 +1,5 @@\n fn a() {}\n"));
+        assert!(full.ends_with(" fn e() {}"));
+    }
+
+    #[test]
+    fn test_unified_diff_new_deleted_and_unchanged_files() {
+        let (patch, summary) =
+            unified_diff("main", None, Some("fn a() {}\n"), "a.rs", DiffContext::default()).unwrap();
+        assert!(patch.starts_with("--- /dev/null\n+++ b/a.rs\n"));
+        assert!(summary.is_new && !summary.is_deleted);
+
+        let (patch, summary) =
+            unified_diff("main", Some("fn a() {}\nfn b() {}"), None, "a.rs", DiffContext::default()).unwrap();
+        assert_eq!(patch, "--- a/a.rs\n+++ /dev/null\n@@ -1,2 +0,0 @@\n-fn a() {}\n-fn b() {}");
+        assert!(summary.is_deleted && !summary.is_new);
+        assert_eq!((summary.added, summary.removed), (0, 2));
+
+        assert!(unified_diff("main", Some(OLD), Some(OLD), "a.rs", DiffContext::default()).is_none());
+        assert!(unified_diff("main", Some("fn a() {}"), Some("fn a() {}\n"), "a.rs", DiffContext::default()).is_none());
+    }
+
+    #[test]
+    fn test_diff_context_parse() {
+        assert_eq!("5".parse(), Ok(DiffContext::Lines(5)));
+        assert_eq!("full".parse(), Ok(DiffContext::FullFile));
+        assert!("many".parse::<DiffContext>().is_err());
+    }
+
+    #[test]
+    fn test_preset_default_needs_a_base() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let builder = || Config::builder().root_dir(temp.path()).preset(PresetKind::CodeReview);
+
+        assert_eq!(DiffOptions::from_config(&builder().build().unwrap()), None);
+
+        let since = builder()
+            .git_selection(GitSelection::Since("main".to_string()))
+            .build()
+            .unwrap();
+        assert_eq!(
+            DiffOptions::from_config(&since).map(|options| options.base),
+            Some("main".to_string())
+        );
+
+        let full = builder()
+            .diff_base("main")
+            .content_mode(ContentMode::Full)
+            .build()
+            .unwrap();
+        assert_eq!(DiffOptions::from_config(&full), None);
+    }
+}
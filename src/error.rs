@@ -90,6 +90,47 @@ pub enum Error {
         errors: Vec<Error>,
     },
 
+    /// Tokenizer vocabulary could not be loaded.
+    #[error("Failed to load tokenizer vocabulary '{path}': {message}")]
+    Tokenizer {
+        /// Path to the vocabulary file
+        path: PathBuf,
+        /// Error message
+        message: String,
+    },
+
+    /// Preset file could not be loaded.
+    #[error("Failed to load preset file '{path}': {message}")]
+    Preset {
+        /// Path to the preset file
+        path: PathBuf,
+        /// Error message
+        message: String,
+    },
+
+    /// Configuration file could not be loaded.
+    #[error("Failed to load configuration file '{path}': {message}")]
+    ConfigFile {
+        /// Path to the configuration file
+        path: PathBuf,
+        /// Error message
+        message: String,
+    },
+
+    /// Git command failed while selecting files.
+    #[error("Git selection failed: {message}")]
+    Git {
+        /// Error message
+        message: String,
+    },
+
+    /// Streamed output would need more than one chunk.
+    #[error("Output needs {chunks} chunks but only one can be streamed.\n\nRaise --max-tokens to fit everything into one chunk, or pass --allow-multi to stream all chunks separated by the chunk separator.")]
+    TooManyChunks {
+        /// Number of chunks the output needs
+        chunks: usize,
+    },
+
     /// Invalid output pattern.
     #[error("Invalid output pattern '{pattern}': {reason}")]
     InvalidPattern {
@@ -157,6 +198,41 @@ impl Error {
         }
     }
 
+    /// Creates a tokenizer vocabulary error.
+    #[must_use]
+    pub fn tokenizer(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::Tokenizer {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Creates a preset file error.
+    #[must_use]
+    pub fn preset(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::Preset {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Creates a configuration file error.
+    #[must_use]
+    pub fn config_file(path: impl Into<PathBuf>, message: impl Into<String>) -> Self {
+        Self::ConfigFile {
+            path: path.into(),
+            message: message.into(),
+        }
+    }
+
+    /// Creates a git selection error.
+    #[must_use]
+    pub fn git(message: impl Into<String>) -> Self {
+        Self::Git {
+            message: message.into(),
+        }
+    }
+
     /// Combines multiple errors into a single error.
     #[must_use]
     pub fn multiple(errors: Vec<Self>) -> Self {
@@ -1,5 +1,7 @@
+use crate::diff::FileDiff;
 use crate::error::{Error, Result};
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fs::File;
 use std::io::{BufReader, Read};
@@ -30,6 +32,43 @@ pub struct FileData {
 
     /// Estimated token count
     pub token_count: usize,
+
+    /// Diff summary when `content` holds a unified diff instead of the file
+    pub diff: Option<FileDiff>,
+
+    /// Position within the source file when this is one part of a split file
+    pub part: Option<FilePart>,
+
+    /// Line number in the source file of each line of `content`, when code
+    /// filtering or splitting means they differ from the line's position
+    pub source_lines: Option<Vec<usize>>,
+}
+
+/// Where a part of a split file sits in the file.
+///
+/// Line numbers are 1-based and refer to the content as rendered, after
+/// code filtering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FilePart {
+    /// Part number, starting at 1
+    pub index: usize,
+    /// Number of parts the file was split into
+    pub total: usize,
+    /// First line of the part
+    pub start_line: usize,
+    /// Last line of the part (inclusive)
+    pub end_line: usize,
+    /// Leading lines repeated from the previous part for context
+    pub overlap: Option<LineRange>,
+}
+
+/// An inclusive range of 1-based line numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineRange {
+    /// First line
+    pub start: usize,
+    /// Last line (inclusive)
+    pub end: usize,
 }
 
 /// File content type (text or binary).
@@ -59,6 +98,9 @@ impl FileData {
             relative_path,
             content: FileContent::Text(content),
             token_count,
+            diff: None,
+            part: None,
+            source_lines: None,
         }
     }
 
@@ -70,6 +112,9 @@ impl FileData {
             relative_path,
             content: FileContent::Binary { size },
             token_count: 0,
+            diff: None,
+            part: None,
+            source_lines: None,
         }
     }
 
@@ -108,6 +153,29 @@ impl FileData {
     pub fn line_count(&self) -> Option<usize> {
         self.content_str().map(|s| s.lines().count())
     }
+
+    /// Returns the source file line number of a 1-based line of `content`.
+    ///
+    /// Returns `None` for binary files, diffs and lines past the end.
+    #[must_use]
+    pub fn source_line(&self, line: usize) -> Option<usize> {
+        if self.diff.is_some() || line == 0 || line > self.line_count()? {
+            return None;
+        }
+        self.source_lines
+            .as_ref()
+            .map_or(Some(line), |lines| lines.get(line - 1).copied())
+    }
+
+    /// Returns the source file line number of every line of `content`,
+    /// like [`source_line`](Self::source_line) without a lookup per line.
+    pub(crate) fn source_line_numbers(&self) -> Vec<usize> {
+        let count = self.line_count().unwrap_or(0);
+        let lines = self.source_lines.as_deref().unwrap_or_default();
+        (1..=count)
+            .map(|line| lines.get(line - 1).copied().unwrap_or(line))
+            .collect()
+    }
 }
 
 /// Determines if a file is likely binary by analyzing its content.
@@ -124,7 +192,6 @@ impl FileData {
 /// Returns an error if the file cannot be opened or read.
 pub(crate) fn is_likely_binary(path: &Path) -> Result<bool> {
     const BUFFER_SIZE: usize = 8192;
-    const ASCII_THRESHOLD: f64 = 0.85;
 
     let file = File::open(path).map_err(|e| Error::io(path, e))?;
     let mut reader = BufReader::with_capacity(BUFFER_SIZE, file);
@@ -132,22 +199,30 @@ pub(crate) fn is_likely_binary(path: &Path) -> Result<bool> {
 
     let bytes_read = reader.read(&mut buffer).map_err(|e| Error::io(path, e))?;
 
-    if bytes_read == 0 {
-        return Ok(false);
-    }
+    Ok(is_binary_content(&buffer[..bytes_read]))
+}
 
-    let sample = &buffer[..bytes_read];
+/// Determines if content is likely binary from its first 8KB, using the
+/// same checks as [`is_likely_binary`].
+#[must_use]
+pub(crate) fn is_binary_content(content: &[u8]) -> bool {
+    const ASCII_THRESHOLD: f64 = 0.85;
+
+    let sample = &content[..content.len().min(8192)];
+    if sample.is_empty() {
+        return false;
+    }
 
     // Быстрая проверка на null bytes с помощью memchr
     if memchr::memchr(0, sample).is_some() {
-        return Ok(true);
+        return true;
     }
 
     // Подсчет ASCII символов
     let ascii_count = sample.iter().filter(|&&b| b < 128).count();
-    let ascii_ratio = ascii_count as f64 / bytes_read as f64;
+    let ascii_ratio = ascii_count as f64 / sample.len() as f64;
 
-    Ok(ascii_ratio < ASCII_THRESHOLD)
+    ascii_ratio < ASCII_THRESHOLD
 }
 
 /// Checks if a file extension suggests a text file.
@@ -157,7 +232,8 @@ pub(crate) fn has_text_extension(path: &Path) -> bool {
         [
             "rs", "toml", "md", "txt", "json", "yaml", "yml", "js", "ts", "jsx", "tsx", "py", "go",
             "java", "c", "cpp", "h", "hpp", "cs", "rb", "php", "html", "css", "scss", "sass",
-            "xml", "svg", "sh", "bash", "zsh", "fish", "vim", "lua",
+            "xml", "svg", "sh", "bash", "zsh", "fish", "vim", "lua", "swift", "kt", "kts", "scala",
+            "sql", "bats",
         ]
         .into_iter()
         .collect()
@@ -267,6 +343,23 @@ mod tests {
         assert_eq!(data.line_count(), Some(3));
     }
 
+    #[test]
+    fn test_source_line() {
+        let mut data = FileData::new_text(
+            PathBuf::from("test.rs"),
+            "test.rs".to_string(),
+            "fn a() {}\nfn b() {}".to_string(),
+            6,
+        );
+        assert_eq!(data.source_line(2), Some(2));
+        assert_eq!(data.source_line(3), None);
+
+        data.source_lines = Some(vec![4, 9]);
+        assert_eq!(data.source_line(1), Some(4));
+        assert_eq!(data.source_line(2), Some(9));
+        assert_eq!(data.source_line(0), None);
+    }
+
     #[test]
     fn test_line_count_binary() {
         let data = FileData::new_binary(PathBuf::from("test.exe"), "test.exe".to_string(), 1024);
@@ -3,7 +3,10 @@
 //! Provides functionality to strip tests, comments, and documentation
 //! from source code before generating prompts.
 
+use crate::lexer::{self, Language, SourceLine, SpanKind};
 use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::collections::HashSet;
+use std::ops::{Range, RangeInclusive};
 use std::path::Path;
 
 /// Configuration for file filtering with glob patterns.
@@ -38,6 +41,19 @@ impl FileFilterConfig {
         self.exclude_all_files_except = paths;
         self
     }
+
+    /// Checks that every pattern is a valid glob.
+    pub(crate) fn validate(&self) -> crate::error::Result<()> {
+        self.exclude_files
+            .iter()
+            .chain(&self.exclude_all_files_except)
+            .chain(&self.exclude_directories)
+            .try_for_each(|pattern| {
+                Glob::new(pattern).map(drop).map_err(|e| {
+                    crate::error::Error::config(format!("Invalid glob pattern '{pattern}': {e}"))
+                })
+            })
+    }
 }
 
 #[derive(Debug, Clone)]
@@ -132,7 +148,11 @@ impl FileFilter {
     }
 }
 /// Configuration for code filtering operations.
+///
+/// Start from [`FilterConfig::default()`] or one of the presets and adjust
+/// individual settings with the chainable setters.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct FilterConfig {
     /// Remove test code (e.g., #[test], #[cfg(test)])
     pub remove_tests: bool,
@@ -147,10 +167,27 @@ pub struct FilterConfig {
     pub remove_blank_lines: bool,
 
     /// Preserve copyright/license headers
+    ///
+    /// The header is the first group of comments in a file when it mentions
+    /// a copyright, license or `SPDX-License-Identifier`; it is kept even
+    /// when comments are removed.
     pub preserve_headers: bool,
 
+    /// Shorten preserved headers to their `SPDX-License-Identifier` line
+    ///
+    /// Headers without an SPDX identifier are kept whole.
+    pub collapse_headers: bool,
+
     /// Remove debug print statements (println!, dbg!, etc.)
     pub remove_debug_prints: bool,
+
+    /// Extra function or macro names whose calls are removed as debug prints
+    ///
+    /// Names are matched against the whole callee at the start of a
+    /// statement, such as `log::debug!` or `logger.debug`. A name starting
+    /// with `.` matches a method call on any receiver, such as
+    /// `.printStackTrace`.
+    pub debug_print_names: Vec<String>,
 }
 
 impl Default for FilterConfig {
@@ -161,7 +198,9 @@ impl Default for FilterConfig {
             remove_comments: false,
             remove_blank_lines: true,
             preserve_headers: true,
+            collapse_headers: false,
             remove_debug_prints: false,
+            debug_print_names: Vec::new(),
         }
     }
 }
@@ -176,7 +215,9 @@ impl FilterConfig {
             remove_comments: true,
             remove_blank_lines: true,
             preserve_headers: false,
+            collapse_headers: false,
             remove_debug_prints: true,
+            debug_print_names: Vec::new(),
         }
     }
 
@@ -189,7 +230,9 @@ impl FilterConfig {
             remove_comments: true,
             remove_blank_lines: true,
             preserve_headers: true,
+            collapse_headers: false,
             remove_debug_prints: false,
+            debug_print_names: Vec::new(),
         }
     }
 
@@ -202,9 +245,71 @@ impl FilterConfig {
             remove_comments: false,
             remove_blank_lines: true,
             preserve_headers: true,
+            collapse_headers: false,
             remove_debug_prints: true,
+            debug_print_names: Vec::new(),
         }
     }
+
+    /// Sets whether test code is removed.
+    #[must_use]
+    pub const fn remove_tests(mut self, enabled: bool) -> Self {
+        self.remove_tests = enabled;
+        self
+    }
+
+    /// Sets whether documentation comments are removed.
+    #[must_use]
+    pub const fn remove_doc_comments(mut self, enabled: bool) -> Self {
+        self.remove_doc_comments = enabled;
+        self
+    }
+
+    /// Sets whether regular comments are removed.
+    #[must_use]
+    pub const fn remove_comments(mut self, enabled: bool) -> Self {
+        self.remove_comments = enabled;
+        self
+    }
+
+    /// Sets whether blank lines are removed after filtering.
+    #[must_use]
+    pub const fn remove_blank_lines(mut self, enabled: bool) -> Self {
+        self.remove_blank_lines = enabled;
+        self
+    }
+
+    /// Sets whether copyright/license headers are kept.
+    #[must_use]
+    pub const fn preserve_headers(mut self, enabled: bool) -> Self {
+        self.preserve_headers = enabled;
+        self
+    }
+
+    /// Sets whether preserved headers are shortened to their SPDX line.
+    #[must_use]
+    pub const fn collapse_headers(mut self, enabled: bool) -> Self {
+        self.collapse_headers = enabled;
+        self
+    }
+
+    /// Sets whether debug print statements are removed.
+    #[must_use]
+    pub const fn remove_debug_prints(mut self, enabled: bool) -> Self {
+        self.remove_debug_prints = enabled;
+        self
+    }
+
+    /// Sets the extra function or macro names removed as debug prints.
+    #[must_use]
+    pub fn debug_print_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.debug_print_names = names.into_iter().map(Into::into).collect();
+        self
+    }
 }
 
 /// Main code filter that dispatches to language-specific filters.
@@ -220,25 +325,78 @@ impl CodeFilter {
         Self { config }
     }
 
+    /// Returns `true` if tests are removed and `path` names a whole test
+    /// file: `*.test.ts`/`*.spec.js` and friends, `*_test.go`,
+    /// `*_spec.rb`/`*_test.rb`, `*Test.php`, `*Tests.swift` or `*_spec.lua`.
+    #[must_use]
+    pub fn is_test_file(&self, path: &Path) -> bool {
+        if !self.config.remove_tests {
+            return false;
+        }
+
+        let (Some(stem), Some(extension)) = (
+            path.file_stem().and_then(|s| s.to_str()),
+            path.extension().and_then(|e| e.to_str()),
+        ) else {
+            return false;
+        };
+
+        match extension {
+            "js" | "ts" | "jsx" | "tsx" => {
+                matches!(Path::new(stem).extension().and_then(|e| e.to_str()), Some("test" | "spec"))
+            }
+            "go" => stem.ends_with("_test"),
+            "rb" => stem.ends_with("_spec") || stem.ends_with("_test"),
+            "php" => stem.len() > "Test".len() && stem.ends_with("Test"),
+            "swift" => stem.len() > "Tests".len() && stem.ends_with("Tests"),
+            "lua" => stem.ends_with("_spec"),
+            _ => false,
+        }
+    }
+
     /// Filters code content based on file extension and configuration.
     ///
     /// Returns filtered content or original if no filtering applies.
     #[must_use]
     pub fn filter(&self, content: &str, path: &Path) -> String {
+        self.filter_with_line_map(content, path).0
+    }
+
+    /// Filters code content like [`filter`](Self::filter), and also returns
+    /// the 1-based line number in `content` of each line of the result.
+    #[must_use]
+    pub fn filter_with_line_map(&self, content: &str, path: &Path) -> (String, Vec<usize>) {
         let extension = path
             .extension()
             .and_then(|e| e.to_str())
             .unwrap_or("");
 
-        match extension {
+        let lines = match extension {
             "rs" => RustFilter::new(&self.config).filter(content),
             "py" => PythonFilter::new(&self.config).filter(content),
             "js" | "ts" | "jsx" | "tsx" => JavaScriptFilter::new(&self.config).filter(content),
             "go" => GoFilter::new(&self.config).filter(content),
-            "java" | "kt" => JavaFilter::new(&self.config).filter(content),
+            "java" => JavaFilter::new(&self.config).filter(content),
             "c" | "cpp" | "cc" | "h" | "hpp" => CFilter::new(&self.config).filter(content),
-            _ => content.to_string(),
-        }
+            "cs" => CSharpFilter::new(&self.config).filter(content),
+            "rb" => RubyFilter::new(&self.config).filter(content),
+            "php" => PhpFilter::new(&self.config).filter(content),
+            "swift" => SwiftFilter::new(&self.config).filter(content),
+            "kt" | "kts" => KotlinFilter::new(&self.config).filter(content),
+            "scala" => ScalaFilter::new(&self.config).filter(content),
+            "sh" | "bash" | "zsh" | "bats" => ShellFilter::new(&self.config).filter(content),
+            "sql" => SqlFilter::new(&self.config).filter(content),
+            "lua" => LuaFilter::new(&self.config).filter(content),
+            _ => return (content.to_string(), (1..=content.lines().count()).collect()),
+        };
+
+        let line_map = lines.iter().map(|(number, _)| *number).collect();
+        let text = lines
+            .into_iter()
+            .map(|(_, line)| line)
+            .collect::<Vec<_>>()
+            .join("\n");
+        (text, line_map)
     }
 }
 
@@ -248,45 +406,444 @@ trait LanguageFilter {
     #[allow(dead_code)]
     fn config(&self) -> &FilterConfig;
 
-    /// Filters the content according to language rules.
-    fn filter(&self, content: &str) -> String;
+    /// Filters the content according to language rules, returning each kept
+    /// line with its 1-based line number in `content`.
+    fn filter(&self, content: &str) -> Vec<(usize, String)>;
+}
+
+/// Lexes `content` and removes the comments the configuration asks for.
+fn source_lines(config: &FilterConfig, content: &str, language: Language) -> Vec<SourceLine> {
+    let header = config
+        .preserve_headers
+        .then(|| Header::find(content, language))
+        .flatten();
+    let header_range = header.as_ref().map_or(0..0, |header| header.range.clone());
+
+    let mut lines = lexer::strip_comments(content, language, |span| {
+        if header_range.contains(&span.range.start) {
+            return false;
+        }
+        match span.kind {
+            SpanKind::DocComment => config.remove_doc_comments,
+            SpanKind::LineComment | SpanKind::BlockComment => config.remove_comments,
+            SpanKind::Code | SpanKind::String => false,
+        }
+    });
+
+    if let Some(header) = header.filter(|_| config.collapse_headers) {
+        header.collapse(&mut lines, language);
+    }
+    if config.remove_debug_prints {
+        strip_debug_prints(&mut lines, language, &config.debug_print_names);
+    }
+    lines
+}
+
+/// Calls removed as debug prints in each language.
+///
+/// A name starting with `.` matches a method call on any receiver.
+const fn debug_print_names(language: Language) -> &'static [&'static str] {
+    match language {
+        Language::Rust => &["println!", "eprintln!", "print!", "eprint!", "dbg!"],
+        Language::Python => &["print", "pprint", "pprint.pprint", "breakpoint", "pdb.set_trace"],
+        Language::JavaScript => &["console.log", "console.debug", "console.trace"],
+        Language::Go => &[
+            "fmt.Print", "fmt.Printf", "fmt.Println", "log.Print", "log.Printf", "log.Println",
+            "print", "println",
+        ],
+        Language::Java | Language::Kotlin => &[
+            "System.out.print", "System.out.printf", "System.out.println", "System.err.print",
+            "System.err.printf", "System.err.println", ".printStackTrace", "print", "println",
+        ],
+        Language::C => &["printf", "std::printf", "std::cout", "std::cerr", "cout", "cerr"],
+        Language::CSharp => &[
+            "Console.Write", "Console.WriteLine", "System.Console.WriteLine", "Debug.Write",
+            "Debug.WriteLine", "Debug.Print", "Trace.WriteLine",
+        ],
+        Language::Ruby => &["p", "pp", "puts", "print", "binding.pry", "binding.irb", "byebug", "debugger"],
+        Language::Php => &["var_dump", "print_r", "var_export", "debug_zval_dump", "dump", "dd", "error_log"],
+        Language::Swift => &["print", "debugPrint", "dump", "NSLog"],
+        Language::Scala => &["println", "print", "printf", "Console.println", ".printStackTrace"],
+        Language::Lua => &["print"],
+        Language::Shell | Language::Sql => &[],
+    }
+}
 
-    /// Checks if a line is a comment.
-    fn is_comment_line(&self, line: &str) -> bool;
+/// Returns `true` for languages whose calls may leave out parentheses, so
+/// that a debug print is also a line such as `puts x` or `binding.pry`.
+const fn has_paren_free_calls(language: Language) -> bool {
+    matches!(language, Language::Ruby | Language::Shell)
+}
 
-    /// Checks if a line is a doc comment.
-    fn is_doc_comment(&self, line: &str) -> bool;
+/// A debug print statement being removed.
+enum DebugPrint {
+    /// A call, with its open parentheses
+    Call(i32),
 
-    /// Removes comments from a line while preserving strings.
-    /// Removes comments from a line while preserving strings.
-    fn strip_line_comment(&self, line: &str, _comment_start: &str) -> String {
-        let mut in_string = false;
-        let mut escape_next = false;
-        let chars: Vec<char> = line.chars().collect();
+    /// A `<<` stream statement, which ends at `;`
+    Stream,
+}
 
-        for i in 0..chars.len() {
-            if escape_next {
-                escape_next = false;
-                continue;
+/// Removes statements that start with a call to a debug print name, up to
+/// the parenthesis that closes the call and an optional `;`, and
+/// `std::cout << ...` statements up to their `;`. Code after the statement
+/// on its last line is kept. In Ruby and shell scripts, a call without
+/// parentheses is removed with its line when its name is not a parameter or
+/// local variable of the enclosing method and it has an argument, unless it
+/// is a method call such as `binding.pry` or a breakpoint such as `byebug`.
+///
+/// Parentheses are counted in code only, so a call may span several lines
+/// and hold parentheses inside strings.
+fn strip_debug_prints(lines: &mut Vec<SourceLine>, language: Language, extra: &[String]) {
+    let names: Vec<&str> = debug_print_names(language)
+        .iter()
+        .copied()
+        .chain(extra.iter().map(String::as_str))
+        .collect();
+    let is_debug_print = |callee: &str| {
+        names.iter().any(|name| {
+            callee == *name || (name.starts_with('.') && callee.len() > name.len() && callee.ends_with(name))
+        })
+    };
+    let split_callee = |code: &str| {
+        let code = code.trim();
+        let end = code
+            .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '$' | '.' | ':' | '!')))
+            .unwrap_or(code.len());
+        let (callee, rest) = code.split_at(end);
+        (callee.to_string(), rest.trim_start().to_string())
+    };
+
+    let paren_free = has_paren_free_calls(language);
+    let mut locals: HashSet<String> = HashSet::new();
+    let mut statement = None;
+    lines.retain_mut(|line| {
+        if paren_free && !line.continued {
+            let (callee, rest) = split_callee(&line.code);
+            if matches!(callee.as_str(), "def" | "class" | "module") {
+                locals.clear();
+            } else if is_assignment(&rest) {
+                locals.insert(callee.clone());
             }
+            let parameters = match callee.as_str() {
+                "def" => rest.split_once(|c: char| c == '(' || c.is_whitespace()).map(|(_, parameters)| parameters),
+                _ => rest.split_once('|').and_then(|(_, rest)| rest.split_once('|')).map(|(parameters, _)| parameters),
+            };
+            locals.extend(
+                parameters
+                    .unwrap_or_default()
+                    .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                    .filter(|name| name.starts_with(|c: char| c.is_lowercase() || c == '_'))
+                    .map(str::to_string),
+            );
+        }
 
-            match chars[i] {
-                '\\' if in_string => {
-                    escape_next = true;
+        let mut at_start = !line.continued;
+        loop {
+            if statement.is_none() {
+                if !at_start {
+                    return true;
                 }
-                '"' => {
-                    in_string = !in_string;
+                let (callee, rest) = split_callee(&line.code);
+                if !is_debug_print(&callee) {
+                    return true;
                 }
-                '/' if !in_string && i + 1 < chars.len() && chars[i + 1] == '/' => {
-                    // Found comment outside of string
-                    return line[..i].trim_end().to_string();
+                if rest.starts_with('(') {
+                    statement = Some(DebugPrint::Call(0));
+                } else if rest.starts_with("<<") {
+                    statement = Some(DebugPrint::Stream);
+                } else {
+                    let removed = paren_free
+                        && !locals.contains(&callee)
+                        && (callee.contains('.')
+                            || matches!(callee.as_str(), "byebug" | "debugger")
+                            || has_argument(&line.text, &callee));
+                    return !removed;
                 }
-                _ => {}
             }
+
+            let Some(end) = statement.as_mut().and_then(|statement| statement_end(statement, &line.code)) else {
+                return false;
+            };
+            statement = None;
+            if !cut_statement(line, end) {
+                return false;
+            }
+            at_start = true;
+        }
+    });
+}
+
+/// Returns `true` if `callee` at the start of `text` is followed by an
+/// argument without parentheses, as in `puts "x"`.
+fn has_argument(text: &str, callee: &str) -> bool {
+    text.trim_start()
+        .strip_prefix(callee)
+        .is_some_and(|rest| {
+            rest.starts_with(char::is_whitespace)
+                && rest
+                    .trim_start()
+                    .starts_with(|c: char| c.is_alphanumeric() || matches!(c, '_' | ':' | '@' | '$' | '"' | '\'' | '['))
+        })
+}
+
+/// Returns `true` if `rest`, the code after a name, assigns to the name.
+fn is_assignment(rest: &str) -> bool {
+    let rest = ["||", "&&", "+", "-", "*", "/"]
+        .iter()
+        .find_map(|operator| rest.strip_prefix(operator))
+        .unwrap_or(rest);
+    rest.starts_with('=') && !rest.starts_with("==") && !rest.starts_with("=~") && !rest.starts_with("=>")
+}
+
+/// Advances `statement` over `code` and returns the char index just past
+/// its end, or `None` if it continues on the next line.
+fn statement_end(statement: &mut DebugPrint, code: &str) -> Option<usize> {
+    code.chars().enumerate().find_map(|(index, ch)| match statement {
+        DebugPrint::Call(depth) => {
+            match ch {
+                '(' => *depth += 1,
+                ')' => *depth -= 1,
+                _ => return None,
+            }
+            (*depth <= 0).then_some(index + 1)
+        }
+        DebugPrint::Stream => (ch == ';').then_some(index + 1),
+    })
+}
+
+/// Removes a statement ending at char index `end` from the start of `line`,
+/// with a `;` that follows it, keeping the indentation. Returns `false` if
+/// nothing but whitespace is left.
+fn cut_statement(line: &mut SourceLine, end: usize) -> bool {
+    let mut chars = line.text.chars().zip(line.code.chars()).skip(end).peekable();
+    let mut end = end;
+    let mut semicolon = true;
+    while let Some(&(text, code)) = chars.peek() {
+        if text.is_whitespace() || (semicolon && text == ';' && code == ';') {
+            semicolon &= text != ';';
+            end += 1;
+            chars.next();
+        } else {
+            break;
+        }
+    }
+
+    let indent = line.text.chars().take_while(|c| c.is_whitespace()).count();
+    let cut = |text: &str| text.chars().take(indent).chain(text.chars().skip(end)).collect::<String>();
+    line.text = cut(&line.text);
+    line.code = cut(&line.code);
+    !line.text.trim().is_empty()
+}
+
+/// Words that mark a leading comment group as a copyright or license header.
+const HEADER_KEYWORDS: &[&str] = &["copyright", "license", "licence", "spdx-license-identifier", "©"];
+
+/// A leading copyright or license comment block.
+struct Header {
+    /// Byte range from the first to the last comment
+    range: Range<usize>,
+
+    /// Lines the header spans
+    lines: RangeInclusive<usize>,
+
+    /// Line number and value of the `SPDX-License-Identifier`, if any
+    spdx: Option<(usize, String)>,
+}
+
+impl Header {
+    /// Returns the first group of comments in `content` if it is a header.
+    ///
+    /// The group is either a single block comment or a run of line comments
+    /// of the same kind with no code and no blank line between them; only
+    /// whitespace may precede it.
+    fn find(content: &str, language: Language) -> Option<Self> {
+        let mut group: Option<(SpanKind, Range<usize>)> = None;
+
+        for span in lexer::lex(content, language) {
+            let text = &content[span.range.clone()];
+            let block = span.kind == SpanKind::BlockComment || text.starts_with("/*");
+            match (&mut group, span.kind) {
+                (None, SpanKind::Code) if text.trim().is_empty() => {}
+                (None, kind) if kind.is_comment() => {
+                    group = Some((kind, span.range));
+                    if block {
+                        break;
+                    }
+                }
+                (Some(_), SpanKind::Code) if text.trim().is_empty() && text.matches('\n').count() <= 1 => {}
+                (Some((kind, range)), next) if *kind == next && !block => range.end = span.range.end,
+                _ => break,
+            }
+        }
+
+        let (_, range) = group?;
+        let text = content[range.clone()].to_lowercase();
+        if !HEADER_KEYWORDS.iter().any(|keyword| text.contains(keyword)) {
+            return None;
+        }
+
+        let line_of = |offset: usize| content[..offset].matches('\n').count() + 1;
+        let spdx = content[range.clone()]
+            .find("SPDX-License-Identifier:")
+            .map(|index| {
+                let start = range.start + index;
+                let value = content[start..range.end]
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+                    .trim_start_matches("SPDX-License-Identifier:")
+                    .trim()
+                    .trim_end_matches("*/")
+                    .trim_end();
+                (line_of(start), value.to_string())
+            })
+            .filter(|(_, value)| !value.is_empty());
+
+        Some(Self {
+            lines: line_of(range.start)..=line_of(range.end - 1),
+            range,
+            spdx,
+        })
+    }
+
+    /// Replaces the comment-only lines of the header with a single
+    /// `SPDX-License-Identifier` line comment.
+    fn collapse(self, lines: &mut Vec<SourceLine>, language: Language) {
+        let Some((spdx_line, license)) = self.spdx else {
+            return;
+        };
+        let marker = language.line_comment();
+
+        lines.retain_mut(|line| {
+            if !self.lines.contains(&line.number) || !line.code.trim().is_empty() {
+                return true;
+            }
+            if line.number == spdx_line {
+                line.text = format!("{marker} SPDX-License-Identifier: {license}");
+                return true;
+            }
+            false
+        });
+    }
+}
+
+/// Returns `true` if a line is left out as empty: it only held removed
+/// comments, or it is blank and blank lines are removed.
+fn is_dropped(config: &FilterConfig, line: &SourceLine) -> bool {
+    line.emptied || (config.remove_blank_lines && line.is_blank())
+}
+
+/// How a test block found by a language filter ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TestBlock {
+    /// Where its brackets balance again, as in `it("x", () => ...);`
+    Brackets,
+
+    /// Where its brackets balance again after a `{` has been opened, so
+    /// that annotations and a signature split over several lines are
+    /// followed to the body, or at the end of a declaration without a
+    /// body (`;`) or with an expression body (`= ...`)
+    Braces,
+}
+
+/// Removes comments, blank lines and, when tests are removed, the test
+/// blocks starting at lines for which `test_start` returns a [`TestBlock`].
+///
+/// Brackets are counted in code only, so braces inside strings and
+/// comments are ignored.
+fn filter_test_blocks(
+    config: &FilterConfig,
+    lines: Vec<SourceLine>,
+    test_start: impl Fn(&str) -> Option<TestBlock>,
+) -> Vec<(usize, String)> {
+    let mut result = Vec::new();
+    // Bracket depth, whether a brace was opened and whether an expression
+    // body was started with `=`, inside a test block
+    let mut block: Option<(TestBlock, usize, bool, bool)> = None;
+
+    for line in lines {
+        let code = line.code.as_str();
+
+        if config.remove_tests && block.is_none() && !line.continued {
+            block = test_start(code.trim_start()).map(|kind| (kind, 0, false, false));
+        }
+
+        if let Some((kind, depth, opened_brace, expression)) = &mut block {
+            let mut previous = ' ';
+            let mut chars = code.chars().peekable();
+            while let Some(ch) = chars.next() {
+                match ch {
+                    '(' | '[' => *depth += 1,
+                    '{' => {
+                        *depth += 1;
+                        *opened_brace = true;
+                    }
+                    ')' | ']' | '}' => *depth = depth.saturating_sub(1),
+                    '=' if *depth == 0
+                        && !matches!(previous, '=' | '!' | '<' | '>')
+                        && !matches!(chars.peek(), Some('=' | '>')) =>
+                    {
+                        *expression = true;
+                    }
+                    _ => {}
+                }
+                previous = ch;
+            }
+
+            let trimmed = code.trim_end();
+            let declaration_ended = (*expression && !trimmed.ends_with('=')) || trimmed.ends_with(';');
+            if *depth == 0 && (*opened_brace || *kind == TestBlock::Brackets || declaration_ended) {
+                block = None;
+            }
+            continue;
+        }
+
+        if is_dropped(config, &line) {
+            continue;
         }
 
-        line.to_string()
+        result.push((line.number, line.text));
+    }
+
+    result
+}
+
+/// Returns the identifier at the start of `code`.
+fn leading_ident(code: &str) -> &str {
+    let end = code
+        .find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$'))
+        .unwrap_or(code.len());
+    &code[..end]
+}
+
+/// Returns `code` without its leading `modifiers`, such as `public` or
+/// `override`.
+fn strip_modifiers<'a>(mut code: &'a str, modifiers: &[&str]) -> &'a str {
+    while let Some(rest) = modifiers.iter().find_map(|modifier| {
+        code.strip_prefix(modifier)
+            .filter(|rest| rest.starts_with(char::is_whitespace))
+    }) {
+        code = rest.trim_start();
     }
+    code
+}
+
+/// Returns the name of the annotation or attribute opened by `marker` at
+/// the start of `code`, such as `Test` for `@Test` or `[Test]`.
+fn annotation<'a>(code: &'a str, marker: &str) -> Option<&'a str> {
+    code.strip_prefix(marker).map(leading_ident)
+}
+
+/// Returns the type named after the last `separator` in a class
+/// declaration, such as `TestCase` in `class FooTest extends TestCase {`.
+fn supertype<'a>(declaration: &'a str, separator: &str) -> Option<&'a str> {
+    let (_, rest) = declaration.rsplit_once(separator)?;
+    let rest = rest.trim_start();
+    let end = rest
+        .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '.' | ':' | '\\')))
+        .unwrap_or(rest.len());
+    Some(&rest[..end])
 }
 
 /// Rust-specific code filter.
@@ -300,8 +857,8 @@ impl<'a> RustFilter<'a> {
     }
 
     /// Checks if we're entering a test module or function.
-    fn is_test_start(&self, line: &str) -> bool {
-        let trimmed = line.trim();
+    fn is_test_start(code: &str) -> bool {
+        let trimmed = code.trim();
         trimmed.starts_with("#[test]")
             || trimmed.starts_with("#[cfg(test)]")
             || trimmed.starts_with("#[tokio::test]")
@@ -309,131 +866,37 @@ impl<'a> RustFilter<'a> {
     }
 
     /// Checks if a line contains test-related attributes.
-    fn has_test_attribute(&self, line: &str) -> bool {
-        let trimmed = line.trim();
+    fn has_test_attribute(code: &str) -> bool {
+        let trimmed = code.trim();
         trimmed.contains("#[test")
             || trimmed.contains("#[cfg(test")
             || trimmed.contains("#[should_panic")
             || trimmed.contains("#[ignore")
     }
 
-    /// Checks if a line contains a debug print macro.
-    fn is_debug_print(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("println!")
-            || trimmed.starts_with("eprintln!")
-            || trimmed.starts_with("dbg!")
-            || trimmed.starts_with("print!")
-            || trimmed.starts_with("eprint!")
-            || trimmed.contains("println!(")
-            || trimmed.contains("eprintln!(")
-            || trimmed.contains("dbg!(")
-    }
-
-    /// Removes debug print statements from a line.
-    /// Returns (processed_line, is_multiline_print)
-    fn strip_debug_prints(&self, line: &str) -> (String, bool) {
-        if !self.config.remove_debug_prints {
-            return (line.to_string(), false);
-        }
-
-        let trimmed = line.trim();
-
-        // Check if line starts with a debug print macro
-        if self.is_debug_print(trimmed) {
-            // Count parentheses to see if it's a complete statement
-            let open_count = line.matches('(').count();
-            let close_count = line.matches(')').count();
-
-            if open_count > close_count {
-                // Multi-line print, need to skip subsequent lines
-                return (String::new(), true);
-            } else {
-                // Single line print, skip it
-                return (String::new(), false);
-            }
-        }
-
-        (line.to_string(), false)
-    }
 }
 
-impl<'a> LanguageFilter for RustFilter<'a> {
+impl LanguageFilter for RustFilter<'_> {
     fn config(&self) -> &FilterConfig {
         self.config
     }
 
-    fn is_comment_line(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("//") && !trimmed.starts_with("///")
-    }
-
-    fn is_doc_comment(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("///") || trimmed.starts_with("//!")
-    }
-
-    fn filter(&self, content: &str) -> String {
-        let lines: Vec<&str> = content.lines().collect();
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
         let mut result = Vec::new();
         let mut in_test_block = false;
-        let mut in_block_comment = false;
-        let mut in_doc_comment = false;
-        let mut in_multiline_print = false;
         let mut brace_depth = 0;
         let mut test_block_depth = 0;
 
-        for line in lines {
-            let trimmed = line.trim();
-
-            // Handle multi-line print statements
-            if in_multiline_print {
-                let close_count = line.matches(')').count();
-                let open_count = line.matches('(').count();
+        for line in source_lines(self.config, content, Language::Rust) {
+            let code = line.code.as_str();
 
-                if close_count > open_count {
-                    in_multiline_print = false;
-                }
-                continue;
-            }
-
-            // Handle block comments
-            if trimmed.starts_with("/*") {
-                in_block_comment = true;
-                in_doc_comment = trimmed.starts_with("/**") || trimmed.starts_with("/*!");
-            }
-
-            if in_block_comment {
-                if trimmed.ends_with("*/") {
-                    in_block_comment = false;
-                    in_doc_comment = false;
-                }
-
-                let should_skip = if in_doc_comment {
-                    self.config.remove_doc_comments
-                } else {
-                    self.config.remove_comments
-                };
-
-                if !should_skip {
-                    result.push(line.to_string());
-                }
-                continue;
-            }
-
-            // Skip doc comments
-            if self.config.remove_doc_comments && self.is_doc_comment(line) {
-                continue;
-            }
-
-            // Skip regular comments
-            if self.config.remove_comments && self.is_comment_line(line) {
+            if line.emptied {
                 continue;
             }
 
             // Handle test blocks
             if self.config.remove_tests {
-                if self.is_test_start(line) || self.has_test_attribute(line) {
+                if Self::is_test_start(code) || Self::has_test_attribute(code) {
                     in_test_block = true;
                     test_block_depth = 0;
                     continue;
@@ -441,7 +904,7 @@ impl<'a> LanguageFilter for RustFilter<'a> {
 
                 if in_test_block {
                     // Track braces to find end of test block
-                    for ch in trimmed.chars() {
+                    for ch in code.chars() {
                         match ch {
                             '{' => brace_depth += 1,
                             '}' => {
@@ -457,28 +920,15 @@ impl<'a> LanguageFilter for RustFilter<'a> {
                 }
             }
 
-            // Remove debug prints
-            let (processed_line, is_multiline) = self.strip_debug_prints(line);
-            if is_multiline {
-                in_multiline_print = true;
-                continue;
-            }
-
-            // Remove inline comments if configured
-            let mut final_line = processed_line;
-            if self.config.remove_comments && !final_line.is_empty() {
-                final_line = self.strip_line_comment(&final_line, "//");
-            }
-
             // Skip blank lines if configured
-            if self.config.remove_blank_lines && final_line.trim().is_empty() {
+            if self.config.remove_blank_lines && line.is_blank() {
                 continue;
             }
 
-            result.push(final_line);
+            result.push((line.number, line.text));
         }
 
-        result.join("\n")
+        result
     }
 }
 
@@ -492,84 +942,49 @@ impl<'a> PythonFilter<'a> {
         Self { config }
     }
 
-    fn is_test_function(&self, line: &str) -> bool {
-        let trimmed = line.trim();
+    fn is_test_function(code: &str) -> bool {
+        let trimmed = code.trim();
         (trimmed.starts_with("def test_") || trimmed.starts_with("async def test_"))
             && trimmed.contains('(')
     }
 
-    fn is_test_decorator(&self, line: &str) -> bool {
-        let trimmed = line.trim();
+    fn is_test_decorator(code: &str) -> bool {
+        let trimmed = code.trim();
         trimmed.starts_with("@pytest")
             || trimmed.starts_with("@unittest")
             || trimmed == "@test"
     }
 }
 
-impl<'a> LanguageFilter for PythonFilter<'a> {
+impl LanguageFilter for PythonFilter<'_> {
     fn config(&self) -> &FilterConfig {
         self.config
     }
 
-    fn is_comment_line(&self, line: &str) -> bool {
-        line.trim().starts_with('#')
-    }
-
-    fn is_doc_comment(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("\"\"\"") || trimmed.starts_with("'''")
-    }
-
-    fn filter(&self, content: &str) -> String {
-        let lines: Vec<&str> = content.lines().collect();
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
         let mut result = Vec::new();
-        let mut in_docstring = false;
         let mut in_test_function = false;
-        let _indent_level = 0;
         let mut test_indent = 0;
 
-        for line in lines {
-            let trimmed = line.trim();
-
-            // Handle docstrings
-            if trimmed.starts_with("\"\"\"") || trimmed.starts_with("'''") {
-                in_docstring = !in_docstring;
-                if self.config.remove_doc_comments {
-                    continue;
-                }
-            }
-
-            if in_docstring {
-                if self.config.remove_doc_comments {
-                    continue;
-                }
-                result.push(line.to_string());
-                continue;
-            }
-
-            // Skip comments
-            if self.config.remove_comments && self.is_comment_line(line) {
+        for line in source_lines(self.config, content, Language::Python) {
+            if line.emptied {
                 continue;
             }
 
             // Handle test functions
             if self.config.remove_tests {
-                let current_indent = line.len() - line.trim_start().len();
-
-                if self.is_test_decorator(line) {
-                    in_test_function = true;
-                    test_indent = current_indent;
-                    continue;
-                }
+                let code = line.code.as_str();
+                let current_indent = code.len() - code.trim_start().len();
 
-                if self.is_test_function(line) {
+                if Self::is_test_decorator(code) || Self::is_test_function(code) {
                     in_test_function = true;
                     test_indent = current_indent;
                     continue;
                 }
 
                 if in_test_function {
-                    if !trimmed.is_empty() && current_indent <= test_indent {
+                    // Lines inside multi-line strings do not end the function
+                    if !line.continued && !code.trim().is_empty() && current_indent <= test_indent {
                         in_test_function = false;
                     } else {
                         continue;
@@ -578,14 +993,14 @@ impl<'a> LanguageFilter for PythonFilter<'a> {
             }
 
             // Skip blank lines if configured
-            if self.config.remove_blank_lines && trimmed.is_empty() {
+            if self.config.remove_blank_lines && line.is_blank() {
                 continue;
             }
 
-            result.push(line.to_string());
+            result.push((line.number, line.text));
         }
 
-        result.join("\n")
+        result
     }
 }
 
@@ -599,87 +1014,27 @@ impl<'a> JavaScriptFilter<'a> {
         Self { config }
     }
 
-    #[allow(dead_code)]
-    fn is_test_block(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("describe(")
-            || trimmed.starts_with("it(")
-            || trimmed.starts_with("test(")
-            || trimmed.starts_with("expect(")
+    /// Checks if a line starts a Jest/Mocha/Vitest suite, test or hook,
+    /// including modifiers such as `describe.only(` and `it.each(`.
+    fn is_test_block(code: &str) -> bool {
+        const NAMES: &[&str] = &[
+            "describe", "it", "test", "xdescribe", "xit", "xtest", "fdescribe", "fit", "suite",
+            "beforeEach", "afterEach", "beforeAll", "afterAll",
+        ];
+
+        let name = leading_ident(code);
+        NAMES.contains(&name) && matches!(code[name.len()..].trim_start().chars().next(), Some('(' | '.'))
     }
 }
 
-impl<'a> LanguageFilter for JavaScriptFilter<'a> {
+impl LanguageFilter for JavaScriptFilter<'_> {
     fn config(&self) -> &FilterConfig {
         self.config
     }
 
-    fn is_comment_line(&self, line: &str) -> bool {
-        line.trim().starts_with("//")
-    }
-
-    fn is_doc_comment(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("/**") || trimmed.starts_with("///")
-    }
-
-    fn filter(&self, content: &str) -> String {
-        let lines: Vec<&str> = content.lines().collect();
-        let mut result = Vec::new();
-        let mut in_block_comment = false;
-        let mut in_doc_comment = false;
-
-        for line in lines {
-            let trimmed = line.trim();
-
-            // Handle block comments
-            if trimmed.starts_with("/*") {
-                in_block_comment = true;
-                in_doc_comment = trimmed.starts_with("/**");
-            }
-
-            if in_block_comment {
-                if trimmed.ends_with("*/") {
-                    in_block_comment = false;
-                    in_doc_comment = false;
-                }
-
-                let should_skip = if in_doc_comment {
-                    self.config.remove_doc_comments
-                } else {
-                    self.config.remove_comments
-                };
-
-                if !should_skip {
-                    result.push(line.to_string());
-                }
-                continue;
-            }
-
-            // Skip comments
-            if self.config.remove_comments && self.is_comment_line(line) {
-                continue;
-            }
-
-            if self.config.remove_doc_comments && self.is_doc_comment(line) {
-                continue;
-            }
-
-            // Skip blank lines if configured
-            if self.config.remove_blank_lines && trimmed.is_empty() {
-                continue;
-            }
-
-            // Remove inline comments
-            let mut processed_line = line.to_string();
-            if self.config.remove_comments {
-                processed_line = self.strip_line_comment(&processed_line, "//");
-            }
-
-            result.push(processed_line);
-        }
-
-        result.join("\n")
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::JavaScript);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_block(code).then_some(TestBlock::Brackets))
     }
 }
 
@@ -692,27 +1047,34 @@ impl<'a> GoFilter<'a> {
     const fn new(config: &'a FilterConfig) -> Self {
         Self { config }
     }
-}
 
-impl<'a> LanguageFilter for GoFilter<'a> {
-    fn config(&self) -> &FilterConfig {
-        self.config
-    }
+    /// Checks if a line declares a `TestXxx`, `BenchmarkXxx`, `ExampleXxx`
+    /// or `FuzzXxx` function.
+    fn is_test_function(code: &str) -> bool {
+        let Some(signature) = code.strip_prefix("func ") else {
+            return false;
+        };
+        let name = leading_ident(signature.trim_start());
 
-    fn is_comment_line(&self, line: &str) -> bool {
-        line.trim().starts_with("//")
+        ["Test", "Benchmark", "Example", "Fuzz"].iter().any(|prefix| {
+            name.strip_prefix(prefix)
+                .is_some_and(|rest| !rest.starts_with(|c: char| c.is_lowercase()))
+        })
     }
+}
 
-    fn is_doc_comment(&self, _line: &str) -> bool {
-        false // Go doesn't have special doc comments
+impl LanguageFilter for GoFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
     }
 
-    fn filter(&self, content: &str) -> String {
-        JavaScriptFilter::new(self.config).filter(content)
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::Go);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_function(code).then_some(TestBlock::Braces))
     }
 }
 
-/// Java/Kotlin code filter.
+/// Java code filter.
 struct JavaFilter<'a> {
     config: &'a FilterConfig,
 }
@@ -722,8 +1084,8 @@ impl<'a> JavaFilter<'a> {
         Self { config }
     }
 
-    fn is_test_annotation(&self, line: &str) -> bool {
-        let trimmed = line.trim();
+    fn is_test_annotation(code: &str) -> bool {
+        let trimmed = code.trim();
         trimmed.starts_with("@Test")
             || trimmed.starts_with("@org.junit")
             || trimmed.starts_with("@BeforeEach")
@@ -731,118 +1093,422 @@ impl<'a> JavaFilter<'a> {
     }
 }
 
-impl<'a> LanguageFilter for JavaFilter<'a> {
+impl LanguageFilter for JavaFilter<'_> {
     fn config(&self) -> &FilterConfig {
         self.config
     }
 
-    fn is_comment_line(&self, line: &str) -> bool {
-        line.trim().starts_with("//")
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::Java);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_annotation(code).then_some(TestBlock::Braces))
     }
+}
+
+/// C/C++ code filter.
+struct CFilter<'a> {
+    config: &'a FilterConfig,
+}
 
-    fn is_doc_comment(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("/**")
+impl<'a> CFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
     }
 
-    fn filter(&self, content: &str) -> String {
-        let lines: Vec<&str> = content.lines().collect();
-        let mut result = Vec::new();
-        let mut in_block_comment = false;
-        let mut in_doc_comment = false;
-        let mut skip_next_method = false;
+    /// Checks if a line starts a gtest, Catch2 or doctest test case.
+    fn is_test_macro(code: &str) -> bool {
+        const MACROS: &[&str] = &[
+            "TEST", "TEST_F", "TEST_P", "TYPED_TEST", "TYPED_TEST_P", "TEST_CASE",
+            "TEST_CASE_METHOD", "TEMPLATE_TEST_CASE", "SCENARIO",
+        ];
 
-        for line in lines {
-            let trimmed = line.trim();
+        let name = leading_ident(code);
+        MACROS.contains(&name) && code[name.len()..].trim_start().starts_with('(')
+    }
+}
 
-            // Check for test annotations
-            if self.config.remove_tests && self.is_test_annotation(line) {
-                skip_next_method = true;
-                continue;
-            }
+impl LanguageFilter for CFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
+    }
 
-            // Handle block comments
-            if trimmed.starts_with("/*") {
-                in_block_comment = true;
-                in_doc_comment = trimmed.starts_with("/**");
-            }
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::C);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_macro(code).then_some(TestBlock::Braces))
+    }
+}
 
-            if in_block_comment {
-                if trimmed.ends_with("*/") {
-                    in_block_comment = false;
-                    in_doc_comment = false;
-                }
+/// C# code filter.
+struct CSharpFilter<'a> {
+    config: &'a FilterConfig,
+}
 
-                let should_skip = if in_doc_comment {
-                    self.config.remove_doc_comments
-                } else {
-                    self.config.remove_comments
-                };
+impl<'a> CSharpFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
+    }
 
-                if !should_skip {
-                    result.push(line.to_string());
-                }
-                continue;
-            }
+    /// Checks if a line holds an NUnit, xUnit or MSTest attribute marking
+    /// a test, a test fixture or a setup method.
+    fn is_test_attribute(code: &str) -> bool {
+        const ATTRIBUTES: &[&str] = &[
+            "Test", "TestCase", "TestCaseSource", "SetUp", "TearDown", "OneTimeSetUp",
+            "OneTimeTearDown", "TestFixture", "Fact", "Theory", "InlineData", "MemberData",
+            "ClassData", "TestMethod", "DataTestMethod", "DataRow", "TestInitialize", "TestCleanup",
+            "TestClass",
+        ];
+
+        annotation(code, "[").is_some_and(|name| ATTRIBUTES.contains(&name))
+    }
+}
+
+impl LanguageFilter for CSharpFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
+    }
 
-            // Skip test methods
-            if skip_next_method {
-                if trimmed.contains('{') {
-                    // Found method start, now skip until closing brace
-                    let brace_count = trimmed.matches('{').count() as i32
-                        - trimmed.matches('}').count() as i32;
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::CSharp);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_attribute(code).then_some(TestBlock::Braces))
+    }
+}
 
-                    if brace_count == 0 {
-                        skip_next_method = false;
-                    }
+/// Ruby code filter.
+struct RubyFilter<'a> {
+    config: &'a FilterConfig,
+}
+
+impl<'a> RubyFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Checks if a line starts an RSpec example group or example, a
+    /// Minitest `test_` method or a Minitest or `Test::Unit` test class.
+    fn is_test_start(code: &str) -> bool {
+        const NAMES: &[&str] = &[
+            "describe", "context", "it", "specify", "xdescribe", "xcontext", "xit", "fdescribe",
+            "fcontext", "fit", "shared_examples", "shared_examples_for", "shared_context",
+            "feature", "scenario",
+        ];
+
+        let name = leading_ident(code);
+        let rest = &code[name.len()..];
+        if NAMES.contains(&name) {
+            return (rest.is_empty() || rest.starts_with([' ', '(', '{']))
+                && !rest.trim_start().starts_with('=');
+        }
+
+        code.starts_with("RSpec.describe")
+            || code.strip_prefix("def ").is_some_and(|rest| rest.trim_start().starts_with("test_"))
+            || (code.starts_with("class ")
+                && supertype(code, " < ")
+                    .is_some_and(|name| name.ends_with("Minitest::Test") || name.ends_with("TestCase")))
+    }
+}
+
+impl LanguageFilter for RubyFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
+    }
+
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let mut result = Vec::new();
+        // Indentation of the line that started the test block being removed
+        let mut test_indent = None;
+
+        for line in source_lines(self.config, content, Language::Ruby) {
+            let code = line.code.as_str();
+            let trimmed = code.trim();
+            let indent = code.len() - code.trim_start().len();
+
+            // A block ends at the next line indented no deeper than its
+            // start, which is dropped too when it is the closing `end`/`}`
+            if let Some(start) = test_indent {
+                if line.continued || trimmed.is_empty() || indent > start {
+                    continue;
+                }
+                test_indent = None;
+                if indent == start && (leading_ident(trimmed) == "end" || trimmed.starts_with('}')) {
+                    continue;
                 }
-                continue;
             }
 
-            // Skip comments
-            if self.config.remove_comments && self.is_comment_line(line) {
+            if self.config.remove_tests && !line.continued && Self::is_test_start(trimmed) {
+                test_indent = Some(indent);
                 continue;
             }
 
-            // Skip blank lines
-            if self.config.remove_blank_lines && trimmed.is_empty() {
+            if is_dropped(self.config, &line) {
                 continue;
             }
 
-            result.push(line.to_string());
+            result.push((line.number, line.text));
         }
 
-        result.join("\n")
+        result
     }
 }
 
-/// C/C++ code filter.
-struct CFilter<'a> {
+/// PHP code filter.
+struct PhpFilter<'a> {
     config: &'a FilterConfig,
 }
 
-impl<'a> CFilter<'a> {
+impl<'a> PhpFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Checks if a line starts a Pest test or hook, a PHPUnit test
+    /// attribute, or a PHPUnit test class, which holds the `test*` methods.
+    fn test_start(code: &str) -> Option<TestBlock> {
+        const PEST: &[&str] = &["test", "it", "describe", "beforeEach", "afterEach", "beforeAll", "afterAll"];
+        const ATTRIBUTES: &[&str] = &["Test", "DataProvider", "TestWith", "Depends"];
+        const MODIFIERS: &[&str] = &["public", "protected", "private", "static", "final", "abstract"];
+
+        let name = leading_ident(code);
+        if PEST.contains(&name) && code[name.len()..].trim_start().starts_with('(') {
+            return Some(TestBlock::Brackets);
+        }
+
+        let declaration = strip_modifiers(code, MODIFIERS);
+        let is_test = annotation(code, "#[").is_some_and(|name| ATTRIBUTES.contains(&name))
+            || (declaration.starts_with("class ")
+                && supertype(declaration, " extends ").is_some_and(|name| name.ends_with("TestCase")));
+        is_test.then_some(TestBlock::Braces)
+    }
+}
+
+impl LanguageFilter for PhpFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
+    }
+
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::Php);
+        filter_test_blocks(self.config, lines, Self::test_start)
+    }
+}
+
+/// Swift code filter.
+struct SwiftFilter<'a> {
+    config: &'a FilterConfig,
+}
+
+impl<'a> SwiftFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Checks if a line starts an XCTest case class, which holds the
+    /// `test*` methods and `setUp`/`tearDown`, or a Swift Testing `@Test` or
+    /// `@Suite`.
+    fn is_test_start(code: &str) -> bool {
+        const MODIFIERS: &[&str] = &[
+            "public", "private", "fileprivate", "internal", "open", "final", "override", "static",
+            "@MainActor", "nonisolated",
+        ];
+
+        if matches!(annotation(code, "@"), Some("Test" | "Suite")) {
+            return true;
+        }
+
+        let declaration = strip_modifiers(code, MODIFIERS);
+        declaration.starts_with("class ") && supertype(declaration, ":").is_some_and(|name| name.ends_with("TestCase"))
+    }
+}
+
+impl LanguageFilter for SwiftFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
+    }
+
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::Swift);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_start(code).then_some(TestBlock::Braces))
+    }
+}
+
+/// Kotlin code filter.
+struct KotlinFilter<'a> {
+    config: &'a FilterConfig,
+}
+
+impl<'a> KotlinFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Checks if a line holds a JUnit or `kotlin.test` test annotation, or
+    /// declares a Kotest spec class.
+    fn is_test_start(code: &str) -> bool {
+        const ANNOTATIONS: &[&str] = &[
+            "Test", "ParameterizedTest", "RepeatedTest", "TestFactory", "BeforeEach", "AfterEach",
+            "BeforeAll", "AfterAll", "BeforeTest", "AfterTest", "Before", "After",
+        ];
+        const SPECS: &[&str] = &[
+            "FunSpec", "StringSpec", "ShouldSpec", "DescribeSpec", "BehaviorSpec", "WordSpec",
+            "FreeSpec", "FeatureSpec", "ExpectSpec", "AnnotationSpec",
+        ];
+
+        annotation(code, "@").is_some_and(|name| ANNOTATIONS.contains(&name))
+            || (strip_modifiers(code, &["public", "internal", "open", "abstract"]).starts_with("class ")
+                && supertype(code, ":").is_some_and(|name| SPECS.contains(&name)))
+    }
+}
+
+impl LanguageFilter for KotlinFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
+    }
+
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::Kotlin);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_start(code).then_some(TestBlock::Braces))
+    }
+}
+
+/// Scala code filter.
+struct ScalaFilter<'a> {
+    config: &'a FilterConfig,
+}
+
+impl<'a> ScalaFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Checks if a line starts a ScalaTest or MUnit test, a JUnit `@Test`
+    /// method, or a class extending a `*Suite` or `*Spec`.
+    fn is_test_start(code: &str) -> bool {
+        const CALLS: &[&str] = &["test", "describe", "it", "they", "property", "ignore", "scenario"];
+        // Words following the test name string in `"x" should "y" in {`
+        const VERBS: &[&str] = &["should", "must", "can", "in", "when"];
+
+        if annotation(code, "@") == Some("Test") {
+            return true;
+        }
+
+        let name = leading_ident(code);
+        let rest = code[name.len()..].trim_start();
+        if CALLS.contains(&name) && (rest.starts_with('(') || VERBS.contains(&leading_ident(rest))) {
+            return true;
+        }
+        if VERBS.contains(&name) && code.trim_end().ends_with('{') {
+            return true;
+        }
+
+        let declaration = strip_modifiers(code, &["final", "abstract", "sealed", "case"]);
+        (declaration.starts_with("class ") || declaration.starts_with("object "))
+            && supertype(declaration, " extends ").is_some_and(|name| {
+                name.ends_with("Suite") || name.ends_with("Spec") || name.ends_with("Specification")
+            })
+    }
+}
+
+impl LanguageFilter for ScalaFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
+    }
+
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::Scala);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_start(code).then_some(TestBlock::Braces))
+    }
+}
+
+/// Shell script code filter.
+struct ShellFilter<'a> {
+    config: &'a FilterConfig,
+}
+
+impl<'a> ShellFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Checks if a line starts a Bats `@test` or defines a shUnit2 `test*`
+    /// function.
+    fn is_test_start(code: &str) -> bool {
+        if code.starts_with("@test ") {
+            return true;
+        }
+
+        let keyword = code.strip_prefix("function ").map(str::trim_start);
+        let declaration = keyword.unwrap_or(code);
+        let name = leading_ident(declaration);
+        let is_function = keyword.is_some() || declaration[name.len()..].trim_start().starts_with("()");
+        is_function && name.starts_with("test") && name.len() > "test".len()
+    }
+}
+
+impl LanguageFilter for ShellFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
+    }
+
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::Shell);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_start(code).then_some(TestBlock::Braces))
+    }
+}
+
+/// SQL code filter, which only removes comments and blank lines.
+struct SqlFilter<'a> {
+    config: &'a FilterConfig,
+}
+
+impl<'a> SqlFilter<'a> {
     const fn new(config: &'a FilterConfig) -> Self {
         Self { config }
     }
 }
 
-impl<'a> LanguageFilter for CFilter<'a> {
+impl LanguageFilter for SqlFilter<'_> {
     fn config(&self) -> &FilterConfig {
         self.config
     }
 
-    fn is_comment_line(&self, line: &str) -> bool {
-        line.trim().starts_with("//")
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        source_lines(self.config, content, Language::Sql)
+            .into_iter()
+            .filter(|line| !is_dropped(self.config, line))
+            .map(|line| (line.number, line.text))
+            .collect()
     }
+}
+
+/// Lua code filter.
+struct LuaFilter<'a> {
+    config: &'a FilterConfig,
+}
+
+impl<'a> LuaFilter<'a> {
+    const fn new(config: &'a FilterConfig) -> Self {
+        Self { config }
+    }
+
+    /// Checks if a line starts a busted block, such as
+    /// `describe("x", function()` or `before_each(function()`.
+    fn is_test_block(code: &str) -> bool {
+        const NAMES: &[&str] = &[
+            "describe", "context", "insulate", "expose", "it", "spec", "test", "pending",
+            "before_each", "after_each", "setup", "teardown", "lazy_setup", "lazy_teardown",
+        ];
+
+        let name = leading_ident(code);
+        NAMES.contains(&name) && code[name.len()..].trim_start().starts_with('(') && code.contains("function")
+    }
+}
 
-    fn is_doc_comment(&self, line: &str) -> bool {
-        let trimmed = line.trim();
-        trimmed.starts_with("///") || trimmed.starts_with("/**")
+impl LanguageFilter for LuaFilter<'_> {
+    fn config(&self) -> &FilterConfig {
+        self.config
     }
 
-    fn filter(&self, content: &str) -> String {
-        JavaScriptFilter::new(self.config).filter(content)
+    fn filter(&self, content: &str) -> Vec<(usize, String)> {
+        let lines = source_lines(self.config, content, Language::Lua);
+        filter_test_blocks(self.config, lines, |code| Self::is_test_block(code).then_some(TestBlock::Brackets))
     }
 }
 
@@ -890,6 +1556,35 @@ fn code() {} // inline comment
         assert!(filtered.contains("fn code()"));
     }
 
+    #[test]
+    fn test_filter_line_map() {
+        let filter = CodeFilter::new(FilterConfig {
+            remove_comments: true,
+            remove_doc_comments: true,
+            ..Default::default()
+        });
+
+        let code = r"// Header comment
+fn first() {}
+
+#[test]
+fn test_first() {
+    assert!(true);
+}
+
+/// Docs
+fn second() {} // trailing
+";
+
+        let (filtered, line_map) = filter.filter_with_line_map(code, Path::new("lib.rs"));
+        assert_eq!(filtered, "fn first() {}\nfn second() {}");
+        assert_eq!(line_map, [2, 10]);
+
+        let (text, line_map) = filter.filter_with_line_map("a\nb\n", Path::new("notes.txt"));
+        assert_eq!(text, "a\nb\n");
+        assert_eq!(line_map, [1, 2]);
+    }
+
     #[test]
     fn test_python_filter_removes_tests() {
         let config = FilterConfig::default();
@@ -995,4 +1690,809 @@ fn main() {
         assert!(!filtered.contains("dbg!"));
         assert!(filtered.contains("let x = 5"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_remove_debug_prints_across_languages() {
+        let cases = [
+            (
+                "app.py",
+                "def f(x):\n    print(\"(\",\n          x)\n    pprint.pprint(x)\n    breakpoint()\n    printer(x)\n    return x",
+                "def f(x):\n    printer(x)\n    return x",
+            ),
+            (
+                "app.ts",
+                "function f(x) {\n  console.log(\"x)\", {\n    x,\n  });\n  console.debug(x);\n  logger.log(x);\n  return x;\n}",
+                "function f(x) {\n  logger.log(x);\n  return x;\n}",
+            ),
+            (
+                "main.go",
+                "func f(x int) int {\n\tfmt.Printf(\"%d)\\n\",\n\t\tx)\n\tlog.Println(x)\n\tfmt.Sprintf(\"%d\", x)\n\treturn x\n}",
+                "func f(x int) int {\n\tfmt.Sprintf(\"%d\", x)\n\treturn x\n}",
+            ),
+            (
+                "Main.java",
+                "class Main {\n  void f(int x) {\n    System.out.println(x);\n    try { g(); } catch (Exception e) {\n      e.printStackTrace();\n    }\n  }\n}",
+                "class Main {\n  void f(int x) {\n    try { g(); } catch (Exception e) {\n    }\n  }\n}",
+            ),
+            (
+                "main.cpp",
+                "int f(int x) {\n    printf(\"%d\\n\", x);\n    std::cout << \"x = \"\n              << x << std::endl;\n    snprintf(buf, 4, \"%d\", x);\n    return x;\n}",
+                "int f(int x) {\n    snprintf(buf, 4, \"%d\", x);\n    return x;\n}",
+            ),
+        ];
+
+        let filter = CodeFilter::new(FilterConfig {
+            remove_debug_prints: true,
+            ..Default::default()
+        });
+        for (file, code, expected) in cases {
+            assert_eq!(filter.filter(code, Path::new(file)), expected, "{file}");
+        }
+
+        let kept = CodeFilter::new(FilterConfig {
+            remove_debug_prints: false,
+            ..Default::default()
+        });
+        for (file, code, _) in cases {
+            assert_eq!(kept.filter(code, Path::new(file)), code, "{file}");
+        }
+    }
+
+    #[test]
+    fn test_remove_debug_prints_keeps_code_after_the_call() {
+        let filter = CodeFilter::new(FilterConfig::default().remove_debug_prints(true));
+
+        assert_eq!(
+            filter.filter("console.log(x); save(x);\nconsole.log(\"a;\"); console.log(b); done();", Path::new("app.js")),
+            "save(x);\ndone();"
+        );
+        assert_eq!(
+            filter.filter("fn f() {\n    dbg!(\n        x,\n    ); g(\"é\");\n}", Path::new("lib.rs")),
+            "fn f() {\n    g(\"é\");\n}"
+        );
+        assert_eq!(
+            filter.filter("std::cout << x; f();", Path::new("a.cpp")),
+            "f();"
+        );
+    }
+
+    #[test]
+    fn test_paren_free_debug_prints_keep_locals() {
+        let filter = CodeFilter::new(FilterConfig::default().remove_debug_prints(true));
+
+        let code = "def double(x)\n  p = x * 2\n  p if p > 0\n  p\nend\n\ndef show(x)\n  p x\n  pp\n  byebug\n  x\nend";
+        assert_eq!(
+            filter.filter(code, Path::new("a.rb")),
+            "def double(x)\n  p = x * 2\n  p if p > 0\n  p\nend\ndef show(x)\n  pp\n  x\nend"
+        );
+    }
+
+    #[test]
+    fn test_remove_extra_debug_print_names() {
+        let filter = CodeFilter::new(FilterConfig {
+            remove_debug_prints: true,
+            debug_print_names: vec!["log::debug!".to_string(), ".dump".to_string()],
+            ..Default::default()
+        });
+
+        let code = "fn f(x: u32) {\n    log::debug!(\n        \"x = {}\",\n        x,\n    );\n    state.dump();\n    dump();\n    log::info!(\"x\");\n}";
+        assert_eq!(
+            filter.filter(code, Path::new("lib.rs")),
+            "fn f(x: u32) {\n    dump();\n    log::info!(\"x\");\n}"
+        );
+    }
+
+    #[test]
+    fn test_comment_markers_in_literals_survive() {
+        let filter = CodeFilter::new(FilterConfig {
+            remove_comments: true,
+            remove_doc_comments: true,
+            ..Default::default()
+        });
+
+        let rust = "let a = r#\"// \"x\" /*\"#; /* outer /* inner */ still */ let c = '\"'; // gone";
+        assert_eq!(
+            filter.filter(rust, Path::new("lib.rs")),
+            "let a = r#\"// \"x\" /*\"#;  let c = '\"';"
+        );
+
+        let js = "const s = `a // ${b /* c */}`; const r = /\\/\\//g; // gone";
+        assert_eq!(
+            filter.filter(js, Path::new("app.js")),
+            "const s = `a // ${b }`; const r = /\\/\\//g;"
+        );
+
+        let python = "s = 'a # b'  # gone\nt = \"\"\"\n\n# kept\n\"\"\"";
+        assert_eq!(
+            filter.filter(python, Path::new("app.py")),
+            "s = 'a # b'\nt = \"\"\"\n\n# kept\n\"\"\""
+        );
+    }
+
+    #[test]
+    fn test_rust_test_attribute_in_string_is_kept() {
+        let filter = CodeFilter::new(FilterConfig::default());
+        let code = "const ATTR: &str = \"#[test]\";\nfn after() {}";
+
+        assert_eq!(filter.filter(code, Path::new("lib.rs")), code);
+    }
+
+    /// Deterministic xorshift generator for the property tests.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            // The high 31 bits fit in any `usize`
+            usize::try_from(self.0 >> 33).unwrap()
+        }
+
+        fn pick<'a>(&mut self, items: &[&'a str]) -> &'a str {
+            items[self.next() % items.len()]
+        }
+    }
+
+    /// Returns the literals of `source` verbatim and its code split at
+    /// whitespace, leaving out comments.
+    fn code_tokens(source: &str, language: Language) -> Vec<String> {
+        let mut tokens = Vec::new();
+        for span in lexer::lex(source, language) {
+            let text = &source[span.range];
+            match span.kind {
+                SpanKind::Code => tokens.extend(text.split_whitespace().map(str::to_string)),
+                SpanKind::String => tokens.push(text.to_string()),
+                _ => {}
+            }
+        }
+        tokens
+    }
+
+    /// Filters random programs built from `fragments` and checks that all
+    /// comments are gone while every other token is unchanged.
+    fn assert_only_comments_removed(language: Language, file: &str, fragments: &[&str]) {
+        let filter = CodeFilter::new(FilterConfig {
+            remove_tests: false,
+            remove_doc_comments: true,
+            remove_comments: true,
+            remove_blank_lines: true,
+            preserve_headers: false,
+            collapse_headers: false,
+            remove_debug_prints: false,
+            debug_print_names: Vec::new(),
+        });
+        let separators = ["", " ", "  ", "\n", "\n\n"];
+
+        for seed in 1..=500u64 {
+            let mut rng = Rng(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+            let length = 2 + rng.next() % 30;
+            let mut program = String::new();
+            for _ in 0..length {
+                program.push_str(rng.pick(fragments));
+                program.push_str(rng.pick(&separators));
+            }
+            // Filtered output never ends with a line break
+            let program = program.trim_end();
+
+            let filtered = filter.filter(program, Path::new(file));
+
+            assert_eq!(
+                code_tokens(&filtered, language),
+                code_tokens(program, language),
+                "tokens changed in {program:?} -> {filtered:?}"
+            );
+            assert!(
+                lexer::lex(&filtered, language)
+                    .iter()
+                    .all(|span| !span.kind.is_comment()),
+                "comment left in {program:?} -> {filtered:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_property_rust_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Rust,
+            "lib.rs",
+            &[
+                "fn", "main", "(", ")", "{", "}", "let", "x", "=", "1", ";", "/", "*", "<'a>",
+                "&'a", "'x'", "'\\''", "'\"'", "b'/'", "\"s // no\"", "\"/* no */\"",
+                "\"multi\n\n  line \"", "r#\"a \"// b\"#", "br\"/*\"", "r\"\\\"", "// line",
+                "/// doc", "//! inner", "//// plain", "/* block */", "/* a /* nested */ b */",
+                "/** doc */", "/**/", "/*\n  multi\n*/",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_python_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Python,
+            "app.py",
+            &[
+                "def", "f", "(", ")", ":", "x", "=", "1", "    ", "'a # b'", "\"c # d\"",
+                "'it\\'s'", "r'\\d+ #'", "f\"{x} #\"", "'''tri\n\n# ple'''", "\"\"\"doc\"\"\"",
+                "\"\"\"\nlong # doc\n\"\"\"", "# comment", "#",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_javascript_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::JavaScript,
+            "app.ts",
+            &[
+                "const", "x", "=", "a", "/", "2", "(", ")", "{", "}", ";", "return", "[1]",
+                "/re\\/g[/]/i", "'s // no'", "\"/* no */\"", "`t // ${x} /* ${`n ${y}`} */`",
+                "`multi\n\n// line`", "// line", "/// <reference />", "/* block */",
+                "/** doc */", "/*\n * multi\n */",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_go_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Go,
+            "main.go",
+            &[
+                "func", "main", "(", ")", "{", "}", "x", ":=", "/", "`raw // ${}`",
+                "`multi\n\n/* raw */`", "\"s /* no */\"", "'\\''", "'/'", "// c", "/* b */",
+                "/*\n b\n*/",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_java_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Java,
+            "A.java",
+            &[
+                "class", "A", "{", "}", "String", "s", "=", ";", "/",
+                "\"\"\"\n  // text\n\n  \"\"\"", "\"a // b\"", "'/'", "'\\''", "// c", "/* b */",
+                "/** doc */", "/*\n b\n*/",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_c_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::C,
+            "main.cpp",
+            &[
+                "int", "main", "(", ")", "{", "}", "x", "=", ";", "/", "1'000", "'/'", "'\\''",
+                "R\"d(a // b)d\"", "u8R\"(/*)\"", "\"s // no\"", "// c", "/// doc", "//! doc",
+                "/* b */", "/** doc */", "/*! doc */",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_csharp_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::CSharp,
+            "A.cs",
+            &[
+                "class", "A", "{", "}", "var", "s", "=", ";", "/", "@\"C:\\// no\"",
+                "@\"a \"\" /* b\"", "$@\"{x} //\"", "\"\"\"\n  // raw\n  \"\"\"", "\"a // b\"", "'/'",
+                "'\\''", "// c", "/// <summary>", "/* b */", "/** doc */",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_ruby_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Ruby,
+            "a.rb",
+            &[
+                "def", "f", "end", "x", "=", "=~", "if", "/", "%", "2", "<<", " << y", "\"a # b\"",
+                "'c # d'", "/a#b/i", "/[/]#/", "%q{a # {b}}", "%w[#x]", "%r!#!", "%(a # b)",
+                "\"multi\n\n# line\"", "<<~EOS\n  # body\n  EOS\n", "<<-'A'\n#{x}\nA\n", "# c",
+                "\n=begin\n# block\n=end\n",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_php_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Php,
+            "a.php",
+            &[
+                "function", "f", "(", ")", "{", "}", "$x", "=", ";", "/", "#[Attr]", "'a // b'",
+                "\"c # d\"", "\"multi\n\n// line\"", "<<<EOT\n  # body\n  EOT\n", "<<<'N'\n/* x */\nN\n",
+                "// c", "# c", "/* b */", "/** doc */",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_swift_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Swift,
+            "a.swift",
+            &[
+                "func", "f", "(", ")", "{", "}", "let", "x", "=", "/", "#if", "\"a // b\"",
+                "#\"a \" // b\"#", "##\"\"\"\n\"\"\"# /* no */\n\"\"\"##",
+                "\"\"\"\n  // text\n\n  \"\"\"", "// c", "/// doc", "/* b */", "/* a /* nested */ b */",
+                "/** doc */",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_kotlin_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Kotlin,
+            "a.kt",
+            &[
+                "fun", "f", "(", ")", "{", "}", "val", "x", "=", "/", "\"a // b\"", "'/'",
+                "\"\"\"\n  /* raw */\n\n  \"\"\"", "// c", "/* b */", "/* a /* nested */ b */",
+                "/** doc */",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_scala_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Scala,
+            "a.scala",
+            &[
+                "def", "f", "(", ")", "{", "}", "val", "x", "=", "/", "\"a // b\"", "'/'", "'\\''",
+                "\"\"\"\n  // raw\n\n  \"\"\"", "// c", "/* b */", "/* a /* nested */ b */", "/** doc */",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_shell_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Shell,
+            "a.sh",
+            &[
+                "echo", "x", "=", ";", "|", "$#", "${#x}", "url=a#b", "'a # b'", "\"c # d\"",
+                "\"multi\n\n# line\"", "<<EOF\n# body\nEOF\n", "<<-'EOF'\n\t# body\n\tEOF\n", "# c",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_sql_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Sql,
+            "a.sql",
+            &[
+                "SELECT", "x", "FROM", "t", ";", "-", "/", "'a -- b'", "\"c /* d */\"", "-- c",
+                "/* b */", "/*\n b\n*/",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_property_lua_filter_only_removes_comments() {
+        assert_only_comments_removed(
+            Language::Lua,
+            "a.lua",
+            &[
+                "local", "x", "=", "-", "[", "]", "t[1]", "'a -- b'", "\"c --[[ d\"",
+                "[[long\n\n-- string]]", "[==[a ]] b]==]", "-- c", "--- doc", "--[[ block\n]]",
+                "--[==[ a ]] b ]==]",
+            ],
+        );
+    }
+
+    #[test]
+    fn test_javascript_filter_removes_tests() {
+        let code = r#"import { sum } from "./sum";
+
+describe("sum", () => {
+  beforeEach(() => {
+    reset();
+  });
+
+  it("adds", () => {
+    const s = "}";
+    expect(sum(1, 2)).toBe(3);
+  });
+
+  test.each([[1, 1]])("pairs %i", (a, b) => expect(a).toBe(b));
+});
+
+it("one liner", () => expect(1).toBe(1));
+
+export function helper() {
+  return "describe(";
+}"#;
+
+        let filter = CodeFilter::new(FilterConfig::default());
+        let result = filter.filter(code, Path::new("sum.ts"));
+
+        assert_eq!(
+            result,
+            "import { sum } from \"./sum\";\nexport function helper() {\n  return \"describe(\";\n}"
+        );
+    }
+
+    #[test]
+    fn test_go_filter_removes_tests() {
+        let code = r#"package sum
+
+func Sum(a, b int) int {
+	return a + b
+}
+
+func TestSum(t *testing.T) {
+	if Sum(1, 2) != 3 {
+		t.Errorf("want %s", "{")
+	}
+}
+
+func BenchmarkSum(
+	b *testing.B,
+) {
+	for i := 0; i < b.N; i++ {
+		Sum(1, 2)
+	}
+}
+
+func ExampleSum() { fmt.Println(Sum(1, 2)) }
+
+func Testify() bool { return true }"#;
+
+        let filter = CodeFilter::new(FilterConfig::default());
+        let result = filter.filter(code, Path::new("sum.go"));
+
+        assert_eq!(
+            result,
+            "package sum\nfunc Sum(a, b int) int {\n\treturn a + b\n}\nfunc Testify() bool { return true }"
+        );
+    }
+
+    #[test]
+    fn test_c_filter_removes_tests() {
+        let code = r#"int add(int a, int b) { return a + b; }
+
+TEST(AddTest, Positive)
+{
+    EXPECT_EQ(add(1, 2), 3) << "}";
+}
+
+TEST_CASE("add", "[math]") {
+    SECTION("zero") {
+        REQUIRE(add(0, 0) == 0);
+    }
+}
+
+int main() { return 0; }"#;
+
+        let filter = CodeFilter::new(FilterConfig::default());
+        let result = filter.filter(code, Path::new("add.cpp"));
+
+        assert_eq!(result, "int add(int a, int b) { return a + b; }\nint main() { return 0; }");
+
+        let kept = CodeFilter::new(FilterConfig {
+            remove_tests: false,
+            ..FilterConfig::default()
+        });
+        assert!(kept.filter(code, Path::new("add.cpp")).contains("TEST_CASE"));
+    }
+
+    #[test]
+    fn test_java_filter_removes_tests() {
+        let code = r#"class SumTest {
+    @Test void inline() { assertEquals(3, sum(1, 2)); }
+    void kept() {}
+
+    @Test
+    @DisplayName("two {")
+    void multiLine()
+    {
+        if (sum(1, 1) == 2) {
+            log("}");
+        }
+    }
+
+    int helper() { return 1; }
+}"#;
+
+        let filter = CodeFilter::new(FilterConfig::default());
+        let result = filter.filter(code, Path::new("Sum.java"));
+
+        assert_eq!(
+            result,
+            "class SumTest {\n    void kept() {}\n    int helper() { return 1; }\n}"
+        );
+    }
+
+    #[test]
+    fn test_filter_removes_tests_in_more_languages() {
+        let cases = [
+            (
+                "Calc.cs",
+                "public class Calc\n{\n    public int Add(int a, int b) => a + b;\n\n    [Fact]\n    [Trait(\"kind\", \"}\")]\n    public void Adds()\n    {\n        Assert.Equal(3, Add(1, 2));\n    }\n}",
+                "public class Calc\n{\n    public int Add(int a, int b) => a + b;\n}",
+            ),
+            (
+                "calc.rb",
+                "class Calc\n  def add(a, b) = a + b\nend\n\nRSpec.describe Calc do\n  it \"adds\" do\n    expect(Calc.new.add(1, 2)).to eq(3)\n  end\n\n  it { is_expected.to be_a(Calc) }\nend\n\nclass CalcTest < Minitest::Test\n  def test_add\n    assert_equal 3, Calc.new.add(1, 2)\n  end\nend\n\nit = 1",
+                "class Calc\n  def add(a, b) = a + b\nend\nit = 1",
+            ),
+            (
+                "Calc.php",
+                "<?php\nfunction add($a, $b) { return $a + $b; }\n\nfinal class CalcTest extends \\PHPUnit\\Framework\\TestCase\n{\n    public function testAdd(): void\n    {\n        $this->assertSame(3, add(1, 2));\n    }\n}\n\ntest('adds', fn () => expect(add(1, 2))->toBe(3));\n\nit('adds again', function () {\n    expect(add(1, 2))->toBe(3);\n});\n\necho add(1, 2);",
+                "<?php\nfunction add($a, $b) { return $a + $b; }\necho add(1, 2);",
+            ),
+            (
+                "Calc.swift",
+                "func add(_ a: Int, _ b: Int) -> Int { a + b }\n\nfinal class CalcTests: XCTestCase {\n    func testAdd() {\n        XCTAssertEqual(add(1, 2), 3)\n    }\n}\n\n@Test(\"adds\")\nfunc adds() async throws {\n    #expect(add(1, 2) == 3)\n}\n\nfunc testable() -> Bool { true }",
+                "func add(_ a: Int, _ b: Int) -> Int { a + b }\nfunc testable() -> Bool { true }",
+            ),
+            (
+                "Calc.kt",
+                "fun add(a: Int, b: Int) = a + b\n\nclass CalcTest {\n    @Test\n    fun adds() {\n        assertEquals(3, add(1, 2))\n    }\n}\n\nclass CalcSpec : FunSpec({\n    test(\"adds\") { add(1, 2) shouldBe 3 }\n})",
+                "fun add(a: Int, b: Int) = a + b\nclass CalcTest {\n}",
+            ),
+            (
+                "Calc.scala",
+                "object Calc {\n  def add(a: Int, b: Int): Int = a + b\n}\n\nclass CalcSuite extends munit.FunSuite {\n  test(\"adds\") {\n    assertEquals(Calc.add(1, 2), 3)\n  }\n}\n\nclass CalcSpec extends AnyFlatSpec with Matchers {\n  \"add\" should \"sum\" in {\n    Calc.add(1, 2) shouldBe 3\n  }\n}",
+                "object Calc {\n  def add(a: Int, b: Int): Int = a + b\n}",
+            ),
+            (
+                "calc.sh",
+                "add() {\n  echo $(( $1 + $2 ))\n}\n\ntest_add() {\n  assertEquals 3 \"$(add 1 2)\"\n}\n\nfunction testAddZero {\n  assertEquals 0 \"$(add 0 0)\"\n}\n\ntest -f calc.sh && add 1 2",
+                "add() {\n  echo $(( $1 + $2 ))\n}\ntest -f calc.sh && add 1 2",
+            ),
+            (
+                "calc.bats",
+                "load helper\n\n@test \"adds\" {\n  run add 1 2\n  [ \"$output\" = \"3\" ]\n}",
+                "load helper",
+            ),
+            (
+                "calc.lua",
+                "local function add(a, b) return a + b end\n\ndescribe(\"add\", function()\n  before_each(function() reset() end)\n\n  it(\"adds\", function()\n    assert.are.equal(3, add(1, 2))\n  end)\nend)\n\nsetup()\nreturn add",
+                "local function add(a, b) return a + b end\nsetup()\nreturn add",
+            ),
+        ];
+
+        let filter = CodeFilter::new(FilterConfig::default());
+        for (file, code, expected) in cases {
+            assert_eq!(filter.filter(code, Path::new(file)), expected, "{file}");
+        }
+
+        let kept = CodeFilter::new(FilterConfig {
+            remove_tests: false,
+            remove_blank_lines: false,
+            ..FilterConfig::default()
+        });
+        for (file, code, _) in cases {
+            assert_eq!(kept.filter(code, Path::new(file)), code, "{file}");
+        }
+    }
+
+    #[test]
+    fn test_braceless_test_declarations_end_at_their_line() {
+        let filter = CodeFilter::new(FilterConfig::default());
+
+        let kotlin = "@Test fun addsTwo() = assertEquals(4, add(2, 2))\n@Test\nfun addsThree() =\n    assertEquals(\n        6,\n        add(3, 3),\n    )\nfun add(a: Int, b: Int) = a + b";
+        assert_eq!(filter.filter(kotlin, Path::new("Calc.kt")), "fun add(a: Int, b: Int) = a + b");
+
+        let php = "<?php\ntrait Checks\n{\n    #[Test]\n    abstract public function testChecks(): void;\n    public function check(): bool { return true; }\n}";
+        assert_eq!(
+            filter.filter(php, Path::new("Checks.php")),
+            "<?php\ntrait Checks\n{\n    public function check(): bool { return true; }\n}"
+        );
+
+        let csharp = "public abstract class Base\n{\n    [Test]\n    public abstract void Checks();\n    public int Run() => 1;\n}";
+        assert_eq!(
+            filter.filter(csharp, Path::new("Base.cs")),
+            "public abstract class Base\n{\n    public int Run() => 1;\n}"
+        );
+    }
+
+    #[test]
+    fn test_test_named_methods_outside_test_classes_are_kept() {
+        let filter = CodeFilter::new(FilterConfig::default());
+
+        let php = "<?php\nclass Database\n{\n    public function testConnection(): bool\n    {\n        return true;\n    }\n}";
+        assert_eq!(filter.filter(php, Path::new("Database.php")), php);
+
+        let swift = "class Database {\n    func setUp() {\n        connect()\n    }\n\n    func testConnection() -> Bool {\n        true\n    }\n}";
+        assert_eq!(
+            filter.filter(swift, Path::new("Database.swift")),
+            swift.replace("\n\n", "\n")
+        );
+
+        let tests = "class DatabaseTests: BaseTestCase {\n    func testConnection() {}\n}\nlet db = Database()";
+        assert_eq!(filter.filter(tests, Path::new("Database.swift")), "let db = Database()");
+    }
+
+    #[test]
+    fn test_filter_removes_comments_in_more_languages() {
+        let cases = [
+            ("a.cs", "/// <summary>Doc</summary>\nvar s = @\"C:\\// no\"; // gone", "var s = @\"C:\\// no\";"),
+            ("a.rb", "=begin\ngone\n=end\ns = \"# kept\" # gone", "s = \"# kept\""),
+            ("a.php", "<?php\n/** Doc */\n#[Attr]\n$s = '// kept'; # gone", "<?php\n#[Attr]\n$s = '// kept';"),
+            ("a.swift", "/// Doc\n/* a /* b */ c */\nlet s = \"\"\"\n// kept\n\"\"\"", "let s = \"\"\"\n// kept\n\"\"\""),
+            ("a.kt", "/** KDoc */\nval s = \"// kept\" /* gone */", "val s = \"// kept\""),
+            ("a.scala", "/** Scaladoc */\nval s = \"\"\"/* kept */\"\"\" // gone", "val s = \"\"\"/* kept */\"\"\""),
+            ("a.sh", "#!/bin/sh\necho \"# kept\" ${#x} # gone", "echo \"# kept\" ${#x}"),
+            ("b.sh", "cat <<EOF > a.conf # gone\n# kept\n\nEOF", "cat <<EOF > a.conf\n# kept\n\nEOF"),
+            ("a.sql", "-- gone\nSELECT '-- kept' /* gone */ FROM t;", "SELECT '-- kept'  FROM t;"),
+            ("a.lua", "--- Doc\n--[[ gone\n]]\nlocal s = \"-- kept\" -- gone", "local s = \"-- kept\""),
+        ];
+
+        let filter = CodeFilter::new(FilterConfig::minimal());
+        for (file, code, expected) in cases {
+            assert_eq!(filter.filter(code, Path::new(file)), expected, "{file}");
+        }
+    }
+
+    #[test]
+    fn test_remove_debug_prints_in_more_languages() {
+        let cases = [
+            ("a.cs", "Console.WriteLine(\n    x);\nDebug.WriteLine(x);\nreturn x;", "return x;"),
+            ("a.rb", "puts \"x = #{x}\"\np x\nbinding.pry\npp(\n  x\n)\np = x\nx", "p = x\nx"),
+            ("a.php", "var_dump($x);\ndd($x);\nprint_r($x, true);\necho $x;", "echo $x;"),
+            ("a.swift", "print(\"x\", x)\ndebugPrint(x)\nreturn x", "return x"),
+            ("a.kt", "println(x)\ne.printStackTrace()\nreturn x", "return x"),
+            ("a.scala", "println(s\"x = $x\")\nx", "x"),
+            ("a.lua", "print(\"x\", x)\nreturn x", "return x"),
+        ];
+
+        let filter = CodeFilter::new(FilterConfig {
+            remove_debug_prints: true,
+            ..Default::default()
+        });
+        for (file, code, expected) in cases {
+            assert_eq!(filter.filter(code, Path::new(file)), expected, "{file}");
+        }
+
+        let shell = CodeFilter::new(FilterConfig {
+            remove_debug_prints: true,
+            debug_print_names: vec!["debug".to_string()],
+            ..Default::default()
+        });
+        assert_eq!(
+            shell.filter("debug \"x=$x\"\necho \"$x\"", Path::new("a.sh")),
+            "echo \"$x\""
+        );
+    }
+
+    #[test]
+    fn test_collapse_headers_uses_language_comment_marker() {
+        let sql = "/*\n * Copyright 2024 Example Corp.\n * SPDX-License-Identifier: MIT\n */\nSELECT 1;";
+        assert_eq!(
+            header_filter(true).filter(sql, Path::new("a.sql")),
+            "-- SPDX-License-Identifier: MIT\nSELECT 1;"
+        );
+
+        let ruby = "# Copyright 2024 Example Corp.\n# SPDX-License-Identifier: MIT\nx = 1";
+        assert_eq!(
+            header_filter(false).filter(ruby, Path::new("a.rb")),
+            ruby
+        );
+        assert_eq!(
+            header_filter(true).filter(ruby, Path::new("a.rb")),
+            "# SPDX-License-Identifier: MIT\nx = 1"
+        );
+    }
+
+    #[test]
+    fn test_is_test_file() {
+        let filter = CodeFilter::new(FilterConfig::default());
+
+        assert!(filter.is_test_file(Path::new("src/sum.test.ts")));
+        assert!(filter.is_test_file(Path::new("src/sum.spec.js")));
+        assert!(filter.is_test_file(Path::new("pkg/sum_test.go")));
+        assert!(!filter.is_test_file(Path::new("src/test.ts")));
+        assert!(!filter.is_test_file(Path::new("pkg/sum.go")));
+        assert!(!filter.is_test_file(Path::new("tests/sum_test.py")));
+        assert!(filter.is_test_file(Path::new("spec/sum_spec.rb")));
+        assert!(filter.is_test_file(Path::new("tests/SumTest.php")));
+        assert!(filter.is_test_file(Path::new("Tests/SumTests.swift")));
+        assert!(filter.is_test_file(Path::new("spec/sum_spec.lua")));
+        assert!(!filter.is_test_file(Path::new("src/Test.php")));
+
+        let kept = CodeFilter::new(FilterConfig {
+            remove_tests: false,
+            ..FilterConfig::default()
+        });
+        assert!(!kept.is_test_file(Path::new("src/sum.test.ts")));
+    }
+
+    fn header_filter(collapse_headers: bool) -> CodeFilter {
+        CodeFilter::new(FilterConfig {
+            preserve_headers: true,
+            collapse_headers,
+            ..FilterConfig::minimal()
+        })
+    }
+
+    #[test]
+    fn test_preserve_headers_across_languages() {
+        let cases = [
+            (
+                "lib.rs",
+                "// Copyright 2024 Example Corp.\n// SPDX-License-Identifier: MIT\n\n//! Crate docs\n// comment\nfn main() {}",
+                "// Copyright 2024 Example Corp.\n// SPDX-License-Identifier: MIT\nfn main() {}",
+            ),
+            (
+                "app.py",
+                "#!/usr/bin/env python\n# Copyright (c) 2024 Example Corp.\n# Licensed under the Apache License, Version 2.0\n\"\"\"Module docs.\"\"\"\n# comment\nx = 1",
+                "#!/usr/bin/env python\n# Copyright (c) 2024 Example Corp.\n# Licensed under the Apache License, Version 2.0\nx = 1",
+            ),
+            (
+                "app.js",
+                "/**\n * @license MIT\n */\n/** Docs */\nconst x = 1; // comment",
+                "/**\n * @license MIT\n */\nconst x = 1;",
+            ),
+            (
+                "main.go",
+                "// Copyright 2024 The Example Authors. All rights reserved.\n// Use of this source code is governed by a BSD-style\n// license that can be found in the LICENSE file.\n\npackage main // comment",
+                "// Copyright 2024 The Example Authors. All rights reserved.\n// Use of this source code is governed by a BSD-style\n// license that can be found in the LICENSE file.\npackage main",
+            ),
+            (
+                "Main.java",
+                "/*\n * Copyright 2024 Example Corp.\n *\n * Licensed under the Apache License, Version 2.0\n */\npackage app;\n/* comment */\nclass Main {}",
+                "/*\n * Copyright 2024 Example Corp.\n *\n * Licensed under the Apache License, Version 2.0\n */\npackage app;\nclass Main {}",
+            ),
+            (
+                "main.c",
+                "/* SPDX-License-Identifier: GPL-2.0-only */\n#include <stdio.h> /* comment */",
+                "/* SPDX-License-Identifier: GPL-2.0-only */\n#include <stdio.h>",
+            ),
+        ];
+
+        for (file, code, expected) in cases {
+            assert_eq!(header_filter(false).filter(code, Path::new(file)), expected, "{file}");
+
+            let stripped = CodeFilter::new(FilterConfig::minimal()).filter(code, Path::new(file));
+            assert!(!stripped.contains("icense"), "{file}: {stripped}");
+        }
+    }
+
+    #[test]
+    fn test_collapse_headers_to_spdx_line() {
+        let rust = "// Copyright 2024 Example Corp.\n// SPDX-License-Identifier: MIT OR Apache-2.0\n// All rights reserved.\nfn main() {}";
+        let (text, line_map) = header_filter(true).filter_with_line_map(rust, Path::new("lib.rs"));
+        assert_eq!(text, "// SPDX-License-Identifier: MIT OR Apache-2.0\nfn main() {}");
+        assert_eq!(line_map, [2, 4]);
+
+        let c = "/*\n * Copyright 2024 Example Corp.\n * SPDX-License-Identifier: BSD-3-Clause\n */\nint x;";
+        assert_eq!(
+            header_filter(true).filter(c, Path::new("x.c")),
+            "// SPDX-License-Identifier: BSD-3-Clause\nint x;"
+        );
+
+        let python = "# SPDX-License-Identifier: MIT\n# Copyright 2024 Example Corp.\nx = 1";
+        assert_eq!(
+            header_filter(true).filter(python, Path::new("x.py")),
+            "# SPDX-License-Identifier: MIT\nx = 1"
+        );
+
+        // Without an SPDX line the header is kept whole
+        let go = "// Copyright 2024 Example Corp.\npackage main";
+        assert_eq!(header_filter(true).filter(go, Path::new("x.go")), go);
+    }
+
+    #[test]
+    fn test_header_needs_license_keywords_at_file_start() {
+        let filter = CodeFilter::new(FilterConfig {
+            preserve_headers: true,
+            ..FilterConfig::minimal()
+        });
+
+        let plain = "// Helpers for parsing\nfn parse() {}";
+        assert_eq!(filter.filter(plain, Path::new("a.rs")), "fn parse() {}");
+
+        let late = "fn parse() {}\n// Copyright 2024 Example Corp.";
+        assert_eq!(filter.filter(late, Path::new("a.rs")), "fn parse() {}");
+    }
+}
@@ -0,0 +1,575 @@
+//! Selection of files from the local git repository.
+//!
+//! A [`GitSelection`] narrows a scan to the files touched by a branch, the
+//! index or the working tree, and a [`GitRevSource`] reads the files of a
+//! revision without checking it out. Only the local repository is queried;
+//! nothing is fetched.
+
+use crate::error::{Error, Result};
+use crate::source::{FileSource, SourceEntry};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use tracing::debug;
+
+/// Restricts a scan to a subset of files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GitSelection {
+    /// Files changed since the merge base of a ref and `HEAD`, including
+    /// uncommitted changes
+    Since(String),
+    /// Files with staged changes
+    Staged,
+    /// Files with staged or unstaged changes, plus untracked files
+    Uncommitted,
+    /// Files listed in a file, one path per line (`-` reads stdin)
+    ///
+    /// Relative paths are resolved against the scan root. This mode does not
+    /// need a git repository.
+    FilesFrom(PathBuf),
+}
+
+impl GitSelection {
+    /// Returns the selected files as paths relative to `root`.
+    ///
+    /// Deleted files and files outside `root` are left out.
+    pub(crate) fn resolve(&self, root: &Path) -> Result<HashSet<PathBuf>> {
+        let root = root.canonicalize().map_err(|e| Error::io(root, e))?;
+
+        let paths = match self {
+            Self::FilesFrom(list) => read_file_list(list)?
+                .into_iter()
+                .map(|path| root.join(path))
+                .collect(),
+            Self::Since(rev) => {
+                let base = merge_base(&root, rev)?;
+                changed_files(&root, &["diff", "--name-only", "-z", "--diff-filter=d", &base])?
+            }
+            Self::Staged => changed_files(
+                &root,
+                &["diff", "--name-only", "-z", "--diff-filter=d", "--cached"],
+            )?,
+            Self::Uncommitted => {
+                let mut paths = changed_files(
+                    &root,
+                    &["diff", "--name-only", "-z", "--diff-filter=d", "HEAD"],
+                )?;
+                paths.extend(changed_files(
+                    &root,
+                    &["ls-files", "--others", "--exclude-standard", "-z", "--full-name"],
+                )?);
+                paths
+            }
+        };
+
+        let selected: HashSet<PathBuf> = paths
+            .into_iter()
+            .filter_map(|path| {
+                let path = path.canonicalize().ok()?;
+                path.strip_prefix(&root).ok().map(Path::to_path_buf)
+            })
+            .collect();
+
+        debug!("Git selection {:?} matched {} files", self, selected.len());
+        Ok(selected)
+    }
+}
+
+/// Reads file contents at the merge base of a ref and `HEAD`.
+pub(crate) struct BaseRevision {
+    root: PathBuf,
+    commit: String,
+    /// Scan root relative to the repository top level
+    prefix: PathBuf,
+    /// Files at the base, relative to the repository top level
+    files: HashSet<PathBuf>,
+}
+
+impl BaseRevision {
+    /// Resolves `rev` for the repository containing `root`.
+    pub(crate) fn resolve(root: &Path, rev: &str) -> Result<Self> {
+        let root = root.canonicalize().map_err(|e| Error::io(root, e))?;
+        let commit = merge_base(&root, rev)?;
+        let prefix = root
+            .strip_prefix(toplevel(&root)?)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let files = git(&root, &["ls-tree", "-r", "-z", "--name-only", "--full-tree", &commit])?
+            .split(|&byte| byte == 0)
+            .filter(|name| !name.is_empty())
+            .map(|name| PathBuf::from(String::from_utf8_lossy(name).as_ref()))
+            .collect();
+
+        Ok(Self {
+            root,
+            commit,
+            prefix,
+            files,
+        })
+    }
+
+    /// Returns the content of a file (relative to the scan root) at the
+    /// base, or `None` if it did not exist there.
+    pub(crate) fn read(&self, relative: &Path) -> Result<Option<String>> {
+        let path = self.prefix.join(relative);
+        if !self.files.contains(&path) {
+            return Ok(None);
+        }
+
+        let spec = format!("{}:{}", self.commit, path.to_string_lossy().replace('\\', "/"));
+        let content = git(&self.root, &["show", &spec])?;
+        Ok(Some(String::from_utf8_lossy(&content).into_owned()))
+    }
+
+    /// Returns the files under the scan root that exist at the base but were
+    /// deleted from the working tree since, relative to the scan root.
+    pub(crate) fn deleted(&self) -> Result<Vec<PathBuf>> {
+        Ok(git(
+            &self.root,
+            &["diff", "--name-only", "-z", "--diff-filter=D", "--relative", &self.commit],
+        )?
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| PathBuf::from(String::from_utf8_lossy(name).as_ref()))
+        .collect())
+    }
+}
+
+/// Files of the local repository at a revision, read from the object
+/// database without checking the revision out.
+///
+/// The files are listed like a directory walk would list them on a checkout
+/// of that revision: hidden files and files matched by the `.gitignore`
+/// files of that tree are left out. Symlinks and submodules are skipped.
+///
+/// # Examples
+///
+/// ```no_run
+/// use llm_utl::{Config, GitRevSource, Pipeline};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let source = GitRevSource::new(".", "v1.0.0")?;
+/// let config = Config::builder().source(source).build()?;
+/// Pipeline::new(config)?.run()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct GitRevSource {
+    root: PathBuf,
+    repo: PathBuf,
+    commit: String,
+    entries: Vec<SourceEntry>,
+    /// Blob id of every listed file, by path relative to the scan root
+    blobs: HashMap<String, String>,
+}
+
+impl GitRevSource {
+    /// Lists the files under `root` at `rev`, for the repository containing
+    /// `root`.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `root` is not inside a git repository or `rev`
+    /// does not name a commit.
+    pub fn new(root: impl Into<PathBuf>, rev: &str) -> Result<Self> {
+        let root = root.into();
+        let repo = root.canonicalize().map_err(|e| Error::io(&root, e))?;
+        if rev.starts_with('-') {
+            return Err(Error::git(format!("invalid revision '{rev}'")));
+        }
+        let commit = git(&repo, &["rev-parse", "--verify", &format!("{rev}^{{commit}}")])?;
+        let commit = String::from_utf8_lossy(&commit).trim().to_string();
+
+        let toplevel = toplevel(&repo)?;
+        let prefix = repo
+            .strip_prefix(&toplevel)
+            .map(Path::to_path_buf)
+            .unwrap_or_default();
+
+        let tree = tree_entries(&repo, &commit)?;
+        let ignores = tree_gitignores(&repo, &toplevel, &prefix, &tree)?;
+
+        let mut entries = Vec::new();
+        let mut blobs = HashMap::new();
+        for entry in tree {
+            let Ok(relative) = entry.path.strip_prefix(&prefix) else {
+                continue;
+            };
+            let hidden = relative
+                .components()
+                .any(|part| part.as_os_str().to_string_lossy().starts_with('.'));
+            if hidden || !entry.is_regular_file() || is_ignored(&toplevel, &ignores, &entry.path) {
+                continue;
+            }
+
+            let relative_path = relative.to_string_lossy().replace('\\', "/");
+            entries.push(SourceEntry {
+                path: root.join(relative),
+                relative_path: relative_path.clone(),
+                size: entry.size,
+            });
+            blobs.insert(relative_path, entry.oid);
+        }
+
+        debug!("Listed {} files at {} ({})", entries.len(), rev, commit);
+        Ok(Self {
+            root,
+            repo,
+            commit,
+            entries,
+            blobs,
+        })
+    }
+
+    /// Returns the full id of the commit the files are read from.
+    #[must_use]
+    pub fn commit(&self) -> &str {
+        &self.commit
+    }
+}
+
+impl FileSource for GitRevSource {
+    fn files(&self) -> Result<Vec<SourceEntry>> {
+        Ok(self.entries.clone())
+    }
+
+    fn read(&self, entry: &SourceEntry) -> Result<Vec<u8>> {
+        let oid = self.blobs.get(&entry.relative_path).ok_or_else(|| {
+            Error::git(format!(
+                "{} is not in {} at {}",
+                entry.relative_path,
+                self.root.display(),
+                self.commit
+            ))
+        })?;
+        git(&self.repo, &["cat-file", "blob", oid])
+    }
+}
+
+/// An entry of `git ls-tree -r -l`.
+struct TreeEntry {
+    mode: String,
+    oid: String,
+    size: u64,
+    /// Path relative to the repository top level
+    path: PathBuf,
+}
+
+impl TreeEntry {
+    /// Returns `true` for regular files, as opposed to symlinks and
+    /// submodules.
+    fn is_regular_file(&self) -> bool {
+        self.mode == "100644" || self.mode == "100755"
+    }
+}
+
+/// Lists every entry of the tree of `commit`.
+fn tree_entries(repo: &Path, commit: &str) -> Result<Vec<TreeEntry>> {
+    let output = git(repo, &["ls-tree", "-r", "-z", "-l", "--full-tree", commit])?;
+
+    Ok(output
+        .split(|&byte| byte == 0)
+        .filter_map(|line| {
+            // <mode> <type> <oid> <size>\t<path>
+            let line = String::from_utf8_lossy(line);
+            let (meta, path) = line.split_once('\t')?;
+            let mut fields = meta.split_whitespace();
+            let mode = fields.next()?.to_string();
+            let oid = fields.nth(1)?.to_string();
+            let size = fields.next()?.parse().unwrap_or(0);
+            Some(TreeEntry {
+                mode,
+                oid,
+                size,
+                path: PathBuf::from(path),
+            })
+        })
+        .collect())
+}
+
+/// Parses the `.gitignore` files of the tree that apply below `prefix`,
+/// deepest directory first.
+fn tree_gitignores(
+    repo: &Path,
+    toplevel: &Path,
+    prefix: &Path,
+    tree: &[TreeEntry],
+) -> Result<Vec<(PathBuf, Gitignore)>> {
+    let mut ignores = Vec::new();
+    for entry in tree {
+        if entry.path.file_name().is_none_or(|name| name != ".gitignore") {
+            continue;
+        }
+        let dir = entry.path.parent().unwrap_or_else(|| Path::new("")).to_path_buf();
+        if !prefix.starts_with(&dir) && !dir.starts_with(prefix) {
+            continue;
+        }
+
+        let content = git(repo, &["cat-file", "blob", &entry.oid])?;
+        let mut builder = GitignoreBuilder::new(toplevel.join(&dir));
+        for line in String::from_utf8_lossy(&content).lines() {
+            if let Err(e) = builder.add_line(Some(toplevel.join(&entry.path)), line) {
+                debug!("Skipping .gitignore pattern: {}", e);
+            }
+        }
+        match builder.build() {
+            Ok(gitignore) => ignores.push((dir, gitignore)),
+            Err(e) => debug!("Skipping {}: {}", entry.path.display(), e),
+        }
+    }
+
+    ignores.sort_by_key(|(dir, _)| std::cmp::Reverse(dir.components().count()));
+    Ok(ignores)
+}
+
+/// Returns whether the closest `.gitignore` with a matching rule ignores
+/// `path` (relative to the repository top level).
+fn is_ignored(toplevel: &Path, ignores: &[(PathBuf, Gitignore)], path: &Path) -> bool {
+    let absolute = toplevel.join(path);
+    for (dir, gitignore) in ignores {
+        if !path.starts_with(dir) {
+            continue;
+        }
+        match gitignore.matched_path_or_any_parents(&absolute, false) {
+            Match::Ignore(_) => return true,
+            Match::Whitelist(_) => return false,
+            Match::None => {}
+        }
+    }
+    false
+}
+
+/// Returns the merge base of `rev` and `HEAD`.
+fn merge_base(root: &Path, rev: &str) -> Result<String> {
+    if rev.starts_with('-') {
+        return Err(Error::git(format!("invalid revision '{rev}'")));
+    }
+    let base = git(root, &["merge-base", rev, "HEAD"])?;
+    Ok(String::from_utf8_lossy(&base).trim().to_string())
+}
+
+/// Returns the repository top level containing `dir`.
+fn toplevel(dir: &Path) -> Result<PathBuf> {
+    let toplevel = git(dir, &["rev-parse", "--show-toplevel"])?;
+    let toplevel = PathBuf::from(String::from_utf8_lossy(&toplevel).trim());
+    toplevel.canonicalize().map_err(|e| Error::io(toplevel, e))
+}
+
+/// Runs a git command in `dir` and returns its stdout.
+fn git(dir: &Path, args: &[&str]) -> Result<Vec<u8>> {
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(args)
+        .output()
+        .map_err(|e| Error::git(format!("failed to run git: {e}")))?;
+
+    if !output.status.success() {
+        return Err(Error::git(format!(
+            "`git {}` failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    Ok(output.stdout)
+}
+
+/// Runs a git command that prints NUL-separated paths relative to the
+/// repository top level, and returns them as absolute paths.
+fn changed_files(root: &Path, args: &[&str]) -> Result<Vec<PathBuf>> {
+    let toplevel = toplevel(root)?;
+
+    Ok(git(root, args)?
+        .split(|&byte| byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| toplevel.join(String::from_utf8_lossy(name).as_ref()))
+        .collect())
+}
+
+fn read_file_list(list: &Path) -> Result<Vec<PathBuf>> {
+    let content = if list == Path::new("-") {
+        let mut content = String::new();
+        std::io::stdin()
+            .read_to_string(&mut content)
+            .map_err(|e| Error::io(list, e))?;
+        content
+    } else {
+        fs::read_to_string(list).map_err(|e| Error::io(list, e))?
+    };
+
+    Ok(content
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(PathBuf::from)
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+
+    /// Runs git in a test repository, panicking on failure.
+    fn run_git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(status.status.success(), "git {args:?}: {status:?}");
+    }
+
+    /// Creates a repository with two commits on `main` and a `feature` branch.
+    ///
+    /// `feature` changes `src/changed.rs` and deletes `src/deleted.rs`;
+    /// `src/same.rs` is untouched.
+    fn fixture_repo() -> TempDir {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path();
+        fs::create_dir(dir.join("src")).unwrap();
+        for name in ["same", "changed", "deleted"] {
+            fs::write(dir.join(format!("src/{name}.rs")), format!("fn {name}() {{}}\n")).unwrap();
+        }
+
+        run_git(dir, &["init", "-q", "-b", "main"]);
+        run_git(dir, &["add", "."]);
+        run_git(dir, &["commit", "-q", "-m", "initial"]);
+        run_git(dir, &["checkout", "-q", "-b", "feature"]);
+        fs::write(dir.join("src/changed.rs"), "fn changed() { todo!() }\n").unwrap();
+        run_git(dir, &["rm", "-q", "src/deleted.rs"]);
+        run_git(dir, &["commit", "-q", "-am", "feature work"]);
+
+        temp
+    }
+
+    fn sorted(selection: HashSet<PathBuf>) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = selection.into_iter().collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_since_ref() {
+        let repo = fixture_repo();
+        fs::write(repo.path().join("src/same.rs"), "fn same() { 1 }\n").unwrap();
+
+        let selected = GitSelection::Since("main".to_string())
+            .resolve(repo.path())
+            .unwrap();
+
+        assert_eq!(
+            sorted(selected),
+            [PathBuf::from("src/changed.rs"), PathBuf::from("src/same.rs")]
+        );
+    }
+
+    #[test]
+    fn test_staged_and_uncommitted() {
+        let repo = fixture_repo();
+        let dir = repo.path();
+        fs::write(dir.join("src/same.rs"), "fn same() { 1 }\n").unwrap();
+        run_git(dir, &["add", "src/same.rs"]);
+        fs::write(dir.join("src/changed.rs"), "fn changed() { 2 }\n").unwrap();
+        fs::write(dir.join("src/new.rs"), "fn new() {}\n").unwrap();
+
+        let staged = GitSelection::Staged.resolve(dir).unwrap();
+        assert_eq!(sorted(staged), [PathBuf::from("src/same.rs")]);
+
+        let uncommitted = GitSelection::Uncommitted.resolve(&dir.join("src")).unwrap();
+        assert_eq!(
+            sorted(uncommitted),
+            [PathBuf::from("changed.rs"), PathBuf::from("new.rs"), PathBuf::from("same.rs")]
+        );
+    }
+
+    #[test]
+    fn test_files_from_list() {
+        let repo = fixture_repo();
+        let list = repo.path().join("files.txt");
+        fs::write(&list, "src/same.rs\n\nsrc/missing.rs\n").unwrap();
+
+        let selected = GitSelection::FilesFrom(list).resolve(repo.path()).unwrap();
+        assert_eq!(sorted(selected), [PathBuf::from("src/same.rs")]);
+    }
+
+    #[test]
+    fn test_base_revision_reads_old_content() {
+        let repo = fixture_repo();
+        fs::write(repo.path().join("src/new.rs"), "fn new() {}\n").unwrap();
+
+        let base = BaseRevision::resolve(&repo.path().join("src"), "main").unwrap();
+        assert_eq!(
+            base.read(Path::new("changed.rs")).unwrap().as_deref(),
+            Some("fn changed() {}\n")
+        );
+        assert_eq!(base.read(Path::new("new.rs")).unwrap(), None);
+    }
+
+    #[test]
+    fn test_unknown_ref_is_an_error() {
+        let repo = fixture_repo();
+
+        let err = GitSelection::Since("no-such-branch".to_string())
+            .resolve(repo.path())
+            .unwrap_err();
+        assert!(err.to_string().contains("merge-base"));
+    }
+
+    #[test]
+    fn test_git_rev_source_reads_committed_files() {
+        let repo = fixture_repo();
+        let dir = repo.path();
+        fs::write(dir.join("src/changed.rs"), "fn changed() { 3 }\n").unwrap();
+        fs::write(dir.join("src/new.rs"), "fn new() {}\n").unwrap();
+
+        let source = GitRevSource::new(dir.join("src"), "main").unwrap();
+        let files = source.files().unwrap();
+        let paths: Vec<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, ["changed.rs", "deleted.rs", "same.rs"]);
+        assert_eq!(source.read(&files[0]).unwrap(), b"fn changed() {}\n");
+        assert_eq!(files[0].size, 16);
+    }
+
+    #[test]
+    fn test_git_rev_source_respects_tree_gitignore() {
+        let repo = fixture_repo();
+        let dir = repo.path();
+        fs::write(dir.join(".gitignore"), "generated/\n").unwrap();
+        fs::write(dir.join("src/.gitignore"), "*.gen.rs\n!keep.gen.rs\n").unwrap();
+        fs::create_dir(dir.join("generated")).unwrap();
+        fs::write(dir.join("generated/out.rs"), "fn out() {}\n").unwrap();
+        fs::write(dir.join("src/skip.gen.rs"), "fn skip() {}\n").unwrap();
+        fs::write(dir.join("src/keep.gen.rs"), "fn keep() {}\n").unwrap();
+        fs::write(dir.join(".hidden.rs"), "fn hidden() {}\n").unwrap();
+        run_git(dir, &["add", "-f", "."]);
+        run_git(dir, &["commit", "-q", "-m", "ignored files"]);
+        run_git(dir, &["checkout", "-q", "main"]);
+
+        let source = GitRevSource::new(dir, "feature").unwrap();
+        let paths: Vec<String> = source
+            .files()
+            .unwrap()
+            .into_iter()
+            .map(|f| f.relative_path)
+            .collect();
+        assert_eq!(paths, ["src/changed.rs", "src/keep.gen.rs", "src/same.rs"]);
+    }
+
+    #[test]
+    fn test_git_rev_source_unknown_rev_is_an_error() {
+        let repo = fixture_repo();
+
+        assert!(GitRevSource::new(repo.path(), "no-such-tag").is_err());
+        assert!(GitRevSource::new(repo.path(), "--all").is_err());
+    }
+}
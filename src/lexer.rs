@@ -0,0 +1,1196 @@
+//! Splits source code into code, string and comment spans.
+//!
+//! The code filters work on these spans instead of guessing from line
+//! prefixes, so comment markers inside strings, raw strings, char literals,
+//! template and regex literals, and nested block comments are classified
+//! correctly. The lexer never fails: anything it does not recognize is code,
+//! and unterminated strings and comments run to the end of the input.
+//!
+//! Ruby, shell and PHP heredocs, Ruby regex and `%q{}` literals and Swift
+//! raw strings are strings. Interpolations holding quotes (Swift
+//! `"\(f("x"))"`, Kotlin and Scala `"${m["k"]}"`, Ruby `"#{h["k"]}"`, shell
+//! `"$(cmd "x")"`) are not recognized, so comment markers inside them may be
+//! taken for comments.
+
+use std::ops::Range;
+
+/// Languages with their own lexical rules.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Language {
+    Rust,
+    Python,
+    JavaScript,
+    Go,
+    Java,
+    C,
+    CSharp,
+    Ruby,
+    Php,
+    Swift,
+    Kotlin,
+    Scala,
+    Shell,
+    Sql,
+    Lua,
+}
+
+impl Language {
+    /// Returns the marker starting a line comment.
+    pub(crate) const fn line_comment(self) -> &'static str {
+        match self {
+            Self::Python | Self::Ruby | Self::Shell => "#",
+            Self::Sql | Self::Lua => "--",
+            _ => "//",
+        }
+    }
+}
+
+/// What a span of source code is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SpanKind {
+    /// Everything that is not a literal or a comment
+    Code,
+    /// String, char, template and regex literals
+    String,
+    /// Comment running to the end of the line (`//`, `#`, `--`)
+    LineComment,
+    /// Delimited comment (`/* */`, `--[[ ]]`, `=begin`/`=end`)
+    BlockComment,
+    /// Documentation comment or Python docstring
+    DocComment,
+}
+
+impl SpanKind {
+    /// Returns `true` for comments of any kind.
+    pub(crate) const fn is_comment(self) -> bool {
+        matches!(self, Self::LineComment | Self::BlockComment | Self::DocComment)
+    }
+}
+
+/// A classified byte range of the source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Span {
+    pub(crate) kind: SpanKind,
+    pub(crate) range: Range<usize>,
+}
+
+/// A line of source after comment removal.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SourceLine {
+    /// 1-based line number in the original source
+    pub(crate) number: usize,
+
+    /// Line text, without removed comments
+    pub(crate) text: String,
+
+    /// Line text with strings and kept comments blanked out, for matching
+    /// code structure (attributes, braces, calls)
+    pub(crate) code: String,
+
+    /// The line only held removed comments
+    pub(crate) emptied: bool,
+
+    /// The line starts inside a multi-line string or kept comment, so its
+    /// text must be kept as is
+    pub(crate) continued: bool,
+}
+
+impl SourceLine {
+    /// Returns `true` if the line is blank and may be dropped as such.
+    pub(crate) fn is_blank(&self) -> bool {
+        !self.continued && self.text.trim().is_empty()
+    }
+}
+
+/// Splits `source` into spans covering all of it, in order.
+pub(crate) fn lex(source: &str, language: Language) -> Vec<Span> {
+    let mut lexer = Lexer {
+        src: source,
+        bytes: source.as_bytes(),
+        pos: 0,
+        language,
+        tokens: Vec::new(),
+        regex_allowed: true,
+        word_start: 0,
+        heredocs: Vec::new(),
+    };
+    lexer.run(false);
+
+    // Fill the gaps between literals and comments with code
+    let mut spans = Vec::with_capacity(lexer.tokens.len() * 2 + 1);
+    let mut last = 0;
+    for token in lexer.tokens {
+        if token.range.start > last {
+            spans.push(Span {
+                kind: SpanKind::Code,
+                range: last..token.range.start,
+            });
+        }
+        last = token.range.end;
+        spans.push(token);
+    }
+    if last < source.len() {
+        spans.push(Span {
+            kind: SpanKind::Code,
+            range: last..source.len(),
+        });
+    }
+    spans
+}
+
+/// Removes the comment spans for which `remove` returns `true` and returns
+/// the remaining lines.
+///
+/// Removed block comments keep their line breaks, so every line of the
+/// result still corresponds to the line with the same number in `source`.
+/// A comment removed between two tokens on the same line leaves a space.
+pub(crate) fn strip_comments(
+    source: &str,
+    language: Language,
+    remove: impl Fn(&Span) -> bool,
+) -> Vec<SourceLine> {
+    let line_count = source.lines().count();
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(source.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let line_of = |offset: usize| line_starts.partition_point(|&start| start <= offset) - 1;
+
+    let mut text = String::with_capacity(source.len());
+    let mut code = String::with_capacity(source.len());
+    let mut touched = vec![false; line_count + 1];
+    let mut continued = vec![false; line_count + 1];
+
+    for span in lex(source, language) {
+        let slice = &source[span.range.clone()];
+
+        if span.kind.is_comment() && remove(&span) {
+            touched[line_of(span.range.start)..=line_of(span.range.end - 1)].fill(true);
+            let newlines = slice.matches('\n').count();
+            let before = text.chars().next_back().is_some_and(|c| !c.is_whitespace());
+            let after = source[span.range.end..]
+                .chars()
+                .next()
+                .is_some_and(|c| !c.is_whitespace());
+            if newlines == 0 && before && after {
+                text.push(' ');
+                code.push(' ');
+            }
+            for _ in 0..newlines {
+                text.push('\n');
+                code.push('\n');
+            }
+            continue;
+        }
+
+        if slice.starts_with('\n') && touched[line_of(span.range.start)] {
+            // A heredoc body starts at the line break, after trailing code
+            let trailing = text.len() - text.trim_end_matches([' ', '\t']).len();
+            text.truncate(text.len() - trailing);
+            code.truncate(code.len() - trailing);
+        }
+        text.push_str(slice);
+        if span.kind == SpanKind::Code {
+            code.push_str(slice);
+        } else {
+            code.extend(slice.chars().map(|c| if c == '\n' { '\n' } else { ' ' }));
+            continued[line_of(span.range.start) + 1..=line_of(span.range.end - 1)].fill(true);
+        }
+    }
+
+    text.lines()
+        .zip(code.lines())
+        .enumerate()
+        .map(|(index, (line, code))| {
+            // Trailing whitespace inside a string that continues is content
+            let (line, code) = if touched[index] && !continued[index + 1] {
+                (line.trim_end(), code.trim_end())
+            } else {
+                (line, code)
+            };
+            SourceLine {
+                number: index + 1,
+                text: line.to_string(),
+                code: code.to_string(),
+                emptied: touched[index] && line.trim().is_empty(),
+                continued: continued[index],
+            }
+        })
+        .collect()
+}
+
+/// Words after which a `/` starts a regex literal in JavaScript.
+const REGEX_KEYWORDS: &[&str] = &[
+    "return", "typeof", "instanceof", "in", "of", "new", "delete", "void", "throw", "case",
+    "do", "else", "yield", "await",
+];
+
+/// Words after which a `/` or `%` starts a literal in Ruby.
+const RUBY_REGEX_KEYWORDS: &[&str] = &[
+    "if", "elsif", "unless", "when", "while", "until", "and", "or", "not", "return", "then",
+    "else", "do", "in",
+];
+
+struct Lexer<'a> {
+    src: &'a str,
+    bytes: &'a [u8],
+    pos: usize,
+    language: Language,
+    /// Literal and comment spans, in order
+    tokens: Vec<Span>,
+    /// Whether a `/` in JavaScript or Ruby code starts a regex literal
+    regex_allowed: bool,
+    /// Start of the identifier or number ending at `pos`
+    word_start: usize,
+    /// Terminators of heredocs opened on the current line, and whether
+    /// they may be indented
+    heredocs: Vec<(String, bool)>,
+}
+
+impl Lexer<'_> {
+    /// Lexes until the end of the input, or with `nested` until the `}`
+    /// closing a template literal substitution.
+    fn run(&mut self, nested: bool) {
+        let mut depth = 0usize;
+
+        while self.pos < self.bytes.len() {
+            let start = self.pos;
+            let byte = self.bytes[start];
+
+            if nested {
+                match byte {
+                    b'{' => depth += 1,
+                    b'}' if depth == 0 => return,
+                    b'}' => depth -= 1,
+                    _ => {}
+                }
+            }
+
+            if byte == b'\n' && !self.heredocs.is_empty() {
+                self.heredoc_bodies(start);
+                continue;
+            }
+            if let Some((end, terminator)) = self.heredoc_opener(start) {
+                self.heredocs.push(terminator);
+                self.pos = end;
+                continue;
+            }
+
+            if let Some((kind, end)) = self.token(start) {
+                if kind == SpanKind::String {
+                    self.regex_allowed = false;
+                }
+                self.tokens.push(Span {
+                    kind,
+                    range: start..end,
+                });
+                self.pos = end;
+                continue;
+            }
+
+            if self.language == Language::JavaScript && byte == b'`' {
+                self.template();
+                self.regex_allowed = false;
+                continue;
+            }
+
+            self.code_char(start);
+        }
+    }
+
+    /// Advances past one character of code, tracking what precedes a `/`.
+    fn code_char(&mut self, start: usize) {
+        let byte = self.bytes[start];
+        self.pos = start + self.char_len(start);
+
+        if is_word_byte(byte) {
+            if start == 0 || !is_word_byte(self.bytes[start - 1]) {
+                self.word_start = start;
+            }
+            let keywords = if self.language == Language::Ruby {
+                RUBY_REGEX_KEYWORDS
+            } else {
+                REGEX_KEYWORDS
+            };
+            self.regex_allowed = keywords.contains(&&self.src[self.word_start..self.pos]);
+        } else if !byte.is_ascii_whitespace() {
+            self.regex_allowed = !matches!(byte, b')' | b']');
+        }
+    }
+
+    /// Returns the kind and end of the literal or comment starting at `at`.
+    fn token(&self, at: usize) -> Option<(SpanKind, usize)> {
+        let byte = self.bytes[at];
+        let next = self.bytes.get(at + 1).copied();
+
+        match (self.language, byte, next) {
+            (Language::Python | Language::Ruby, b'#', _) => {
+                Some((SpanKind::LineComment, self.line_end(at)))
+            }
+            (Language::Python, b'"' | b'\'', _) => Some(self.python_string(at)),
+            (Language::Python, ..) => None,
+
+            (Language::Ruby, b'=', _) if self.src[at..].starts_with("=begin") && self.at_line_start(at) => {
+                let end = self.src[at..]
+                    .find("\n=end")
+                    .map_or(self.bytes.len(), |i| self.line_end(at + i + 1));
+                Some((SpanKind::BlockComment, end))
+            }
+            (Language::Php, b'#', next) if next != Some(b'[') => {
+                Some((SpanKind::LineComment, self.line_end(at)))
+            }
+            (Language::Shell, b'#', _) if self.starts_shell_word(at) => {
+                Some((SpanKind::LineComment, self.line_end(at)))
+            }
+            (Language::Shell, b'\'', _) => Some((SpanKind::String, self.find(at + 1, "'"))),
+            (Language::Sql, b'-', Some(b'-')) => Some((SpanKind::LineComment, self.line_end(at))),
+            (Language::Sql, b'\'' | b'"', _) => {
+                Some((SpanKind::String, self.find(at + 1, &self.src[at..=at])))
+            }
+            (Language::Lua, b'-', Some(b'-')) => Some(self.lua_comment(at)),
+            (Language::Lua, b'[', _) => self.lua_long_bracket_end(at).map(|end| (SpanKind::String, end)),
+
+            (_, b'/', Some(b'/')) if self.has_slash_comments() => {
+                Some((self.line_comment_kind(at), self.line_end(at)))
+            }
+            (_, b'/', Some(b'*')) if self.has_slash_comments() || self.language == Language::Sql => {
+                Some((self.block_comment_kind(at), self.block_comment_end(at)))
+            }
+            (Language::JavaScript | Language::Ruby, b'/', _) if self.regex_allowed => {
+                Some((SpanKind::String, self.regex_end(at)))
+            }
+            (Language::Ruby, b'%', _) => self.ruby_percent_literal(at),
+
+            (Language::Rust, b'r', _) => self.rust_raw_string(at),
+            (Language::Swift, b'#', _) => self.swift_raw_string(at),
+            (Language::Rust | Language::Scala, b'\'', _) => self.rust_char(at),
+            (Language::CSharp, b'@', Some(b'"')) => Some((SpanKind::String, self.verbatim_end(at + 1))),
+            (Language::CSharp, b'@', Some(b'$')) if self.bytes.get(at + 2) == Some(&b'"') => {
+                Some((SpanKind::String, self.verbatim_end(at + 2)))
+            }
+            (Language::C, b'R', Some(b'"')) => self.cpp_raw_string(at),
+            (Language::C, b'\'', _) if self.is_digit_separator(at) => None,
+            (Language::Go, b'`', _) => Some((SpanKind::String, self.find(at + 1, "`"))),
+            (
+                Language::Java | Language::Kotlin | Language::Scala | Language::Swift | Language::CSharp,
+                b'"',
+                _,
+            ) if self.src[at..].starts_with("\"\"\"") => {
+                Some((SpanKind::String, self.find(at + 3, "\"\"\"")))
+            }
+
+            (_, b'"', _) => Some((SpanKind::String, self.quoted_end(at, b'"'))),
+            (
+                Language::JavaScript
+                | Language::Go
+                | Language::Java
+                | Language::C
+                | Language::CSharp
+                | Language::Kotlin
+                | Language::Php
+                | Language::Ruby
+                | Language::Lua,
+                b'\'',
+                _,
+            ) => {
+                Some((SpanKind::String, self.quoted_end(at, b'\'')))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns `true` if `//` and `/*` start comments.
+    const fn has_slash_comments(&self) -> bool {
+        !matches!(
+            self.language,
+            Language::Python | Language::Ruby | Language::Shell | Language::Sql | Language::Lua
+        )
+    }
+
+    /// Classifies a `//` comment.
+    fn line_comment_kind(&self, at: usize) -> SpanKind {
+        let rest = &self.src[at..];
+        let doc = match self.language {
+            Language::Rust => {
+                (rest.starts_with("///") && !rest.starts_with("////")) || rest.starts_with("//!")
+            }
+            Language::JavaScript => rest.starts_with("///"),
+            Language::C => rest.starts_with("///") || rest.starts_with("//!"),
+            Language::CSharp | Language::Swift => rest.starts_with("///") && !rest.starts_with("////"),
+            _ => false,
+        };
+        if doc {
+            SpanKind::DocComment
+        } else {
+            SpanKind::LineComment
+        }
+    }
+
+    /// Classifies a `/*` comment.
+    fn block_comment_kind(&self, at: usize) -> SpanKind {
+        let rest = &self.src[at..];
+        let javadoc =
+            rest.starts_with("/**") && !rest.starts_with("/***") && !rest.starts_with("/**/");
+        let doc = match self.language {
+            Language::Rust | Language::C => javadoc || rest.starts_with("/*!"),
+            Language::JavaScript
+            | Language::Java
+            | Language::CSharp
+            | Language::Php
+            | Language::Swift
+            | Language::Kotlin
+            | Language::Scala => javadoc,
+            _ => false,
+        };
+        if doc {
+            SpanKind::DocComment
+        } else {
+            SpanKind::BlockComment
+        }
+    }
+
+    /// Returns the end of a block comment; Rust, Swift, Kotlin and Scala
+    /// block comments nest.
+    fn block_comment_end(&self, at: usize) -> usize {
+        if !matches!(
+            self.language,
+            Language::Rust | Language::Swift | Language::Kotlin | Language::Scala
+        ) {
+            return self.find(at + 2, "*/");
+        }
+
+        let mut depth = 0usize;
+        let mut pos = at;
+        while pos < self.bytes.len() {
+            if self.bytes[pos..].starts_with(b"/*") {
+                depth += 1;
+                pos += 2;
+            } else if self.bytes[pos..].starts_with(b"*/") {
+                depth -= 1;
+                pos += 2;
+                if depth == 0 {
+                    return pos;
+                }
+            } else {
+                pos += 1;
+            }
+        }
+        self.bytes.len()
+    }
+
+    /// Returns the end of a string or char literal closed by `quote`, with
+    /// backslash escapes. An unescaped line break ends it early, except in
+    /// Rust, PHP, Ruby and shell strings, which may span lines.
+    fn quoted_end(&self, at: usize, quote: u8) -> usize {
+        let multiline = match self.language {
+            Language::Rust => quote == b'"',
+            Language::Php | Language::Ruby | Language::Shell => true,
+            _ => false,
+        };
+        let mut pos = at + 1;
+        while pos < self.bytes.len() {
+            match self.bytes[pos] {
+                b'\\' => pos += 2,
+                b'\n' if !multiline => return pos,
+                byte if byte == quote => return pos + 1,
+                _ => pos += 1,
+            }
+        }
+        self.bytes.len()
+    }
+
+    /// Lexes a Python string, with or without triple quotes. Triple-quoted
+    /// strings that stand alone on their lines are docstrings.
+    fn python_string(&self, at: usize) -> (SpanKind, usize) {
+        let quote = self.bytes[at];
+        let triple = [quote; 3];
+        if !self.bytes[at..].starts_with(&triple) {
+            return (SpanKind::String, self.quoted_end(at, quote));
+        }
+
+        let mut pos = at + 3;
+        let end = loop {
+            match self.bytes.get(pos) {
+                None => break self.bytes.len(),
+                Some(b'\\') => pos += 2,
+                Some(_) if self.bytes[pos..].starts_with(&triple) => break pos + 3,
+                Some(_) => pos += 1,
+            }
+        };
+
+        let line_start = self.src[..at].rfind('\n').map_or(0, |i| i + 1);
+        let prefix = self.src[line_start..at].trim_start();
+        let rest = self.src[end..self.line_end(end)].trim_start();
+        let standalone = prefix.chars().all(|c| "rRuUbB".contains(c))
+            && (rest.is_empty() || rest.starts_with('#'));
+        let kind = if standalone {
+            SpanKind::DocComment
+        } else {
+            SpanKind::String
+        };
+        (kind, end)
+    }
+
+    /// Lexes a Rust raw string (`r"…"`, `r#"…"#`, `br"…"`, `cr"…"`) starting
+    /// at its `r`.
+    fn rust_raw_string(&self, at: usize) -> Option<(SpanKind, usize)> {
+        // The `r` starts a word, or follows a lone `b`/`c` prefix
+        let prefixed = at >= 1
+            && matches!(self.bytes[at - 1], b'b' | b'c')
+            && (at < 2 || !is_word_byte(self.bytes[at - 2]));
+        if at > 0 && is_word_byte(self.bytes[at - 1]) && !prefixed {
+            return None;
+        }
+
+        let hashes = self.bytes[at + 1..].iter().take_while(|&&b| b == b'#').count();
+        if self.bytes.get(at + 1 + hashes) != Some(&b'"') {
+            return None;
+        }
+        let closing = format!("\"{}", "#".repeat(hashes));
+        Some((SpanKind::String, self.find(at + 2 + hashes, &closing)))
+    }
+
+    /// Lexes a Swift raw string (`#"…"#`, `##"…"##`, `#"""…"""#`) starting
+    /// at its first `#`.
+    fn swift_raw_string(&self, at: usize) -> Option<(SpanKind, usize)> {
+        let hashes = self.bytes[at..].iter().take_while(|&&b| b == b'#').count();
+        let quote = at + hashes;
+        if self.bytes.get(quote) != Some(&b'"') {
+            return None;
+        }
+        let hashes = "#".repeat(hashes);
+        let (open, closing) = if self.src[quote..].starts_with("\"\"\"") {
+            (3, format!("\"\"\"{hashes}"))
+        } else {
+            (1, format!("\"{hashes}"))
+        };
+        Some((SpanKind::String, self.find(quote + open, &closing)))
+    }
+
+    /// Lexes a Rust char or byte literal, or returns `None` for a lifetime
+    /// or label.
+    fn rust_char(&self, at: usize) -> Option<(SpanKind, usize)> {
+        let rest = &self.src[at + 1..];
+        let mut chars = rest.chars();
+        match chars.next()? {
+            '\\' => Some((SpanKind::String, self.quoted_end(at, b'\''))),
+            c if c != '\n' && chars.next() == Some('\'') => {
+                Some((SpanKind::String, at + 2 + c.len_utf8()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Lexes a C++ raw string (`R"delim(…)delim"`, optionally with an
+    /// encoding prefix) starting at its `R`.
+    fn cpp_raw_string(&self, at: usize) -> Option<(SpanKind, usize)> {
+        let word_start = self.src[..at]
+            .rfind(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .map_or(0, |i| i + 1);
+        if !["", "L", "u", "U", "u8"].contains(&&self.src[word_start..at]) {
+            return None;
+        }
+
+        let open = self.src[at + 2..].find('(')?;
+        let delimiter = &self.src[at + 2..at + 2 + open];
+        if delimiter.len() > 16 || delimiter.contains(|c: char| c.is_whitespace() || c == ')') {
+            return None;
+        }
+        let closing = format!("){delimiter}\"");
+        Some((SpanKind::String, self.find(at + 3 + open, &closing)))
+    }
+
+    /// Returns the end of a C# verbatim string whose `"` is at `at`; a
+    /// doubled `""` inside it is a quote.
+    fn verbatim_end(&self, at: usize) -> usize {
+        let mut pos = at + 1;
+        while pos < self.bytes.len() {
+            match self.bytes[pos] {
+                b'"' if self.bytes.get(pos + 1) == Some(&b'"') => pos += 2,
+                b'"' => return pos + 1,
+                _ => pos += 1,
+            }
+        }
+        self.bytes.len()
+    }
+
+    /// Lexes a Lua comment: `--[[ … ]]` and `--[==[ … ]==]` are block
+    /// comments, `---` starts a doc comment.
+    fn lua_comment(&self, at: usize) -> (SpanKind, usize) {
+        if let Some(end) = self.lua_long_bracket_end(at + 2) {
+            return (SpanKind::BlockComment, end);
+        }
+        let rest = &self.src[at..];
+        let kind = if rest.starts_with("---") && !rest.starts_with("----") {
+            SpanKind::DocComment
+        } else {
+            SpanKind::LineComment
+        };
+        (kind, self.line_end(at))
+    }
+
+    /// Returns the end of a Lua long bracket (`[[ … ]]`, `[=[ … ]=]`)
+    /// opening at `at`, or `None` if there is none.
+    fn lua_long_bracket_end(&self, at: usize) -> Option<usize> {
+        if self.bytes.get(at) != Some(&b'[') {
+            return None;
+        }
+        let level = self.bytes[at + 1..].iter().take_while(|&&b| b == b'=').count();
+        if self.bytes.get(at + 1 + level) != Some(&b'[') {
+            return None;
+        }
+        let closing = format!("]{}]", "=".repeat(level));
+        Some(self.find(at + 2 + level, &closing))
+    }
+
+    /// Returns `true` if `at` starts a shell word, where `#` starts a
+    /// comment rather than being part of `$#` or `${#x}`.
+    fn starts_shell_word(&self, at: usize) -> bool {
+        at == 0 || matches!(self.bytes[at - 1], b' ' | b'\t' | b'\n' | b';' | b'|' | b'&' | b'(')
+    }
+
+    /// Returns the end of a Ruby (`<<~EOS`, `<<-EOS`, `<<EOS`), shell
+    /// (`<<EOF`, `<<-'EOF'`) or PHP (`<<<EOT`) heredoc opener at `at`, with
+    /// its terminator and whether the terminator may be indented.
+    fn heredoc_opener(&self, at: usize) -> Option<(usize, (String, bool))> {
+        let rest = &self.src[at..];
+        let (opener, indented) = match self.language {
+            Language::Php if rest.starts_with("<<<") => (3, true),
+            Language::Ruby | Language::Shell
+                if rest.starts_with("<<") && !rest.starts_with("<<<") && !self.src[..at].ends_with('<') =>
+            {
+                match self.bytes.get(at + 2) {
+                    Some(b'~' | b'-') => (3, true),
+                    _ => (2, false),
+                }
+            }
+            _ => return None,
+        };
+
+        let mut pos = at + opener;
+        if self.language != Language::Ruby {
+            pos += self.bytes[pos..].iter().take_while(|&&b| b == b' ' || b == b'\t').count();
+        }
+        let quote = match self.bytes.get(pos) {
+            Some(&quote @ (b'\'' | b'"' | b'`')) => Some(quote),
+            Some(b'\\') if self.language == Language::Shell => {
+                pos += 1;
+                None
+            }
+            _ => None,
+        };
+        let start = pos + usize::from(quote.is_some());
+        let end = self.src[start..]
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .map_or(self.src.len(), |i| start + i);
+        let terminator = &self.src[start..end];
+
+        // A bare Ruby `<<name` is more likely a shift or an append
+        let bare = self.language == Language::Ruby && !indented && quote.is_none();
+        if terminator.is_empty()
+            || terminator.starts_with(|c: char| c.is_ascii_digit())
+            || (bare && !terminator.starts_with(|c: char| c.is_uppercase()))
+        {
+            return None;
+        }
+        let end = match quote {
+            Some(quote) if self.bytes.get(end) == Some(&quote) => end + 1,
+            Some(_) => return None,
+            None => end,
+        };
+        Some((end, (terminator.to_string(), indented)))
+    }
+
+    /// Lexes the bodies of the heredocs opened on the line ending at `at`
+    /// as one string, from that line break through the last terminator.
+    fn heredoc_bodies(&mut self, at: usize) {
+        let mut end = at;
+        let mut line_end = at;
+        for (terminator, indented) in std::mem::take(&mut self.heredocs) {
+            while line_end < self.bytes.len() {
+                let line_start = line_end + 1;
+                line_end = self.line_end(line_start);
+                let line = &self.src[line_start..line_end];
+                let body = if indented { line.trim_start() } else { line };
+                let closes = body.strip_prefix(terminator.as_str()).is_some_and(|rest| {
+                    !rest.starts_with(|c: char| c.is_alphanumeric() || c == '_')
+                        && (self.language == Language::Php || rest.trim_end().is_empty())
+                });
+                if closes {
+                    end = line_end - (body.len() - terminator.len());
+                    break;
+                }
+                end = line_end;
+            }
+        }
+
+        self.tokens.push(Span {
+            kind: SpanKind::String,
+            range: at..end,
+        });
+        self.pos = end;
+    }
+
+    /// Lexes a Ruby percent literal (`%q{…}`, `%w[…]`, `%r!…!i`, `%(…)`)
+    /// starting at its `%`, or returns `None` for a modulo operator.
+    /// Bracket delimiters nest.
+    fn ruby_percent_literal(&self, at: usize) -> Option<(SpanKind, usize)> {
+        let typed = self.bytes.get(at + 1).filter(|b| b"qQwWiIrsx".contains(b)).copied();
+        let mut pos = at + 1 + usize::from(typed.is_some());
+        let open = *self.bytes.get(pos)?;
+        if open.is_ascii_alphanumeric()
+            || open.is_ascii_whitespace()
+            || !open.is_ascii()
+            || (typed.is_none() && open == b'=')
+        {
+            return None;
+        }
+        // `x %w[a]` passes an array to `x`, `x % w` takes a modulo
+        let after_space = typed.is_some() && at > 0 && self.bytes[at - 1].is_ascii_whitespace();
+        if !self.regex_allowed && !after_space {
+            return None;
+        }
+
+        let close = match open {
+            b'(' => b')',
+            b'[' => b']',
+            b'{' => b'}',
+            b'<' => b'>',
+            _ => open,
+        };
+        let mut depth = 0usize;
+        pos += 1;
+        while pos < self.bytes.len() {
+            match self.bytes[pos] {
+                b'\\' => pos += 2,
+                byte if byte == close && depth == 0 => {
+                    pos += 1;
+                    if typed == Some(b'r') {
+                        pos += self.bytes[pos..].iter().take_while(|b| b.is_ascii_alphabetic()).count();
+                    }
+                    return Some((SpanKind::String, pos));
+                }
+                byte if byte == close => {
+                    depth -= 1;
+                    pos += 1;
+                }
+                byte if byte == open => {
+                    depth += 1;
+                    pos += 1;
+                }
+                _ => pos += 1,
+            }
+        }
+        Some((SpanKind::String, self.bytes.len()))
+    }
+
+    /// Returns `true` if `at` is the first byte of its line.
+    fn at_line_start(&self, at: usize) -> bool {
+        at == 0 || self.bytes[at - 1] == b'\n'
+    }
+
+    /// Returns `true` if the `'` at `at` separates digits (`1'000'000`).
+    fn is_digit_separator(&self, at: usize) -> bool {
+        let number_start = self.src[..at]
+            .rfind(|c: char| !c.is_ascii_alphanumeric() && c != '\'' && c != '.')
+            .map_or(0, |i| i + 1);
+        number_start < at && self.bytes[number_start].is_ascii_digit()
+    }
+
+    /// Returns the end of a JavaScript or Ruby regex literal, including its
+    /// flags.
+    fn regex_end(&self, at: usize) -> usize {
+        let mut pos = at + 1;
+        let mut in_class = false;
+        while pos < self.bytes.len() {
+            match self.bytes[pos] {
+                b'\\' => pos += 1,
+                b'\n' => return pos,
+                b'[' => in_class = true,
+                b']' => in_class = false,
+                b'/' if !in_class => {
+                    pos += 1;
+                    while pos < self.bytes.len() && self.bytes[pos].is_ascii_alphabetic() {
+                        pos += 1;
+                    }
+                    return pos;
+                }
+                _ => {}
+            }
+            pos += 1;
+        }
+        self.bytes.len()
+    }
+
+    /// Lexes a JavaScript template literal, whose `${…}` substitutions are
+    /// code.
+    fn template(&mut self) {
+        let mut start = self.pos;
+        let mut pos = self.pos + 1;
+
+        while pos < self.bytes.len() {
+            match self.bytes[pos] {
+                b'\\' => pos += 2,
+                b'`' => {
+                    pos += 1;
+                    break;
+                }
+                b'$' if self.bytes.get(pos + 1) == Some(&b'{') => {
+                    self.tokens.push(Span {
+                        kind: SpanKind::String,
+                        range: start..pos + 2,
+                    });
+                    self.pos = pos + 2;
+                    self.regex_allowed = true;
+                    self.run(true);
+                    // Resume at the closing `}`, which belongs to the literal
+                    start = self.pos;
+                    pos = self.pos + 1;
+                }
+                _ => pos += 1,
+            }
+        }
+
+        let end = pos.min(self.bytes.len());
+        if start < end {
+            self.tokens.push(Span {
+                kind: SpanKind::String,
+                range: start..end,
+            });
+        }
+        self.pos = end;
+    }
+
+    /// Returns the position of the line break ending the line at `at`.
+    fn line_end(&self, at: usize) -> usize {
+        self.src[at..].find('\n').map_or(self.bytes.len(), |i| at + i)
+    }
+
+    /// Returns the position just past the next `pattern` at or after `at`,
+    /// or the end of the input.
+    fn find(&self, at: usize, pattern: &str) -> usize {
+        let at = at.min(self.bytes.len());
+        self.src[at..]
+            .find(pattern)
+            .map_or(self.bytes.len(), |i| at + i + pattern.len())
+    }
+
+    /// Returns the length of the character at `at`.
+    fn char_len(&self, at: usize) -> usize {
+        self.src[at..].chars().next().map_or(1, char::len_utf8)
+    }
+}
+
+/// Returns `true` for bytes of identifiers and numbers.
+const fn is_word_byte(byte: u8) -> bool {
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte == b'$'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Returns the non-code spans as `(kind, text)` pairs.
+    fn tokens(source: &str, language: Language) -> Vec<(SpanKind, &str)> {
+        lex(source, language)
+            .into_iter()
+            .filter(|span| span.kind != SpanKind::Code)
+            .map(|span| (span.kind, &source[span.range]))
+            .collect()
+    }
+
+    #[test]
+    fn test_spans_cover_source() {
+        let source = "let s = \"a // b\"; // c\n/* d */ x";
+        let spans = lex(source, Language::Rust);
+
+        let joined: String = spans.iter().map(|span| &source[span.range.clone()]).collect();
+        assert_eq!(joined, source);
+    }
+
+    #[test]
+    fn test_rust_raw_strings_and_chars() {
+        let source = r##"let a = r#"x // "y" "#; let b = br"/*"; let c = '"'; let d = '\''; fn f<'a>(x: &'a str) {} // end"##;
+
+        assert_eq!(
+            tokens(source, Language::Rust),
+            [
+                (SpanKind::String, r##"r#"x // "y" "#"##),
+                (SpanKind::String, r#"r"/*""#),
+                (SpanKind::String, "'\"'"),
+                (SpanKind::String, r"'\''"),
+                (SpanKind::LineComment, "// end"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rust_nested_and_doc_comments() {
+        let source = "/* a /* b */ c */ x\n/// doc\n//// not doc\n/** doc */ /**/ y //! inner";
+
+        assert_eq!(
+            tokens(source, Language::Rust),
+            [
+                (SpanKind::BlockComment, "/* a /* b */ c */"),
+                (SpanKind::DocComment, "/// doc"),
+                (SpanKind::LineComment, "//// not doc"),
+                (SpanKind::DocComment, "/** doc */"),
+                (SpanKind::BlockComment, "/**/"),
+                (SpanKind::DocComment, "//! inner"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_javascript_templates_and_regexes() {
+        let source = "const a = `x // ${b + `/* ${c} */`} y`; const r = /\\/\\/[/]/g; z = a / 2 / 3; // end";
+
+        assert_eq!(
+            tokens(source, Language::JavaScript),
+            [
+                (SpanKind::String, "`x // ${"),
+                (SpanKind::String, "`/* ${"),
+                (SpanKind::String, "} */`"),
+                (SpanKind::String, "} y`"),
+                (SpanKind::String, "/\\/\\/[/]/g"),
+                (SpanKind::LineComment, "// end"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_javascript_regex_after_keyword() {
+        let source = "if (x) return /a\\/b/.test(y); else z = x[1] / 2 // c";
+
+        assert_eq!(
+            tokens(source, Language::JavaScript),
+            [(SpanKind::String, "/a\\/b/"), (SpanKind::LineComment, "// c")]
+        );
+    }
+
+    #[test]
+    fn test_python_strings_and_docstrings() {
+        let source = "def f():\n    \"\"\"Doc # not comment\"\"\"\n    s = 'it''s # no' # yes\n    t = '''a\n# b'''\n";
+
+        assert_eq!(
+            tokens(source, Language::Python),
+            [
+                (SpanKind::DocComment, "\"\"\"Doc # not comment\"\"\""),
+                (SpanKind::String, "'it'"),
+                (SpanKind::String, "'s # no'"),
+                (SpanKind::LineComment, "# yes"),
+                (SpanKind::String, "'''a\n# b'''"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_go_raw_strings_and_runes() {
+        let source = "s := `a // b` + \"/*\"; r := '\\''; // c";
+
+        assert_eq!(
+            tokens(source, Language::Go),
+            [
+                (SpanKind::String, "`a // b`"),
+                (SpanKind::String, "\"/*\""),
+                (SpanKind::String, "'\\''"),
+                (SpanKind::LineComment, "// c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_cpp_raw_strings_and_digit_separators() {
+        let source = "auto s = R\"x(a )\" // b)x\"; int n = 1'000'000; char c = '/'; // d";
+
+        assert_eq!(
+            tokens(source, Language::C),
+            [
+                (SpanKind::String, "R\"x(a )\" // b)x\""),
+                (SpanKind::String, "'/'"),
+                (SpanKind::LineComment, "// d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_java_text_blocks() {
+        let source = "String s = \"\"\"\n  // not a comment\n  \"\"\"; /** doc */";
+
+        assert_eq!(
+            tokens(source, Language::Java),
+            [
+                (SpanKind::String, "\"\"\"\n  // not a comment\n  \"\"\""),
+                (SpanKind::DocComment, "/** doc */"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_csharp_verbatim_strings() {
+        let source = "var p = @\"C:\\\"\"x\"\"// no\"; var q = $@\"{a}\"; /// doc\n/* c */";
+
+        assert_eq!(
+            tokens(source, Language::CSharp),
+            [
+                (SpanKind::String, "@\"C:\\\"\"x\"\"// no\""),
+                (SpanKind::String, "@\"{a}\""),
+                (SpanKind::DocComment, "/// doc"),
+                (SpanKind::BlockComment, "/* c */"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ruby_and_shell_hash_comments() {
+        let ruby = "x = \"a # b\" # c\n=begin\nblock\n=end\ny = 7 / 2 / 1";
+        assert_eq!(
+            tokens(ruby, Language::Ruby),
+            [
+                (SpanKind::String, "\"a # b\""),
+                (SpanKind::LineComment, "# c"),
+                (SpanKind::BlockComment, "=begin\nblock\n=end"),
+            ]
+        );
+
+        let literals = "x = s =~ /a#b/i # c\ny = %q{a # {b}} + %w[#x] # d\nz = a / b % c # e\nputs %r!#!";
+        assert_eq!(
+            tokens(literals, Language::Ruby),
+            [
+                (SpanKind::String, "/a#b/i"),
+                (SpanKind::LineComment, "# c"),
+                (SpanKind::String, "%q{a # {b}}"),
+                (SpanKind::String, "%w[#x]"),
+                (SpanKind::LineComment, "# d"),
+                (SpanKind::LineComment, "# e"),
+                (SpanKind::String, "%r!#!"),
+            ]
+        );
+
+        let shell = "echo ${#x} $# 'it # no' # yes\nurl=http://x#y";
+        assert_eq!(
+            tokens(shell, Language::Shell),
+            [(SpanKind::String, "'it # no'"), (SpanKind::LineComment, "# yes")]
+        );
+    }
+
+    #[test]
+    fn test_heredocs_are_strings() {
+        let ruby = "sql = <<~SQL.strip # c\n  # kept\n  SQL\nputs(<<-A, <<'B')\n# a\n  A\n# b\nB\nx << y # d";
+        assert_eq!(
+            tokens(ruby, Language::Ruby),
+            [
+                (SpanKind::LineComment, "# c"),
+                (SpanKind::String, "\n  # kept\n  SQL"),
+                (SpanKind::String, "\n# a\n  A\n# b\nB"),
+                (SpanKind::LineComment, "# d"),
+            ]
+        );
+
+        let shell = "cat <<-'EOF' > x.conf\n\t# kept\n\tEOF\necho $((1 << 2)) # c";
+        assert_eq!(
+            tokens(shell, Language::Shell),
+            [(SpanKind::String, "\n\t# kept\n\tEOF"), (SpanKind::LineComment, "# c")]
+        );
+
+        let php = "$s = <<<EOT\n  # kept\n  EOT; # c";
+        assert_eq!(
+            tokens(php, Language::Php),
+            [(SpanKind::String, "\n  # kept\n  EOT"), (SpanKind::LineComment, "# c")]
+        );
+    }
+
+    #[test]
+    fn test_php_hash_comments_and_attributes() {
+        let source = "#[Test]\n$a = 'x // y'; # c\n/** doc */";
+
+        assert_eq!(
+            tokens(source, Language::Php),
+            [
+                (SpanKind::String, "'x // y'"),
+                (SpanKind::LineComment, "# c"),
+                (SpanKind::DocComment, "/** doc */"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sql_and_lua_dash_comments() {
+        let sql = "SELECT '--x' AS \"a--b\" -- c\n/* d */ FROM t";
+        assert_eq!(
+            tokens(sql, Language::Sql),
+            [
+                (SpanKind::String, "'--x'"),
+                (SpanKind::String, "\"a--b\""),
+                (SpanKind::LineComment, "-- c"),
+                (SpanKind::BlockComment, "/* d */"),
+            ]
+        );
+
+        let lua = "local s = [==[a ]] -- b]==] --[[ c\n]] --- doc\nx = 7 // 2 -- e";
+        assert_eq!(
+            tokens(lua, Language::Lua),
+            [
+                (SpanKind::String, "[==[a ]] -- b]==]"),
+                (SpanKind::BlockComment, "--[[ c\n]]"),
+                (SpanKind::DocComment, "--- doc"),
+                (SpanKind::LineComment, "-- e"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_swift_kotlin_scala_nested_comments() {
+        let source = "/* a /* b */ c */ val s = \"\"\"\n// no\n\"\"\"";
+
+        for language in [Language::Swift, Language::Kotlin, Language::Scala] {
+            assert_eq!(
+                tokens(source, language),
+                [
+                    (SpanKind::BlockComment, "/* a /* b */ c */"),
+                    (SpanKind::String, "\"\"\"\n// no\n\"\"\""),
+                ],
+                "{language:?}"
+            );
+        }
+        assert_eq!(tokens("val s = 'sym // c", Language::Scala), [(SpanKind::LineComment, "// c")]);
+
+        let swift = "let s = #\"a \" // b\"# // c\nlet t = ##\"\"\"\n\"\"\"# // no\n\"\"\"##\n#if DEBUG // d";
+        assert_eq!(
+            tokens(swift, Language::Swift),
+            [
+                (SpanKind::String, "#\"a \" // b\"#"),
+                (SpanKind::LineComment, "// c"),
+                (SpanKind::String, "##\"\"\"\n\"\"\"# // no\n\"\"\"##"),
+                (SpanKind::LineComment, "// d"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strip_comments_keeps_line_numbers() {
+        let source = "a /* x\ny */ b\nc // z\n// only\n\"s\n\"";
+        let lines = strip_comments(source, Language::Rust, |_| true);
+
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["a", " b", "c", "", "\"s", "\""]);
+        assert!(lines[3].emptied);
+        assert!(!lines[4].continued && lines[5].continued);
+        assert_eq!(lines[2].number, 3);
+    }
+
+    #[test]
+    fn test_strip_comments_separates_tokens() {
+        let lines = strip_comments("a/* c */b", Language::C, |_| true);
+
+        assert_eq!(lines[0].text, "a b");
+    }
+
+    #[test]
+    fn test_code_masks_strings() {
+        let lines = strip_comments("f(\"(\", '{') // }", Language::Rust, |_| false);
+
+        assert_eq!(lines[0].code.matches('(').count(), 1);
+        assert!(!lines[0].code.contains('{'));
+        assert!(!lines[0].code.contains('}'));
+    }
+}
@@ -8,7 +8,7 @@
 //! - Smart chunking with configurable token limits
 //! - Multiple output formats (Markdown, XML, JSON)
 //! - Atomic file operations with automatic backups
-//! - Advanced tokenization strategies
+//! - Advanced tokenization strategies, including exact BPE counts
 //!
 //! ## Quick Start
 //!
@@ -45,30 +45,54 @@
     clippy::nursery
 )]
 #![allow(clippy::module_name_repetitions)]
+// Crate-internal items are `pub(crate)`, as `unreachable_pub` asks
+#![allow(clippy::redundant_pub_crate)]
 
+mod boundary;
+mod bpe;
+mod cache;
 mod config;
+mod config_file;
+mod diff;
 mod error;
 mod file;
 mod filter;
+mod git;
+mod lexer;
 mod pipeline;
+mod prompt;
+mod render;
 mod scanner;
+mod source;
+mod sourcemap;
 mod splitter;
 mod template;
 mod template_validator;
 mod token;
+mod tree;
+mod watch;
 mod writer;
 
 pub mod preset;
 pub mod api;
 
 pub use config::{Config, ConfigBuilder, OutputFormat};
+pub use config_file::{ConfigSource, ConfigValue, LayeredConfig, PROJECT_CONFIG_FILE};
+pub use diff::{ContentMode, DiffContext, FileDiff};
 pub use error::{Error, Result};
-pub use file::FileData;
+pub use file::{FileData, FilePart, LineRange};
 pub use filter::{CodeFilter, FileFilterConfig, FilterConfig};
-pub use pipeline::{Pipeline, PipelineStats};
+pub use git::{GitRevSource, GitSelection};
+pub use pipeline::{Pipeline, PipelineStats, RunOutput};
 pub use preset::{LLMPreset, PresetKind};
-pub use splitter::Chunk;
+pub use render::{ChunkSummary, RenderedChunk, RunSummary};
+pub use source::{
+    FileSource, FilesystemSource, MemorySource, PathListSource, SourceEntry, TarSource,
+};
+pub use sourcemap::{resolve, ByteRange, ChunkMap, FileSection, LineSegment, SourceMap, SOURCE_MAP_FILE};
+pub use splitter::{Chunk, PackingStrategy};
 pub use token::{TokenEstimator, TokenizerKind};
+pub use watch::{Watcher, DEFAULT_DEBOUNCE, DEFAULT_POLL_INTERVAL};
 
 /// Runs the complete conversion pipeline with the given configuration.
 ///
@@ -1,8 +1,12 @@
 use anyhow::Context;
 use clap::Parser;
-use llm_utl::{Config, FileFilterConfig, FilterConfig, OutputFormat, Pipeline, PresetKind, TokenizerKind};
+use llm_utl::{
+    ConfigSource, ConfigValue, ContentMode, DiffContext, GitRevSource, GitSelection, LayeredConfig,
+    Pipeline, Watcher,
+};
+use std::time::Duration;
 use std::path::PathBuf;
-use tracing_subscriber::{fmt, prelude::*, EnvFilter};
+use tracing_subscriber::{fmt, fmt::writer::BoxMakeWriter, prelude::*, EnvFilter};
 
 #[derive(Parser, Debug)]
 #[command(
@@ -22,40 +26,242 @@ use tracing_subscriber::{fmt, prelude::*, EnvFilter};
       # Use a preset for code review\n  \
       llm-utl --dir ./src --preset code-review\n\n  \
       # Generate JSON output with custom token limit\n  \
-      llm-utl --dir ./src --format json --max-tokens 150000"
+      llm-utl --dir ./src --format json --max-tokens 150000\n\n\
+    CONFIGURATION:\n  \
+      Options can also be set in ~/.config/llm-utl/config.toml, in a .llm-utl.toml \
+      found in the scanned directory or a parent (up to the repository root), and in \
+      LLM_UTL_* environment variables (e.g. LLM_UTL_MAX_TOKENS). Command-line flags \
+      take precedence over environment variables, which take precedence over the \
+      project file and then the user file. Use --print-config to see the result."
 )]
 struct Cli {
-    /// Root directory to scan for source files (must contain code files)
-    #[arg(short, long, default_value = ".", value_name = "PATH")]
-    dir: PathBuf,
+    /// Root directory to scan for source files (must contain code files) [default: .]
+    #[arg(short, long, value_name = "PATH")]
+    dir: Option<PathBuf>,
 
-    /// Output directory for generated prompts
-    #[arg(short, long, default_value = "out", value_name = "PATH")]
-    out: PathBuf,
+    /// Output directory for generated prompts [default: out]
+    #[arg(short, long, value_name = "PATH")]
+    out: Option<PathBuf>,
 
-    /// Output filename pattern
-    #[arg(long, default_value = "prompt_{index:03}.{ext}")]
-    pattern: String,
+    /// Output filename pattern [default: prompt_{index:03}.{ext}]
+    #[arg(long)]
+    pattern: Option<String>,
 
-    /// Output format
-    #[arg(short, long, value_enum, default_value = "markdown")]
-    format: CliFormat,
+    /// Output format [default: markdown]
+    #[arg(short, long, value_enum)]
+    format: Option<CliFormat>,
 
-    /// Max tokens per chunk
-    #[arg(long, default_value_t = 100_000)]
-    max_tokens: usize,
+    /// Max tokens per chunk [default: 100000]
+    #[arg(long)]
+    max_tokens: Option<usize>,
 
-    /// Overlap tokens between chunks
-    #[arg(long, default_value_t = 1_000)]
-    overlap: usize,
+    /// Overlap tokens between chunks [default: 1000]
+    #[arg(long)]
+    overlap: Option<usize>,
 
-    /// Tokenizer to use
-    #[arg(long, value_enum, default_value = "enhanced")]
-    tokenizer: CliTokenizer,
+    /// How files are assigned to chunks [default: sequential]
+    #[arg(long, value_enum)]
+    packing: Option<CliPacking>,
 
-    /// LLM preset for specialized output
-    #[arg(short, long, value_enum)]
-    preset: Option<CliPreset>,
+    /// Tokenizer to use [default: enhanced]
+    #[arg(long, value_enum)]
+    tokenizer: Option<CliTokenizer>,
+
+    /// Vocabulary file for the BPE tokenizer (required with --tokenizer bpe)
+    ///
+    /// Accepts a tiktoken rank file (e.g. cl100k_base.tiktoken) or a
+    /// Hugging Face tokenizer.json with a byte-level BPE model.
+    ///
+    /// Example: llm-utl --tokenizer bpe --tokenizer-file ./cl100k_base.tiktoken
+    #[arg(long, value_name = "PATH")]
+    tokenizer_file: Option<PathBuf>,
+
+    /// LLM preset for specialized output, by id
+    ///
+    /// Built-in presets: code-review, documentation, refactoring, bug-analysis,
+    /// security-audit, test-generation, architecture-review, performance-analysis,
+    /// migration-plan, api-design. Presets from ~/.config/llm-utl/presets/,
+    /// <dir>/.llm-utl/presets/ and --preset-file are also available and
+    /// override built-in presets with the same id.
+    ///
+    /// Example: llm-utl --preset my-team-review --preset-file ./team.toml
+    #[arg(short, long, value_name = "ID")]
+    preset: Option<String>,
+
+    /// Load presets from a TOML or JSON file (can be used multiple times)
+    ///
+    /// Without --preset, a file defining exactly one preset selects it.
+    #[arg(long = "preset-file", value_name = "FILE")]
+    preset_files: Vec<PathBuf>,
+
+    /// Show per-directory file and token totals in the project structure tree
+    #[arg(long)]
+    structure_totals: bool,
+
+    /// Prefix each line of file contents with its original line number
+    ///
+    /// Numbers refer to the source file, so they stay correct after comments
+    /// and tests are stripped and when large files are split.
+    #[arg(long)]
+    line_numbers: bool,
+
+    /// Keep test code (#[test] functions, test modules, test files)
+    #[arg(long)]
+    keep_tests: bool,
+
+    /// Keep regular comments
+    #[arg(long)]
+    keep_comments: bool,
+
+    /// Keep documentation comments
+    #[arg(long)]
+    keep_docs: bool,
+
+    /// Keep debug print statements (println!, dbg!, console.log, ...)
+    #[arg(long)]
+    keep_debug_prints: bool,
+
+    /// Keep blank lines
+    #[arg(long)]
+    keep_blank_lines: bool,
+
+    /// Shorten license headers to their SPDX-License-Identifier line
+    ///
+    /// License headers are kept when comments are removed; with this flag
+    /// only the SPDX line remains. Headers without one are kept whole.
+    #[arg(long)]
+    collapse_headers: bool,
+
+    /// Also remove calls to this function or macro as a debug print (can be used multiple times)
+    ///
+    /// A leading `.` matches a method call on any receiver.
+    ///
+    /// Example: llm-utl --debug-print 'log::debug!' --debug-print logger.debug
+    #[arg(long, value_name = "NAME")]
+    debug_print: Vec<String>,
+
+    /// Only process files matching a glob pattern (can be used multiple times)
+    ///
+    /// Use **/ to match in every directory: **/*.rs rather than *.rs.
+    ///
+    /// Example: llm-utl --include '**/*.rs' --include '**/Cargo.toml'
+    #[arg(long, value_name = "GLOB")]
+    include: Vec<String>,
+
+    /// Skip files matching a glob pattern (can be used multiple times)
+    ///
+    /// Adds to the patterns from config files.
+    ///
+    /// Example: llm-utl --exclude '**/*.lock' --exclude '**/*.min.js'
+    #[arg(long, value_name = "GLOB")]
+    exclude: Vec<String>,
+
+    /// Skip directories matching a glob pattern (can be used multiple times)
+    ///
+    /// Adds to the configured directories (**/templates, **/out and
+    /// **/target by default).
+    ///
+    /// Example: llm-utl --exclude-dir '**/node_modules' --exclude-dir '**/dist'
+    #[arg(long, value_name = "GLOB")]
+    exclude_dir: Vec<String>,
+
+    /// Only process files changed since the merge base of REF and HEAD
+    ///
+    /// Includes uncommitted changes; deleted files are skipped. Uses the local
+    /// repository only, so fetch first if REF is a remote branch.
+    ///
+    /// Example: llm-utl --since main --preset code-review
+    #[arg(long, value_name = "REF", group = "selection")]
+    since: Option<String>,
+
+    /// Only process files with staged changes
+    #[arg(long, group = "selection")]
+    staged: bool,
+
+    /// Only process files with staged or unstaged changes, plus untracked files
+    #[arg(long, group = "selection")]
+    uncommitted: bool,
+
+    /// Only process the files listed in FILE, one per line ("-" reads stdin)
+    ///
+    /// Relative paths are resolved against --dir.
+    ///
+    /// Example: git diff --name-only HEAD~3 | llm-utl --files-from -
+    #[arg(long, value_name = "FILE", group = "selection")]
+    files_from: Option<PathBuf>,
+
+    /// Read files from the local repository at REV instead of the working tree
+    ///
+    /// Nothing is checked out. Hidden files and files matched by the
+    /// .gitignore files of that revision are skipped.
+    ///
+    /// Example: llm-utl --git-rev v1.2.0 --preset documentation
+    #[arg(long, value_name = "REV", conflicts_with_all = ["selection", "diff", "watch"])]
+    git_rev: Option<String>,
+
+    /// Render each file as a unified diff against a base ref
+    ///
+    /// The base is --diff-base, else the --since ref, else HEAD. Files without
+    /// changes are left out. The code-review and bug-analysis presets use this
+    /// mode by default whenever a base is given.
+    ///
+    /// Example: llm-utl --since main --diff
+    #[arg(long, conflicts_with = "full_files")]
+    diff: bool,
+
+    /// Render whole files even when the preset defaults to diffs
+    #[arg(long)]
+    full_files: bool,
+
+    /// Ref to diff against (its merge base with HEAD is used)
+    #[arg(long, value_name = "REF")]
+    diff_base: Option<String>,
+
+    /// Lines of context around each change, or "full" for the whole new file
+    #[arg(long, value_name = "N|full", default_value = "3")]
+    diff_context: DiffContext,
+
+    /// Reuse results from the previous run for unchanged files
+    ///
+    /// Filtered contents and token counts are cached in .llm-utl-cache/ in the
+    /// output directory, and output files whose content is unchanged are not
+    /// rewritten.
+    #[arg(long)]
+    cache: bool,
+
+    /// Write the rendered prompt to stdout instead of the output directory
+    ///
+    /// Fails when the prompt needs more than one chunk, unless --allow-multi
+    /// is given. Logs go to stderr.
+    ///
+    /// Example: llm-utl --stdout | xclip -selection clipboard
+    #[arg(long, conflicts_with_all = ["watch", "dry_run"])]
+    stdout: bool,
+
+    /// With --stdout, write every chunk instead of failing when there are several
+    #[arg(long)]
+    allow_multi: bool,
+
+    /// Text written between chunks with --stdout [default: two newlines]
+    ///
+    /// Example: llm-utl --stdout --allow-multi --separator $'\n\n=====\n\n'
+    #[arg(long, value_name = "TEXT")]
+    separator: Option<String>,
+
+    /// Keep running and regenerate the prompts whenever watched files change
+    ///
+    /// Files are polled, honoring .gitignore and the include/exclude rules.
+    /// Runs use the cache, so only changed chunks are rewritten.
+    #[arg(long)]
+    watch: bool,
+
+    /// Milliseconds without changes before a watch run starts
+    #[arg(long, value_name = "MS", default_value = "300", requires = "watch")]
+    watch_debounce: u64,
+
+    /// Print the effective configuration and where each value came from, then exit
+    #[arg(long)]
+    print_config: bool,
 
     /// Dry run (don't write files)
     #[arg(long)]
@@ -108,120 +314,75 @@ enum CliFormat {
     Custom,
 }
 
-impl From<CliFormat> for OutputFormat {
-    fn from(f: CliFormat) -> Self {
-        match f {
-            CliFormat::Markdown => Self::Markdown,
-            CliFormat::Xml => Self::Xml,
-            CliFormat::Json => Self::Json,
-            CliFormat::Custom => Self::Custom,
-        }
-    }
-}
-
-#[derive(Debug, Clone, Copy, clap::ValueEnum)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 enum CliTokenizer {
     Simple,
     Enhanced,
-}
-
-impl From<CliTokenizer> for TokenizerKind {
-    fn from(t: CliTokenizer) -> Self {
-        match t {
-            CliTokenizer::Simple => Self::Simple,
-            CliTokenizer::Enhanced => Self::Enhanced,
-        }
-    }
+    /// Exact BPE counts (requires --tokenizer-file)
+    Bpe,
 }
 
 #[derive(Debug, Clone, Copy, clap::ValueEnum)]
-enum CliPreset {
-    /// Comprehensive code review
-    CodeReview,
-    /// Documentation generation
-    Documentation,
-    /// Refactoring suggestions
-    Refactoring,
-    /// Bug analysis
-    BugAnalysis,
-    /// Security audit
-    SecurityAudit,
-    /// Test generation
-    TestGeneration,
-    /// Architecture review
-    ArchitectureReview,
-    /// Performance analysis
-    PerformanceAnalysis,
-    /// Migration planning
-    MigrationPlan,
-    /// API design review
-    ApiDesign,
+enum CliPacking {
+    /// Fill chunks in path order
+    Sequential,
+    /// Largest files first, each into the first chunk with room
+    FirstFitDecreasing,
+    /// Keep each directory's files together while packing tightly
+    DirectoryAffinity,
 }
 
-impl From<CliPreset> for PresetKind {
-    fn from(p: CliPreset) -> Self {
-        match p {
-            CliPreset::CodeReview => Self::CodeReview,
-            CliPreset::Documentation => Self::Documentation,
-            CliPreset::Refactoring => Self::Refactoring,
-            CliPreset::BugAnalysis => Self::BugAnalysis,
-            CliPreset::SecurityAudit => Self::SecurityAudit,
-            CliPreset::TestGeneration => Self::TestGeneration,
-            CliPreset::ArchitectureReview => Self::ArchitectureReview,
-            CliPreset::PerformanceAnalysis => Self::PerformanceAnalysis,
-            CliPreset::MigrationPlan => Self::MigrationPlan,
-            CliPreset::ApiDesign => Self::ApiDesign,
-        }
-    }
+/// Returns the name clap accepts for a value enum variant.
+fn value_name(value: impl clap::ValueEnum) -> String {
+    value
+        .to_possible_value()
+        .map(|value| value.get_name().to_string())
+        .unwrap_or_default()
 }
 
 fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     // Настройка трассировки
-    setup_tracing(cli.verbose)?;
+    setup_tracing(cli.verbose, cli.stdout)?;
 
-    // Построение конфигурации
-    let mut builder = Config::builder()
-        .root_dir(cli.dir)
-        .output_dir(cli.out)
-        .output_pattern(cli.pattern)
-        .format(cli.format.into())
-        .max_tokens(cli.max_tokens)
-        .overlap_tokens(cli.overlap)
-        .tokenizer(cli.tokenizer.into())
-        .dry_run(cli.dry_run)
-        .filter_config(FilterConfig {
-            remove_tests: true,
-            remove_doc_comments: true,
-            remove_comments: true,
-            remove_blank_lines: true,
-            preserve_headers: true,
-            remove_debug_prints: true,
-        })
-        .file_filter_config(FileFilterConfig::default()
-                                //.allow_only(vec!("*.toml".to_string()))
-                                //.allow_only(vec!(PathBuf::from("pipeline.rs")))
-            .exclude_directories(vec!("**/templates".to_string(), "**/out".to_string(), "**/target".to_string()))
-        );
-
-    // Добавление preset если указан
-    if let Some(preset) = cli.preset {
-        builder = builder.preset(preset.into());
-    }
+    // Слои конфигурации: defaults < user file < project file < env < CLI
+    let mut layers = LayeredConfig::load(cli.dir.as_deref())
+        .context("Failed to load configuration")?;
+    apply_cli(&mut layers, &cli)?;
 
-    // Добавление template configuration
-    if let Some(template_path) = cli.template {
-        builder = builder.template_path(template_path);
+    if cli.print_config {
+        print!("{layers}");
+        return Ok(());
     }
 
-    if let Some(format_name) = cli.format_name {
-        builder = builder.custom_format_name(format_name);
-    }
+    // Построение конфигурации
+    let mut builder = layers
+        .to_builder()
+        .context("Failed to build configuration")?
+        .dry_run(cli.dry_run);
 
-    if let Some(ext) = cli.ext {
-        builder = builder.custom_extension(ext);
+    if let Some(selection) = git_selection(&cli) {
+        builder = builder.git_selection(selection);
+    }
+    if let Some(rev) = &cli.git_rev {
+        let root_dir = match layers.get("root_dir") {
+            Some(ConfigValue::String(dir)) => PathBuf::from(dir),
+            _ => PathBuf::from("."),
+        };
+        let source = GitRevSource::new(root_dir, rev)
+            .with_context(|| format!("Failed to read revision '{rev}'"))?;
+        builder = builder.source(source);
+    }
+    if cli.diff {
+        builder = builder.content_mode(ContentMode::Diff);
+    } else if cli.full_files {
+        builder = builder.content_mode(ContentMode::Full);
+    }
+    if let Some(base) = &cli.diff_base {
+        builder = builder.diff_base(base.clone());
     }
+    builder = builder.diff_context(cli.diff_context);
 
     // Парсинг template_data из формата key=value
     if !cli.template_data.is_empty() {
@@ -229,7 +390,7 @@ fn main() -> anyhow::Result<()> {
         use serde_json::Value;
 
         let mut custom_data = HashMap::new();
-        for item in cli.template_data {
+        for item in &cli.template_data {
             if let Some((key, value)) = item.split_once('=') {
                 custom_data.insert(
                     key.to_string(),
@@ -245,6 +406,23 @@ fn main() -> anyhow::Result<()> {
     let config = builder.build()
         .context("Failed to build configuration")?;
 
+    if cli.stdout {
+        let stdout = std::io::stdout();
+        Pipeline::new(config)
+            .context("Failed to create pipeline")?
+            .render_to_writer(&mut stdout.lock())
+            .context("Pipeline execution failed")?;
+        return Ok(());
+    }
+
+    if cli.watch {
+        return Watcher::new(config)
+            .context("Failed to create watcher")?
+            .debounce(Duration::from_millis(cli.watch_debounce))
+            .run(|_| true)
+            .context("Watch mode failed");
+    }
+
     // Запуск pipeline
     Pipeline::new(config)
         .context("Failed to create pipeline")?
@@ -254,7 +432,110 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
-fn setup_tracing(verbosity: u8) -> anyhow::Result<()> {
+/// Returns the file selection requested by --since, --staged, --uncommitted or --files-from.
+fn git_selection(cli: &Cli) -> Option<GitSelection> {
+    if let Some(rev) = &cli.since {
+        Some(GitSelection::Since(rev.clone()))
+    } else if cli.staged {
+        Some(GitSelection::Staged)
+    } else if cli.uncommitted {
+        Some(GitSelection::Uncommitted)
+    } else {
+        cli.files_from.clone().map(GitSelection::FilesFrom)
+    }
+}
+
+/// Applies the flags given on the command line as the top configuration layer.
+fn apply_cli(layers: &mut LayeredConfig, cli: &Cli) -> llm_utl::Result<()> {
+    let mut set = |key: &str, value: llm_utl::ConfigValue| layers.set(key, value, ConfigSource::Cli);
+
+    if let Some(dir) = &cli.dir {
+        set("root_dir", dir.clone().into())?;
+    }
+    if let Some(out) = &cli.out {
+        set("output_dir", out.clone().into())?;
+    }
+    if let Some(pattern) = &cli.pattern {
+        set("output_pattern", pattern.clone().into())?;
+    }
+    if let Some(format) = cli.format {
+        set("format", value_name(format).into())?;
+    }
+    if let Some(tokens) = cli.max_tokens {
+        set("max_tokens", tokens.into())?;
+    }
+    if let Some(tokens) = cli.overlap {
+        set("overlap_tokens", tokens.into())?;
+    }
+    if let Some(tokenizer) = cli.tokenizer {
+        set("tokenizer", value_name(tokenizer).into())?;
+    }
+    if let Some(packing) = cli.packing {
+        set("packing", value_name(packing).into())?;
+    }
+    if let Some(path) = &cli.tokenizer_file {
+        set("tokenizer_file", path.clone().into())?;
+    }
+    if let Some(preset) = &cli.preset {
+        set("preset", preset.clone().into())?;
+    }
+    if !cli.preset_files.is_empty() {
+        set("preset_files", cli.preset_files.clone().into())?;
+    }
+    if cli.structure_totals {
+        set("structure_directory_totals", true.into())?;
+    }
+    if cli.line_numbers {
+        set("line_numbers", true.into())?;
+    }
+    if cli.cache {
+        set("cache", true.into())?;
+    }
+    if cli.allow_multi {
+        set("allow_multi", true.into())?;
+    }
+    if let Some(separator) = &cli.separator {
+        set("chunk_separator", separator.clone().into())?;
+    }
+    for (keep, key) in [
+        (cli.keep_tests, "filter.remove_tests"),
+        (cli.keep_comments, "filter.remove_comments"),
+        (cli.keep_docs, "filter.remove_doc_comments"),
+        (cli.keep_debug_prints, "filter.remove_debug_prints"),
+        (cli.keep_blank_lines, "filter.remove_blank_lines"),
+    ] {
+        if keep {
+            set(key, false.into())?;
+        }
+    }
+    if cli.collapse_headers {
+        set("filter.collapse_headers", true.into())?;
+    }
+    if let Some(template) = &cli.template {
+        set("template_path", template.clone().into())?;
+    }
+    if let Some(name) = &cli.format_name {
+        set("custom_format_name", name.clone().into())?;
+    }
+    if let Some(ext) = &cli.ext {
+        set("custom_extension", ext.clone().into())?;
+    }
+
+    for (patterns, key) in [
+        (&cli.include, "files.allow_only"),
+        (&cli.exclude, "files.exclude_files"),
+        (&cli.exclude_dir, "files.exclude_directories"),
+        (&cli.debug_print, "filter.debug_print_names"),
+    ] {
+        if !patterns.is_empty() {
+            layers.extend(key, patterns.iter().cloned(), ConfigSource::Cli)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn setup_tracing(verbosity: u8, to_stderr: bool) -> anyhow::Result<()> {
     let filter = match verbosity {
         0 => EnvFilter::new("llm_utl=info"),
         1 => EnvFilter::new("llm_utl=debug"),
@@ -263,7 +544,17 @@ fn setup_tracing(verbosity: u8) -> anyhow::Result<()> {
 
     tracing_subscriber::registry()
         .with(filter)
-        .with(fmt::layer().with_target(false).with_thread_ids(false))
+        .with(
+            fmt::layer()
+                .with_target(false)
+                .with_thread_ids(false)
+                // Keep stdout clean when the prompt itself goes there
+                .with_writer(if to_stderr {
+                    BoxMakeWriter::new(std::io::stderr)
+                } else {
+                    BoxMakeWriter::new(std::io::stdout)
+                }),
+        )
         .init();
 
     Ok(())
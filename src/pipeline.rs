@@ -1,12 +1,17 @@
 use crate::{
     config::Config,
-    error::Result,
+    error::{Error, Result},
     file::FileData,
+    render::{RenderedChunk, Renderer, RunSummary},
     scanner::Scanner,
-    splitter::Splitter,
+    sourcemap::SourceMap,
+    splitter::{Chunk, Splitter},
+    template::RunContext,
     writer::Writer,
 };
 use serde::Serialize;
+use std::io::Write;
+use std::sync::Arc;
 use std::time::{Duration, Instant};
 use tracing::{info, instrument, warn};
 
@@ -37,6 +42,9 @@ pub struct PipelineStats {
     /// Smallest chunk size in tokens
     pub min_chunk_tokens: usize,
 
+    /// Average share of the chunk limit used per chunk (0.0 to 1.0)
+    pub avg_utilization: f64,
+
     /// Total execution time
     pub duration: Duration,
 
@@ -46,7 +54,7 @@ pub struct PipelineStats {
     /// Time spent splitting
     pub split_duration: Duration,
 
-    /// Time spent writing
+    /// Time spent rendering and writing
     pub write_duration: Duration,
 
     /// Output directory path
@@ -54,16 +62,31 @@ pub struct PipelineStats {
 
     /// Number of files written
     pub files_written: usize,
+
+    /// Number of output files left alone because they were unchanged
+    pub unchanged_files: usize,
+
+    /// Names of the chunk files written in this run
+    pub changed_chunks: Vec<String>,
+
+    /// Number of files reused from the cache
+    pub cache_hits: usize,
+
+    /// Number of files processed because they were not cached
+    pub cache_misses: usize,
 }
 
 impl PipelineStats {
     /// Creates statistics from pipeline execution data.
+    ///
+    /// `chunk_limit` is the token budget each chunk was packed against.
     #[must_use]
     pub fn new(
         total_files: usize,
         text_files: usize,
         binary_files: usize,
         chunks: &[crate::Chunk],
+        chunk_limit: usize,
         duration: Duration,
         scan_duration: Duration,
         split_duration: Duration,
@@ -84,6 +107,15 @@ impl PipelineStats {
 
         let min_chunk_tokens = chunks.iter().map(|c| c.total_tokens).min().unwrap_or(0);
 
+        let (utilization_sum, utilization_count) = chunks
+            .iter()
+            .fold((0.0, 0.0), |(sum, count), c| (sum + c.utilization(chunk_limit), count + 1.0));
+        let avg_utilization = if total_chunks > 0 {
+            utilization_sum / utilization_count
+        } else {
+            0.0
+        };
+
         Self {
             total_files,
             text_files,
@@ -93,12 +125,17 @@ impl PipelineStats {
             avg_tokens_per_chunk,
             max_chunk_tokens,
             min_chunk_tokens,
+            avg_utilization,
             duration,
             scan_duration,
             split_duration,
             write_duration,
             output_directory,
             files_written,
+            unchanged_files: 0,
+            changed_chunks: Vec::new(),
+            cache_hits: 0,
+            cache_misses: 0,
         }
     }
 
@@ -140,11 +177,29 @@ impl PipelineStats {
             "║ Max Chunk Size:       {:>8} tokens                 ║",
             self.max_chunk_tokens
         );
+        println!(
+            "║ Avg Utilization:      {:>7.1}%                        ║",
+            self.avg_utilization * 100.0
+        );
         println!("║                                                       ║");
         println!(
             "║ Files Written:        {:>8}                        ║",
             self.files_written
         );
+        if self.cache_hits + self.cache_misses > 0 {
+            println!(
+                "║   - Unchanged:        {:>8}                        ║",
+                self.unchanged_files
+            );
+            println!(
+                "║ Cache Hits:           {:>8}                        ║",
+                self.cache_hits
+            );
+            println!(
+                "║ Cache Misses:         {:>8}                        ║",
+                self.cache_misses
+            );
+        }
         println!("║ Output Directory:                                     ║");
         println!(
             "║   {}                                              ║",
@@ -184,11 +239,35 @@ impl PipelineStats {
     }
 }
 
+/// Everything a run produces, kept in memory.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunOutput {
+    /// Rendered chunks, in order
+    pub chunks: Vec<RenderedChunk>,
+
+    /// Statistics collected during the run
+    pub stats: PipelineStats,
+
+    /// Summary of the run, as written to `summary.json`
+    pub summary: RunSummary,
+}
+
+impl RunOutput {
+    /// Returns the source map of all chunks, as written to `sourcemap.json`.
+    #[must_use]
+    pub fn source_map(&self) -> SourceMap {
+        SourceMap {
+            chunks: self.chunks.iter().map(|chunk| chunk.source_map.clone()).collect(),
+        }
+    }
+}
+
 /// Main pipeline orchestrator for converting repositories to prompts.
 pub struct Pipeline {
     config: Config,
     scanner: Scanner,
     splitter: Splitter,
+    renderer: Renderer,
     writer: Writer,
 }
 
@@ -199,18 +278,23 @@ impl Pipeline {
     ///
     /// Returns an error if:
     /// - Configuration validation fails
-    /// - Writer initialization fails
+    /// - Tokenizer vocabulary cannot be loaded
+    /// - Template engine initialization fails
     pub fn new(config: Config) -> Result<Self> {
         config.validate()?;
 
-        let scanner = Scanner::new(&config);
-        let splitter = Splitter::new(&config);
-        let writer = Writer::new(&config)?;
+        // Share one tokenizer: BPE vocabularies are expensive to load
+        let tokenizer = config.tokenizer.create()?;
+        let scanner = Scanner::new(&config, Arc::clone(&tokenizer));
+        let splitter = Splitter::new(&config, tokenizer);
+        let renderer = Renderer::new(&config)?;
+        let writer = Writer::new(&config);
 
         Ok(Self {
             config,
             scanner,
             splitter,
+            renderer,
             writer,
         })
     }
@@ -221,7 +305,8 @@ impl Pipeline {
     ///
     /// 1. **Scan**: Discovers and reads files from the root directory
     /// 2. **Split**: Divides content into optimal chunks based on token limits
-    /// 3. **Write**: Renders and persists chunks to output files
+    /// 3. **Render**: Renders chunks through the output template
+    /// 4. **Write**: Persists chunks, `summary.json` and `sourcemap.json`
     ///
     /// # Errors
     ///
@@ -245,6 +330,76 @@ impl Pipeline {
     #[instrument(skip(self), fields(root_dir = %self.config.root_dir.display()))]
     pub fn run(self) -> Result<PipelineStats> {
         let start_time = Instant::now();
+        let RunOutput {
+            chunks,
+            mut stats,
+            summary,
+        } = self.build()?;
+
+        if self.config.dry_run {
+            warn!("Dry run mode enabled - skipping file writes");
+            self.print_dry_run_summary(&chunks);
+        } else {
+            info!("Writing output files...");
+            let write_start = Instant::now();
+            let written = self.writer.write(&chunks, &summary)?;
+            if let Some(cache) = self.scanner.cache() {
+                if let Err(e) = cache.save() {
+                    warn!("Failed to save file cache: {}", e);
+                }
+            }
+
+            // +2 for summary.json and sourcemap.json
+            stats.files_written = written.changed.len() + 2;
+            stats.unchanged_files = written.unchanged;
+            stats.changed_chunks = written.changed;
+            stats.write_duration += write_start.elapsed();
+
+            info!(
+                "✓ Wrote {} files in {:.2}s",
+                stats.files_written,
+                stats.write_duration.as_secs_f64()
+            );
+        }
+
+        stats.duration = start_time.elapsed();
+        info!(
+            "✓ Pipeline completed successfully in {:.2}s",
+            stats.duration.as_secs_f64()
+        );
+
+        Ok(stats)
+    }
+
+    /// Scans, splits and renders the files, returning the rendered chunks
+    /// with statistics and a summary instead of writing them.
+    ///
+    /// Nothing is written to the output directory, including the cache.
+    /// [`PipelineStats::write_duration`] holds the rendering time and
+    /// [`PipelineStats::files_written`] is zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if scanning, splitting or rendering fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::{Config, Pipeline};
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let config = Config::builder().root_dir("./src").build()?;
+    ///
+    /// let output = Pipeline::new(config)?.build()?;
+    /// for chunk in &output.chunks {
+    ///     println!("{}: {} files, {} tokens", chunk.filename, chunk.files.len(), chunk.total_tokens);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[instrument(skip(self), fields(root_dir = %self.config.root_dir.display()))]
+    pub fn build(&self) -> Result<RunOutput> {
+        let start_time = Instant::now();
 
         info!("Starting pipeline execution");
 
@@ -281,50 +436,128 @@ impl Pipeline {
         // Log chunk distribution
         self.log_chunk_distribution(&chunks);
 
-        // Stage 3: Writing
-        let write_start = Instant::now();
-        let files_written = if self.config.dry_run {
-            warn!("Dry run mode enabled - skipping file writes");
-            self.print_dry_run_summary(&chunks);
-            0
-        } else {
-            info!("Stage 3/3: Writing output files...");
-            self.writer.write_chunks(&chunks)?;
-            self.writer.write_summary(&chunks, start_time.elapsed())?;
-            chunks.len() + 1 // +1 for summary.json
-        };
-        let write_duration = write_start.elapsed();
-
-        if !self.config.dry_run {
-            info!(
-                "✓ Wrote {} files in {:.2}s",
-                files_written,
-                write_duration.as_secs_f64()
-            );
-        }
+        // Stage 3: Rendering
+        info!("Stage 3/3: Rendering chunks...");
+        let render_start = Instant::now();
+        let run = RunContext::new(&chunks);
+        let rendered = self.renderer.render(&chunks, &run)?;
+        let render_duration = render_start.elapsed();
 
         let total_duration = start_time.elapsed();
+        let summary = self.renderer.summary(&rendered, &run, total_duration);
 
         // Create statistics
-        let stats = PipelineStats::new(
+        let mut stats = PipelineStats::new(
             total_files,
             text_files,
             binary_files,
             &chunks,
+            self.config.effective_chunk_size(),
             total_duration,
             scan_duration,
             split_duration,
-            write_duration,
+            render_duration,
             self.config.output_dir.display().to_string(),
-            files_written,
+            0,
         );
+        if let Some(cache) = self.scanner.cache() {
+            stats.cache_hits = cache.hits();
+            stats.cache_misses = cache.misses();
+            info!("  Cache: {} hits, {} misses", stats.cache_hits, stats.cache_misses);
+        }
 
-        info!(
-            "✓ Pipeline completed successfully in {:.2}s",
-            total_duration.as_secs_f64()
-        );
+        Ok(RunOutput {
+            chunks: rendered,
+            stats,
+            summary,
+        })
+    }
 
-        Ok(stats)
+    /// Scans and splits the files, then renders every chunk to a string
+    /// without writing anything to the output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if scanning, splitting or rendering fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::{Config, Pipeline};
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let config = Config::builder().root_dir("./src").build()?;
+    ///
+    /// for prompt in Pipeline::new(config)?.render_to_strings()? {
+    ///     println!("{} bytes", prompt.len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_to_strings(&self) -> Result<Vec<String>> {
+        Ok(self
+            .build()?
+            .chunks
+            .into_iter()
+            .map(|chunk| chunk.content)
+            .collect())
+    }
+
+    /// Scans and splits the files, then streams the rendered chunks to `out`,
+    /// separated by [`Config::chunk_separator`]. Returns the number of chunks.
+    ///
+    /// Nothing is written to the output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::TooManyChunks`] if the output needs more than one
+    /// chunk and [`Config::allow_multi`] is not set, or an error if scanning,
+    /// splitting, rendering or writing to `out` fails.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// use llm_utl::{Config, Pipeline};
+    ///
+    /// # fn main() -> anyhow::Result<()> {
+    /// let config = Config::builder().root_dir("./src").build()?;
+    ///
+    /// Pipeline::new(config)?.render_to_writer(&mut std::io::stdout().lock())?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn render_to_writer(&self, out: &mut impl Write) -> Result<usize> {
+        let chunks = self.scan_and_split()?;
+        if chunks.len() > 1 && !self.config.allow_multi {
+            return Err(Error::TooManyChunks { chunks: chunks.len() });
+        }
+
+        let write_err = |e| Error::io("<output stream>", e);
+        let mut first = true;
+        let run = RunContext::new(&chunks);
+        self.renderer.render_each(&chunks, &run, |chunk| {
+            if !first {
+                out.write_all(self.config.chunk_separator.as_bytes())
+                    .map_err(write_err)?;
+            }
+            first = false;
+            out.write_all(chunk.content.as_bytes()).map_err(write_err)
+        })?;
+        out.flush().map_err(write_err)?;
+
+        Ok(chunks.len())
+    }
+
+    /// Executes the scanning and splitting stages.
+    fn scan_and_split(&self) -> Result<Vec<Chunk>> {
+        let files = self.scan()?;
+        info!("✓ Scanned {} files", files.len());
+
+        let chunks = self.splitter.split(files)?;
+        info!("✓ Created {} chunks", chunks.len());
+        self.log_chunk_distribution(&chunks);
+
+        Ok(chunks)
     }
 
     /// Executes the scanning stage.
@@ -365,7 +598,7 @@ impl Pipeline {
     }
 
     /// Prints a summary for dry run mode.
-    fn print_dry_run_summary(&self, chunks: &[crate::Chunk]) {
+    fn print_dry_run_summary(&self, chunks: &[RenderedChunk]) {
         println!("\n╔═══════════════════════════════════════════════════════╗");
         println!("║                 Dry Run Summary                       ║");
         println!("╠═══════════════════════════════════════════════════════╣");
@@ -437,6 +670,110 @@ mod tests {
         assert!(!temp.child("out").exists());
     }
 
+    #[test]
+    fn test_pipeline_cache_reuses_files() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("file1.rs").write_str("fn main() {}").unwrap();
+        temp.child("file2.rs").write_str("pub fn test() {}").unwrap();
+        let out = assert_fs::TempDir::new().unwrap();
+
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .output_dir(out.path())
+            .backup_existing(false)
+            .cache(true)
+            .build()
+            .unwrap();
+
+        let stats = Pipeline::new(config.clone()).unwrap().run().unwrap();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (0, 2));
+        assert_eq!(stats.unchanged_files, 0);
+
+        let stats = Pipeline::new(config.clone()).unwrap().run().unwrap();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (2, 0));
+        assert_eq!(stats.unchanged_files, stats.total_chunks);
+
+        temp.child("file2.rs").write_str("pub fn changed() {}").unwrap();
+        let stats = Pipeline::new(config).unwrap().run().unwrap();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (1, 1));
+        assert_eq!(stats.unchanged_files, 0);
+    }
+
+    #[test]
+    fn test_pipeline_build_keeps_output_in_memory() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("file1.rs").write_str("fn main() {}").unwrap();
+        temp.child("file2.rs").write_str("pub fn test() {}").unwrap();
+
+        let config = create_test_config(temp.path());
+        let output = Pipeline::new(config).unwrap().build().unwrap();
+
+        assert_eq!(output.chunks.len(), 1);
+        let chunk = &output.chunks[0];
+        assert_eq!(chunk.index, 1);
+        assert_eq!(chunk.filename, "prompt_001.md");
+        assert_eq!(chunk.files, ["file1.rs", "file2.rs"]);
+        assert!(chunk.content.contains("pub fn test() {}"));
+        assert_eq!(chunk.total_tokens, output.stats.total_tokens);
+        assert_eq!(output.summary.total_files, 2);
+        assert!(chunk.content.contains(&output.summary.generated_at));
+        assert_eq!(output.source_map().chunks.len(), 1);
+        assert_eq!(output.stats.files_written, 0);
+        assert!(!temp.child("out").exists());
+    }
+
+    #[test]
+    fn test_pipeline_render_to_writer() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("file1.rs").write_str("fn main() {}").unwrap();
+        temp.child("file2.rs").write_str("pub fn test() {}").unwrap();
+
+        let config = create_test_config(temp.path());
+        let pipeline = Pipeline::new(config).unwrap();
+        let mut out = Vec::new();
+        assert_eq!(pipeline.render_to_writer(&mut out).unwrap(), 1);
+
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("fn main() {}"));
+        assert!(out.contains("pub fn test() {}"));
+        assert_eq!(pipeline.render_to_strings().unwrap().len(), 1);
+        assert!(!temp.child("out").exists());
+    }
+
+    #[test]
+    fn test_pipeline_render_to_writer_multiple_chunks() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let body = "fn f() { let x = 1; }\n".repeat(80);
+        temp.child("a.rs").write_str(&body).unwrap();
+        temp.child("b.rs").write_str(&body).unwrap();
+
+        let builder = || {
+            Config::builder()
+                .root_dir(temp.path())
+                .output_dir(temp.path().join("out"))
+                .max_tokens(600)
+                .overlap_tokens(0)
+                .chunk_safety_margin(100)
+        };
+
+        let pipeline = Pipeline::new(builder().build().unwrap()).unwrap();
+        let err = pipeline.render_to_writer(&mut Vec::new()).unwrap_err();
+        assert!(matches!(err, Error::TooManyChunks { chunks } if chunks > 1));
+
+        let config = builder()
+            .allow_multi(true)
+            .chunk_separator("\n=====\n")
+            .build()
+            .unwrap();
+        let pipeline = Pipeline::new(config).unwrap();
+        let mut out = Vec::new();
+        let chunks = pipeline.render_to_writer(&mut out).unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("\n=====\n").count(), chunks - 1);
+        assert_eq!(pipeline.render_to_strings().unwrap().len(), chunks);
+    }
+
     #[test]
     fn test_pipeline_stats_calculation() {
         use crate::{Chunk, FileData};
@@ -470,6 +807,7 @@ mod tests {
             2,
             0,
             &chunks,
+            400,
             Duration::from_secs(1),
             Duration::from_millis(300),
             Duration::from_millis(200),
@@ -483,6 +821,7 @@ mod tests {
         assert_eq!(stats.avg_tokens_per_chunk, 150);
         assert_eq!(stats.max_chunk_tokens, 200);
         assert_eq!(stats.min_chunk_tokens, 100);
+        assert!((stats.avg_utilization - 0.375).abs() < f64::EPSILON);
     }
 
     #[test]
@@ -498,6 +837,7 @@ mod tests {
                 files: vec![],
                 total_tokens: 10000,
             }],
+            20000,
             Duration::from_secs(2),
             Duration::from_secs(1),
             Duration::from_secs(0),
@@ -3,8 +3,13 @@
 //! This module provides pre-configured templates for common LLM tasks like
 //! code review, documentation generation, refactoring, and more.
 
+use crate::config_file::user_config_dir;
+use crate::diff::ContentMode;
+use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
 
 /// Type of preset for LLM tasks.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -86,36 +91,71 @@ impl PresetKind {
 }
 
 /// Preset configuration for LLM tasks.
+///
+/// Presets can be loaded from TOML or JSON files through [`PresetRegistry`];
+/// only `id`, `name` and `user_prompt_template` are required there.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
 pub struct LLMPreset {
     /// Unique preset identifier
     pub id: String,
     /// Human-readable name
     pub name: String,
     /// Description of the preset
+    #[serde(default)]
     pub description: String,
     /// System prompt for the LLM
+    #[serde(default)]
     pub system_prompt: String,
     /// User prompt template
     pub user_prompt_template: String,
     /// Suggested model for this task
+    #[serde(default)]
     pub suggested_model: String,
     /// Maximum tokens hint
+    #[serde(default = "default_max_tokens_hint")]
     pub max_tokens_hint: usize,
     /// Temperature hint for generation
+    #[serde(default = "default_temperature_hint")]
     pub temperature_hint: f32,
     /// Include metadata in output
+    #[serde(default = "default_true")]
     pub include_metadata: bool,
     /// Include directory structure
+    #[serde(default = "default_true")]
     pub include_structure: bool,
     /// Code block style
+    #[serde(default)]
     pub code_block_style: CodeBlockStyle,
+    /// Default content mode when a diff base is known
+    #[serde(default)]
+    pub content_mode: ContentMode,
+}
+
+const fn default_max_tokens_hint() -> usize {
+    100_000
+}
+
+const fn default_temperature_hint() -> f32 {
+    0.3
+}
+
+const fn default_true() -> bool {
+    true
+}
+
+impl From<PresetKind> for LLMPreset {
+    fn from(kind: PresetKind) -> Self {
+        Self::for_kind(kind)
+    }
 }
 
 /// Code block formatting style.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CodeBlockStyle {
     /// Markdown code blocks
+    #[default]
     Markdown,
     /// XML CDATA sections
     Xml,
@@ -123,6 +163,29 @@ pub enum CodeBlockStyle {
     Inline,
 }
 
+impl CodeBlockStyle {
+    /// Returns the ID string for this style, as used in templates.
+    #[must_use]
+    pub const fn id(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::Xml => "xml",
+            Self::Inline => "inline",
+        }
+    }
+
+    /// Parse code block style from string ID.
+    #[must_use]
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "markdown" => Some(Self::Markdown),
+            "xml" => Some(Self::Xml),
+            "inline" => Some(Self::Inline),
+            _ => None,
+        }
+    }
+}
+
 impl LLMPreset {
     /// Creates a preset for the given kind.
     #[must_use]
@@ -202,6 +265,7 @@ Please structure your review with:
             include_metadata: true,
             include_structure: true,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Diff,
         }
     }
 
@@ -247,6 +311,7 @@ Generate structured markdown documentation ready to use."#.to_string(),
             include_metadata: true,
             include_structure: true,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Full,
         }
     }
 
@@ -292,6 +357,7 @@ For each refactoring suggestion, provide:
             include_metadata: true,
             include_structure: true,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Full,
         }
     }
 
@@ -340,6 +406,7 @@ For each bug, provide:
             include_metadata: true,
             include_structure: false,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Diff,
         }
     }
 
@@ -390,6 +457,7 @@ For each security issue:
             include_metadata: true,
             include_structure: true,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Full,
         }
     }
 
@@ -435,6 +503,7 @@ Generate tests with:
             include_metadata: true,
             include_structure: true,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Full,
         }
     }
 
@@ -483,6 +552,7 @@ Provide:
             include_metadata: true,
             include_structure: true,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Full,
         }
     }
 
@@ -530,6 +600,7 @@ For each optimization:
             include_metadata: true,
             include_structure: false,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Full,
         }
     }
 
@@ -574,6 +645,7 @@ Provide:
             include_metadata: true,
             include_structure: true,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Full,
         }
     }
 
@@ -623,6 +695,345 @@ Provide:
             include_metadata: true,
             include_structure: true,
             code_block_style: CodeBlockStyle::Markdown,
+            content_mode: ContentMode::Full,
+        }
+    }
+}
+
+/// Collection of presets addressable by id.
+///
+/// Starts with the built-in presets; presets loaded from files replace
+/// entries with the same id, so teams can override `code-review` and friends.
+///
+/// # File format
+///
+/// A `.toml` or `.json` file holds either a single preset or a `presets` list:
+///
+/// ```toml
+/// [[presets]]
+/// id = "my-team-review"
+/// name = "Team Review"
+/// system_prompt = "You review code for our team."
+/// user_prompt_template = "Review these {file_count} files ({languages})."
+/// ```
+///
+/// # Examples
+///
+/// ```no_run
+/// use llm_utl::preset::PresetRegistry;
+///
+/// let mut registry = PresetRegistry::discover(".")?;
+/// registry.load_file("./team-presets.toml")?;
+///
+/// let preset = registry.get("my-team-review").expect("preset is defined");
+/// println!("{}", preset.name);
+/// # Ok::<(), llm_utl::Error>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct PresetRegistry {
+    presets: BTreeMap<String, LLMPreset>,
+}
+
+impl Default for PresetRegistry {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl PresetRegistry {
+    /// Creates a registry containing only the built-in presets.
+    #[must_use]
+    pub fn builtin() -> Self {
+        Self {
+            presets: LLMPreset::all_presets().into_iter().collect(),
         }
     }
-}
\ No newline at end of file
+
+    /// Creates a registry with the built-in presets plus any presets found in
+    /// the user directory (`~/.config/llm-utl/presets/`) and the project
+    /// directory (`<project_root>/.llm-utl/presets/`), in that order.
+    ///
+    /// An invalid preset file in the user directory is logged and the whole
+    /// directory skipped, so it cannot break runs in every project.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a preset file in the project directory is invalid.
+    pub fn discover(project_root: impl AsRef<Path>) -> Result<Self> {
+        Self::discover_in(project_root.as_ref(), user_presets_dir().as_deref())
+    }
+
+    /// Like [`discover`](Self::discover), with the user preset directory
+    /// given explicitly.
+    pub(crate) fn discover_in(project_root: &Path, user_dir: Option<&Path>) -> Result<Self> {
+        let mut registry = Self::builtin();
+
+        if let Some(user_dir) = user_dir {
+            let mut with_user = registry.clone();
+            match with_user.load_dir(user_dir) {
+                Ok(_) => registry = with_user,
+                Err(e) => tracing::warn!("Skipping user presets in {}: {e}", user_dir.display()),
+            }
+        }
+        registry.load_dir(project_root.join(PROJECT_PRESETS_DIR))?;
+
+        Ok(registry)
+    }
+
+    /// Loads every `.toml` and `.json` file in a directory, in file name order.
+    ///
+    /// A missing directory is not an error. Returns the number of presets loaded.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory cannot be read or a file is invalid.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Ok(0);
+        }
+
+        let mut files: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| Error::io(dir, e))?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.is_file()
+                    && matches!(
+                        path.extension().and_then(|ext| ext.to_str()),
+                        Some("toml" | "json")
+                    )
+            })
+            .collect();
+        files.sort();
+
+        let mut loaded = 0;
+        for file in files {
+            loaded += self.load_file(&file)?.len();
+        }
+
+        Ok(loaded)
+    }
+
+    /// Loads presets from a TOML or JSON file and returns their ids.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or parsed, a preset has an
+    /// empty id, or its user prompt uses an unknown placeholder.
+    pub fn load_file(&mut self, path: impl AsRef<Path>) -> Result<Vec<String>> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| Error::io(path, e))?;
+
+        let presets = parse_preset_file(path, &content)
+            .map_err(|message| Error::preset(path, message))?;
+
+        let mut ids = Vec::with_capacity(presets.len());
+        for preset in presets {
+            if preset.id.trim().is_empty() {
+                return Err(Error::preset(path, "preset id must not be empty"));
+            }
+
+            crate::prompt::PromptTemplate::parse(&preset.id, &preset.user_prompt_template)
+                .map_err(|e| Error::preset(path, e.to_string()))?;
+
+            if PresetKind::from_id(&preset.id).is_some() {
+                tracing::info!(
+                    "Preset '{}' from {} overrides the built-in preset",
+                    preset.id,
+                    path.display()
+                );
+            } else {
+                tracing::debug!("Loaded preset '{}' from {}", preset.id, path.display());
+            }
+
+            ids.push(preset.id.clone());
+            self.insert(preset);
+        }
+
+        Ok(ids)
+    }
+
+    /// Adds a preset, returning the one it replaced.
+    pub fn insert(&mut self, preset: LLMPreset) -> Option<LLMPreset> {
+        self.presets.insert(preset.id.clone(), preset)
+    }
+
+    /// Returns the preset with the given id.
+    #[must_use]
+    pub fn get(&self, id: &str) -> Option<&LLMPreset> {
+        self.presets.get(id)
+    }
+
+    /// Returns the preset with the given id, or an error listing the known ids.
+    ///
+    /// # Errors
+    ///
+    /// Returns a configuration error if no preset has this id.
+    pub fn resolve(&self, id: &str) -> Result<LLMPreset> {
+        self.get(id).cloned().ok_or_else(|| {
+            Error::config(format!(
+                "Unknown preset '{id}'. Available presets: {}",
+                self.ids().collect::<Vec<_>>().join(", ")
+            ))
+        })
+    }
+
+    /// Returns all preset ids in sorted order.
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.presets.keys().map(String::as_str)
+    }
+
+    /// Returns all presets in id order.
+    pub fn iter(&self) -> impl Iterator<Item = &LLMPreset> {
+        self.presets.values()
+    }
+}
+
+const PROJECT_PRESETS_DIR: &str = ".llm-utl/presets";
+
+/// Returns `$XDG_CONFIG_HOME/llm-utl/presets`, falling back to `~/.config`.
+pub(crate) fn user_presets_dir() -> Option<PathBuf> {
+    user_config_dir().map(|dir| dir.join("presets"))
+}
+
+/// Parses a preset file holding one preset or a `presets` list.
+fn parse_preset_file(path: &Path, content: &str) -> std::result::Result<Vec<LLMPreset>, String> {
+    let value: serde_json::Value = match path.extension().and_then(|ext| ext.to_str()) {
+        Some("json") => serde_json::from_str(content).map_err(|e| e.to_string())?,
+        _ => toml::from_str(content).map_err(|e| e.to_string())?,
+    };
+
+    match value {
+        serde_json::Value::Object(mut table) if table.contains_key("presets") => {
+            let presets = table.remove("presets").unwrap_or_default();
+            if !table.is_empty() {
+                return Err("a file with a `presets` list must not define other keys".to_string());
+            }
+            serde_json::from_value(presets).map_err(|e| e.to_string())
+        }
+        value => serde_json::from_value(value)
+            .map(|preset| vec![preset])
+            .map_err(|e| e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::TempDir;
+
+    #[test]
+    fn test_builtin_registry() {
+        let registry = PresetRegistry::builtin();
+
+        for kind in PresetKind::all() {
+            assert_eq!(registry.get(kind.id()).unwrap().id, kind.id());
+        }
+        assert_eq!(registry.ids().count(), PresetKind::all().len());
+    }
+
+    #[test]
+    fn test_load_toml_preset_with_defaults() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("team.toml");
+        fs::write(
+            &path,
+            r#"
+id = "team-review"
+name = "Team Review"
+user_prompt_template = "Review {file_count} files written in {languages}."
+code_block_style = "xml"
+"#,
+        )
+        .unwrap();
+
+        let mut registry = PresetRegistry::builtin();
+        assert_eq!(registry.load_file(&path).unwrap(), vec!["team-review"]);
+
+        let preset = registry.get("team-review").unwrap();
+        assert_eq!(preset.name, "Team Review");
+        assert_eq!(preset.code_block_style, CodeBlockStyle::Xml);
+        assert_eq!(preset.max_tokens_hint, 100_000);
+        assert!(preset.include_structure);
+    }
+
+    #[test]
+    fn test_load_json_list_overrides_builtin() {
+        let temp = TempDir::new().unwrap();
+        let path = temp.path().join("presets.json");
+        fs::write(
+            &path,
+            r#"{"presets": [
+                {"id": "code-review", "name": "Strict Review", "user_prompt_template": "Be strict."},
+                {"id": "quick", "name": "Quick", "user_prompt_template": "Skim it."}
+            ]}"#,
+        )
+        .unwrap();
+
+        let mut registry = PresetRegistry::builtin();
+        registry.load_file(&path).unwrap();
+
+        assert_eq!(registry.get("code-review").unwrap().name, "Strict Review");
+        assert_eq!(registry.resolve("quick").unwrap().user_prompt_template, "Skim it.");
+    }
+
+    #[test]
+    fn test_invalid_preset_files() {
+        let temp = TempDir::new().unwrap();
+        let mut registry = PresetRegistry::builtin();
+
+        let path = temp.path().join("placeholder.toml");
+        fs::write(&path, "id = \"x\"\nname = \"X\"\nuser_prompt_template = \"{code}\"\n").unwrap();
+        let err = registry.load_file(&path).unwrap_err();
+        assert!(err.to_string().contains("unknown placeholder '{code}'"));
+
+        let path = temp.path().join("typo.toml");
+        fs::write(&path, "id = \"x\"\nname = \"X\"\nuser_prompt = \"hi\"\n").unwrap();
+        let err = registry.load_file(&path).unwrap_err();
+        assert!(err.to_string().contains("typo.toml"));
+        assert!(err.to_string().contains("user_prompt"));
+
+        assert!(registry.resolve("x").is_err());
+    }
+
+    #[test]
+    fn test_discover_project_presets() {
+        let temp = TempDir::new().unwrap();
+        let dir = temp.path().join(".llm-utl").join("presets");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("a.toml"),
+            "id = \"shared\"\nname = \"First\"\nuser_prompt_template = \"a\"\n",
+        )
+        .unwrap();
+        fs::write(
+            dir.join("b.toml"),
+            "id = \"shared\"\nname = \"Second\"\nuser_prompt_template = \"b\"\n",
+        )
+        .unwrap();
+        fs::write(dir.join("notes.txt"), "ignored").unwrap();
+
+        let user = TempDir::new().unwrap();
+        fs::write(
+            user.path().join("user.toml"),
+            "[[presets]]\nid = \"shared\"\nname = \"User\"\nuser_prompt_template = \"u\"\n\n[[presets]]\nid = \"mine\"\nname = \"Mine\"\nuser_prompt_template = \"m\"\n",
+        )
+        .unwrap();
+
+        let registry = PresetRegistry::discover_in(temp.path(), Some(user.path())).unwrap();
+        assert_eq!(registry.get("shared").unwrap().name, "Second");
+        assert_eq!(registry.get("mine").unwrap().name, "Mine");
+
+        let registry = PresetRegistry::discover_in(temp.path(), None).unwrap();
+        assert!(registry.get("mine").is_none());
+
+        let mut registry = PresetRegistry::builtin();
+        assert_eq!(registry.load_dir(temp.path().join("missing")).unwrap(), 0);
+
+        // A broken user file skips the user directory, not the run
+        fs::write(user.path().join("zz-broken.toml"), "id = ").unwrap();
+        let registry = PresetRegistry::discover_in(temp.path(), Some(user.path())).unwrap();
+        assert!(registry.get("mine").is_none());
+        assert_eq!(registry.get("shared").unwrap().name, "Second");
+    }
+}
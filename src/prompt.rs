@@ -0,0 +1,497 @@
+//! Placeholder interpolation for preset user prompts.
+//!
+//! Preset prompts reference values such as `{file_count}` or `{languages}`.
+//! [`PromptTemplate`] parses a prompt once, rejecting unknown placeholders,
+//! and renders it for every chunk from [`PromptStats`] computed over the chunk
+//! and over the whole run.
+
+use crate::{
+    error::{Error, Result},
+    file::FileData,
+    splitter::Chunk,
+    template::detect_language,
+};
+use std::collections::{BTreeMap, HashSet};
+
+/// File names recognised as dependency manifests for `{dependencies}`.
+const MANIFEST_FILES: &[&str] = &[
+    "Cargo.toml",
+    "package.json",
+    "pyproject.toml",
+    "requirements.txt",
+    "Pipfile",
+    "setup.py",
+    "go.mod",
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    "Gemfile",
+    "composer.json",
+];
+
+/// A value that can be substituted into a preset prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Placeholder {
+    FileCount,
+    TotalLines,
+    TotalTokens,
+    Languages,
+    DirectoryStructure,
+    Dependencies,
+    ChunkFileCount,
+    ChunkTotalLines,
+    ChunkTotalTokens,
+    ChunkLanguages,
+    ChunkIndex,
+    TotalChunks,
+    CodeContent,
+}
+
+impl Placeholder {
+    const ALL: &'static [Self] = &[
+        Self::FileCount,
+        Self::TotalLines,
+        Self::TotalTokens,
+        Self::Languages,
+        Self::DirectoryStructure,
+        Self::Dependencies,
+        Self::ChunkFileCount,
+        Self::ChunkTotalLines,
+        Self::ChunkTotalTokens,
+        Self::ChunkLanguages,
+        Self::ChunkIndex,
+        Self::TotalChunks,
+        Self::CodeContent,
+    ];
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::FileCount => "file_count",
+            Self::TotalLines => "total_lines",
+            Self::TotalTokens => "total_tokens",
+            Self::Languages => "languages",
+            Self::DirectoryStructure => "directory_structure",
+            Self::Dependencies => "dependencies",
+            Self::ChunkFileCount => "chunk_file_count",
+            Self::ChunkTotalLines => "chunk_total_lines",
+            Self::ChunkTotalTokens => "chunk_total_tokens",
+            Self::ChunkLanguages => "chunk_languages",
+            Self::ChunkIndex => "chunk_index",
+            Self::TotalChunks => "total_chunks",
+            Self::CodeContent => "code_content",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.iter().copied().find(|p| p.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(Placeholder),
+}
+
+/// A parsed preset prompt with validated placeholders.
+///
+/// Placeholders are written as `{name}`; `{{` and `}}` produce literal braces.
+/// Braces that do not enclose an identifier (e.g. JSON examples) are kept as is.
+#[derive(Debug, Clone)]
+pub(crate) struct PromptTemplate {
+    segments: Vec<Segment>,
+}
+
+impl PromptTemplate {
+    /// Parses a prompt template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error naming the first placeholder that is not supported.
+    pub(crate) fn parse(source_name: &str, template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+
+        while let Some(pos) = rest.find(['{', '}']) {
+            text.push_str(&rest[..pos]);
+            let tail = &rest[pos..];
+
+            if tail.starts_with("{{") || tail.starts_with("}}") {
+                text.push_str(&tail[..1]);
+                rest = &tail[2..];
+                continue;
+            }
+
+            let name = Some(tail)
+                .filter(|tail| tail.starts_with('{'))
+                .and_then(|tail| tail[1..].find('}'))
+                .map(|end| &tail[1..=end])
+                .filter(|name| is_identifier(name));
+
+            let Some(name) = name else {
+                text.push_str(&tail[..1]);
+                rest = &tail[1..];
+                continue;
+            };
+
+            let placeholder = Placeholder::from_name(name).ok_or_else(|| {
+                Error::template_validation(
+                    source_name,
+                    format!(
+                        "unknown placeholder '{{{name}}}' in user prompt (available: {})",
+                        available_placeholders()
+                    ),
+                )
+            })?;
+
+            if !text.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut text)));
+            }
+            segments.push(Segment::Placeholder(placeholder));
+            rest = &tail[name.len() + 2..];
+        }
+
+        text.push_str(rest);
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self { segments })
+    }
+
+    /// Renders the prompt with the given values.
+    pub(crate) fn render(&self, values: &PromptValues<'_>) -> String {
+        let mut output = String::new();
+
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Placeholder(placeholder) => {
+                    output.push_str(&values.resolve(*placeholder));
+                }
+            }
+        }
+
+        output
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+fn available_placeholders() -> String {
+    Placeholder::ALL
+        .iter()
+        .map(|p| format!("{{{}}}", p.name()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Aggregate statistics over a set of files, used to fill prompt placeholders.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct PromptStats {
+    /// Number of distinct source files (split parts count once)
+    pub file_count: usize,
+    /// Total number of lines across text files
+    pub total_lines: usize,
+    /// Total token count
+    pub total_tokens: usize,
+    /// File count per language, most common first
+    pub languages: Vec<(&'static str, usize)>,
+    /// Distinct relative paths, sorted
+    pub paths: Vec<String>,
+}
+
+impl PromptStats {
+    /// Computes statistics for a single chunk.
+    pub(crate) fn for_chunk(chunk: &Chunk) -> Self {
+        Self::from_files(&chunk.files)
+    }
+
+    /// Computes statistics for the whole run.
+    pub(crate) fn for_run(chunks: &[Chunk]) -> Self {
+        Self::from_files(chunks.iter().flat_map(|chunk| &chunk.files))
+    }
+
+    fn from_files<'a>(files: impl IntoIterator<Item = &'a FileData>) -> Self {
+        let mut stats = Self::default();
+        let mut seen = HashSet::new();
+        let mut languages: BTreeMap<&'static str, usize> = BTreeMap::new();
+
+        for file in files {
+            stats.total_lines += file.line_count().unwrap_or(0);
+            stats.total_tokens += file.token_count;
+
+            // Parts of a split file share the source path
+            if !seen.insert(&file.absolute_path) {
+                continue;
+            }
+
+            let path = file.relative_path.as_str();
+            stats.file_count += 1;
+            stats.paths.push(path.to_string());
+
+            if !file.is_binary() {
+                let language = match detect_language(path) {
+                    "" => "other",
+                    language => language,
+                };
+                *languages.entry(language).or_default() += 1;
+            }
+        }
+
+        stats.paths.sort();
+        stats.languages = languages.into_iter().collect();
+        stats
+            .languages
+            .sort_by(|(a_name, a_count), (b_name, b_count)| {
+                b_count.cmp(a_count).then_with(|| a_name.cmp(b_name))
+            });
+
+        stats
+    }
+
+    /// Formats the language breakdown, e.g. `rust (3 files), toml (1 file)`.
+    pub(crate) fn languages_summary(&self) -> String {
+        if self.languages.is_empty() {
+            return "none".to_string();
+        }
+
+        self.languages
+            .iter()
+            .map(|(language, count)| format!("{language} ({})", plural(*count, "file")))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Summarizes the top-level layout, e.g. `src/ (4 files), tests/ (2 files), 1 root file`.
+    pub(crate) fn structure_summary(&self) -> String {
+        let mut directories: BTreeMap<&str, usize> = BTreeMap::new();
+        let mut root_files = 0;
+
+        for path in &self.paths {
+            match path.split_once('/') {
+                Some((dir, _)) => *directories.entry(dir).or_default() += 1,
+                None => root_files += 1,
+            }
+        }
+
+        let mut parts: Vec<String> = directories
+            .into_iter()
+            .map(|(dir, count)| format!("{dir}/ ({})", plural(count, "file")))
+            .collect();
+        if root_files > 0 {
+            parts.push(plural(root_files, "root file"));
+        }
+
+        if parts.is_empty() {
+            "empty".to_string()
+        } else {
+            parts.join(", ")
+        }
+    }
+
+    /// Lists dependency manifests found among the files.
+    pub(crate) fn dependencies_summary(&self) -> String {
+        let manifests: Vec<&str> = self
+            .paths
+            .iter()
+            .map(String::as_str)
+            .filter(|path| {
+                let name = path.rsplit('/').next().unwrap_or(path);
+                MANIFEST_FILES.contains(&name)
+            })
+            .collect();
+
+        if manifests.is_empty() {
+            "no dependency manifests found".to_string()
+        } else {
+            format!("declared in {}", manifests.join(", "))
+        }
+    }
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("{count} {noun}")
+    } else {
+        format!("{count} {noun}s")
+    }
+}
+
+/// Values available when rendering a prompt for one chunk.
+pub(crate) struct PromptValues<'a> {
+    /// Statistics for the whole run
+    pub run: &'a PromptStats,
+    /// Statistics for the chunk being rendered
+    pub chunk: &'a PromptStats,
+    /// 1-based chunk index
+    pub chunk_index: usize,
+    /// Total number of chunks
+    pub total_chunks: usize,
+    /// Text substituted for `{code_content}`
+    pub code_content: &'a str,
+}
+
+impl PromptValues<'_> {
+    fn resolve(&self, placeholder: Placeholder) -> String {
+        match placeholder {
+            Placeholder::FileCount => self.run.file_count.to_string(),
+            Placeholder::TotalLines => self.run.total_lines.to_string(),
+            Placeholder::TotalTokens => self.run.total_tokens.to_string(),
+            Placeholder::Languages => self.run.languages_summary(),
+            Placeholder::DirectoryStructure => self.run.structure_summary(),
+            Placeholder::Dependencies => self.run.dependencies_summary(),
+            Placeholder::ChunkFileCount => self.chunk.file_count.to_string(),
+            Placeholder::ChunkTotalLines => self.chunk.total_lines.to_string(),
+            Placeholder::ChunkTotalTokens => self.chunk.total_tokens.to_string(),
+            Placeholder::ChunkLanguages => self.chunk.languages_summary(),
+            Placeholder::ChunkIndex => self.chunk_index.to_string(),
+            Placeholder::TotalChunks => self.total_chunks.to_string(),
+            Placeholder::CodeContent => self.code_content.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    fn text_file(path: &str, content: &str, tokens: usize) -> FileData {
+        FileData::new_text(
+            PathBuf::from("/project").join(path),
+            path.to_string(),
+            content.to_string(),
+            tokens,
+        )
+    }
+
+    fn sample_chunks() -> Vec<Chunk> {
+        vec![
+            Chunk::new(
+                0,
+                vec![
+                    text_file("src/main.rs", "fn main() {\n}\n", 5),
+                    text_file("src/lib.rs", "pub mod a;\n", 3),
+                    text_file("Cargo.toml", "[package]\nname = \"x\"\n", 4),
+                ],
+                12,
+            ),
+            Chunk::new(
+                1,
+                vec![
+                    text_file("scripts/run.py", "print(1)\n", 2),
+                    FileData::new_binary(
+                        PathBuf::from("/project/logo.png"),
+                        "logo.png".to_string(),
+                        128,
+                    ),
+                ],
+                130,
+            ),
+        ]
+    }
+
+    fn values<'a>(run: &'a PromptStats, chunk: &'a PromptStats) -> PromptValues<'a> {
+        PromptValues {
+            run,
+            chunk,
+            chunk_index: 2,
+            total_chunks: 2,
+            code_content: "<code>",
+        }
+    }
+
+    #[test]
+    fn test_run_stats() {
+        let stats = PromptStats::for_run(&sample_chunks());
+
+        assert_eq!(stats.file_count, 5);
+        assert_eq!(stats.total_lines, 6);
+        assert_eq!(stats.total_tokens, 14);
+        assert_eq!(stats.languages_summary(), "rust (2 files), python (1 file), toml (1 file)");
+        assert_eq!(stats.structure_summary(), "scripts/ (1 file), src/ (2 files), 2 root files");
+        assert_eq!(stats.dependencies_summary(), "declared in Cargo.toml");
+    }
+
+    #[test]
+    fn test_split_parts_count_once() {
+        let chunks = vec![
+            Chunk::new(0, vec![text_file("big.rs", "a\nb\n", 2)], 2),
+            Chunk::new(1, vec![text_file("big.rs", "c\n", 1)], 1),
+        ];
+        // Parts share the absolute path of the source file
+        let chunks: Vec<Chunk> = chunks
+            .into_iter()
+            .map(|mut chunk| {
+                chunk.files[0].absolute_path = PathBuf::from("/project/big.rs");
+                chunk
+            })
+            .collect();
+
+        let stats = PromptStats::for_run(&chunks);
+        assert_eq!(stats.file_count, 1);
+        assert_eq!(stats.total_lines, 3);
+        assert_eq!(stats.paths, vec!["big.rs".to_string()]);
+    }
+
+    #[test]
+    fn test_render_run_and_chunk_values() {
+        let chunks = sample_chunks();
+        let run = PromptStats::for_run(&chunks);
+        let chunk = PromptStats::for_chunk(&chunks[1]);
+
+        let template = PromptTemplate::parse(
+            "test",
+            "Files: {file_count} ({chunk_file_count} here), {chunk_languages}; \
+             part {chunk_index}/{total_chunks}\n{code_content}",
+        )
+        .unwrap();
+
+        assert_eq!(
+            template.render(&values(&run, &chunk)),
+            "Files: 5 (2 here), python (1 file); part 2/2\n<code>"
+        );
+    }
+
+    #[test]
+    fn test_unknown_placeholder_is_rejected() {
+        let err = PromptTemplate::parse("custom", "Stats: {file_cuont}").unwrap_err();
+
+        let message = err.to_string();
+        assert!(message.contains("{file_cuont}"));
+        assert!(message.contains("{file_count}"));
+    }
+
+    #[test]
+    fn test_literal_braces() {
+        let run = PromptStats::default();
+        let template =
+            PromptTemplate::parse("test", r#"{{file_count}} {"key": 1} {} { x } } {languages}"#)
+                .unwrap();
+
+        assert_eq!(
+            template.render(&values(&run, &run)),
+            r#"{file_count} {"key": 1} {} { x } } none"#
+        );
+    }
+
+    #[test]
+    fn test_builtin_presets_parse() {
+        for kind in crate::preset::PresetKind::all() {
+            let preset = crate::preset::LLMPreset::for_kind(*kind);
+            assert!(
+                PromptTemplate::parse(&preset.id, &preset.user_prompt_template).is_ok(),
+                "preset {} has unknown placeholders",
+                preset.id
+            );
+        }
+    }
+}
@@ -0,0 +1,263 @@
+use crate::{
+    config::{Config, OutputFormat},
+    error::Result,
+    sourcemap::ChunkMap,
+    splitter::Chunk,
+    template::{RunContext, TemplateEngine},
+};
+use serde::Serialize;
+use std::path::PathBuf;
+use std::time::Duration;
+use tracing::debug;
+
+/// A chunk rendered through the output template.
+#[derive(Debug, Clone, Serialize)]
+pub struct RenderedChunk {
+    /// Chunk index (1-based, as in `summary.json`)
+    pub index: usize,
+
+    /// Output filename the chunk is written to
+    pub filename: String,
+
+    /// Rendered prompt text
+    pub content: String,
+
+    /// Relative paths of the files in the chunk, in output order
+    pub files: Vec<String>,
+
+    /// Token count of the files in the chunk
+    pub total_tokens: usize,
+
+    /// Where each file's content sits in `content`
+    pub source_map: ChunkMap,
+}
+
+/// Summary of a run, as written to `summary.json`.
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    /// Total number of chunks
+    pub total_chunks: usize,
+
+    /// Total number of files across all chunks
+    pub total_files: usize,
+
+    /// Total token count across all chunks
+    pub total_tokens: usize,
+
+    /// Execution duration in seconds
+    pub duration_secs: f64,
+
+    /// Output directory path
+    pub output_directory: String,
+
+    /// Output format used
+    pub format: String,
+
+    /// Individual chunk summaries
+    pub chunks: Vec<ChunkSummary>,
+
+    /// Generation timestamp, as shown in every chunk
+    pub generated_at: String,
+}
+
+/// Summary of a single chunk.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChunkSummary {
+    /// Chunk index (1-based for user display)
+    pub index: usize,
+
+    /// Number of files in chunk
+    pub files: usize,
+
+    /// Token count in chunk
+    pub tokens: usize,
+
+    /// Output filename
+    pub filename: String,
+}
+
+/// Renders chunks through the output template.
+pub(crate) struct Renderer {
+    template_engine: TemplateEngine,
+    output_dir: PathBuf,
+    output_pattern: String,
+    format: OutputFormat,
+    custom_extension: Option<String>,
+}
+
+impl Renderer {
+    /// Creates a new renderer from configuration.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if template engine initialization fails.
+    pub(crate) fn new(config: &Config) -> Result<Self> {
+        Ok(Self {
+            template_engine: TemplateEngine::new(config)?,
+            output_dir: config.output_dir.clone(),
+            output_pattern: config.output_pattern.clone(),
+            format: config.format,
+            custom_extension: config.custom_extension.clone(),
+        })
+    }
+
+    /// Renders all chunks, passing each one to `emit` in order.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if template rendering fails or `emit` fails.
+    pub(crate) fn render_each(
+        &self,
+        chunks: &[Chunk],
+        run: &RunContext,
+        mut emit: impl FnMut(RenderedChunk) -> Result<()>,
+    ) -> Result<()> {
+        for chunk in chunks {
+            emit(self.render_chunk(chunk, chunks.len(), run)?)?;
+        }
+        Ok(())
+    }
+
+    /// Renders all chunks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if template rendering fails.
+    pub(crate) fn render(&self, chunks: &[Chunk], run: &RunContext) -> Result<Vec<RenderedChunk>> {
+        let mut rendered = Vec::with_capacity(chunks.len());
+        self.render_each(chunks, run, |chunk| {
+            rendered.push(chunk);
+            Ok(())
+        })?;
+        Ok(rendered)
+    }
+
+    /// Summarizes rendered chunks for `summary.json`.
+    pub(crate) fn summary(
+        &self,
+        chunks: &[RenderedChunk],
+        run: &RunContext,
+        duration: Duration,
+    ) -> RunSummary {
+        RunSummary {
+            total_chunks: chunks.len(),
+            total_files: chunks.iter().map(|c| c.files.len()).sum(),
+            total_tokens: chunks.iter().map(|c| c.total_tokens).sum(),
+            duration_secs: duration.as_secs_f64(),
+            output_directory: self.output_dir.display().to_string(),
+            format: format!("{:?}", self.format),
+            chunks: chunks
+                .iter()
+                .map(|c| ChunkSummary {
+                    index: c.index,
+                    files: c.files.len(),
+                    tokens: c.total_tokens,
+                    filename: c.filename.clone(),
+                })
+                .collect(),
+            generated_at: run.generated_at.clone(),
+        }
+    }
+
+    /// Renders a single chunk and locates its files in the output.
+    fn render_chunk(
+        &self,
+        chunk: &Chunk,
+        total_chunks: usize,
+        run: &RunContext,
+    ) -> Result<RenderedChunk> {
+        let content = self.template_engine.render(chunk, total_chunks, run)?;
+        let filename = self.filename(chunk.index);
+        let source_map = ChunkMap::locate(filename.clone(), chunk, &content, |file| {
+            self.template_engine.file_content(file)
+        });
+
+        debug!(
+            "Rendered chunk {}/{} ({} files, {} tokens)",
+            chunk.index + 1,
+            total_chunks,
+            chunk.files.len(),
+            chunk.total_tokens
+        );
+
+        Ok(RenderedChunk {
+            index: chunk.index + 1,
+            filename,
+            content,
+            files: chunk.files.iter().map(|f| f.relative_path.clone()).collect(),
+            total_tokens: chunk.total_tokens,
+            source_map,
+        })
+    }
+
+    /// Generates the output filename for a chunk.
+    fn filename(&self, index: usize) -> String {
+        // Determine extension based on format
+        let extension = match self.format {
+            OutputFormat::Custom => self
+                .custom_extension
+                .as_deref()
+                .unwrap_or("txt"),
+            _ => self.format.extension(),
+        };
+
+        self.output_pattern
+            .replace("{index:03}", &format!("{:03}", index + 1))
+            .replace("{index:02}", &format!("{:02}", index + 1))
+            .replace("{index}", &(index + 1).to_string())
+            .replace("{ext}", extension)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::FileData;
+
+    fn create_test_chunk(index: usize) -> Chunk {
+        Chunk::new(
+            index,
+            vec![FileData::new_text(
+                PathBuf::from("test.rs"),
+                "test.rs".to_string(),
+                "fn main() {}".to_string(),
+                100,
+            )],
+            100,
+        )
+    }
+
+    #[test]
+    fn test_renderer_renders_chunks() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let config = Config::builder().root_dir(temp.path()).build().unwrap();
+        let renderer = Renderer::new(&config).unwrap();
+
+        let chunks = vec![create_test_chunk(0), create_test_chunk(1)];
+        let run = RunContext::new(&chunks);
+        let prompts = renderer.render(&chunks, &run).unwrap();
+
+        assert_eq!(prompts.len(), 2);
+        assert_eq!(prompts[1].index, 2);
+        assert_eq!(prompts[1].filename, "prompt_002.md");
+        assert_eq!(prompts[1].files, ["test.rs"]);
+        assert!(prompts[1].content.contains("fn main() {}"));
+        assert_eq!(prompts[1].source_map.files.len(), 1);
+
+        let summary = renderer.summary(&prompts, &run, Duration::from_secs(1));
+        assert_eq!(summary.total_chunks, 2);
+        assert_eq!(summary.total_tokens, 200);
+        assert_eq!(summary.chunks[0].filename, "prompt_001.md");
+        assert_eq!(summary.generated_at, run.generated_at);
+    }
+
+    #[test]
+    fn test_filename() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let config = Config::builder().root_dir(temp.path()).build().unwrap();
+        let renderer = Renderer::new(&config).unwrap();
+
+        assert_eq!(renderer.filename(0), "prompt_001.md");
+        assert_eq!(renderer.filename(9), "prompt_010.md");
+    }
+}
@@ -1,12 +1,16 @@
+use crate::cache::{content_hash, FileCache};
+use crate::diff::{unified_diff, DiffOptions};
 use crate::filter::FileFilter;
+use crate::git::{BaseRevision, GitSelection};
+use crate::source::{is_scanned, walk_builder, FileSource, SourceEntry};
 use crate::{
     config::Config,
     error::{Error, Result},
-    file::{has_binary_extension, is_likely_binary, FileData},
+    file::{has_binary_extension, is_binary_content, is_likely_binary, FileData},
     filter::CodeFilter,
     token::TokenEstimator,
 };
-use ignore::{DirEntry, WalkBuilder, WalkState};
+use ignore::{DirEntry, WalkState};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::{
@@ -43,28 +47,61 @@ pub(crate) struct Scanner {
     tokenizer: Arc<dyn TokenEstimator>,
     code_filter: CodeFilter,
     file_filter: FileFilter,
+    git_selection: Option<GitSelection>,
+    diff: Option<DiffOptions>,
+    cache: Option<Arc<FileCache>>,
+    /// Source read through [`FileSource`] instead of walking `root_dir`
+    source: Option<Arc<dyn FileSource>>,
 }
 
 impl Scanner {
-    /// Creates a new scanner from configuration.
-    pub(crate) fn new(config: &Config) -> Self {
+    /// Creates a new scanner from configuration and a shared tokenizer.
+    pub(crate) fn new(config: &Config, tokenizer: Arc<dyn TokenEstimator>) -> Self {
+        let local_root = config
+            .source
+            .as_ref()
+            .map_or(Some(config.root_dir.as_path()), |source| source.local_root());
+
         Self {
-            root_dir: config.root_dir.clone(),
+            root_dir: local_root.unwrap_or(&config.root_dir).to_path_buf(),
             include_binary: config.include_binary_files,
-            tokenizer: config.tokenizer.create(),
+            tokenizer,
             code_filter: CodeFilter::new(config.filter_config.clone()),
             file_filter: FileFilter::new(config.file_filter_config.clone()),
+            git_selection: config.git_selection.clone(),
+            diff: DiffOptions::from_config(config),
+            cache: config.cache.then(|| Arc::new(FileCache::load(config))),
+            source: config.source.clone().filter(|_| local_root.is_none()),
         }
     }
 
+    /// Returns the file cache when incremental runs are enabled.
+    pub(crate) fn cache(&self) -> Option<&FileCache> {
+        self.cache.as_deref()
+    }
+
     /// Scans the root directory and returns all processable files.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - No files are found
+    /// - The git selection cannot be resolved
     /// - Critical scanning errors occur
     pub(crate) fn scan(&self) -> Result<Vec<FileData>> {
+        if let Some(source) = &self.source {
+            return self.scan_source(source.as_ref());
+        }
+
+        // Paths relative to the root; None scans everything. Diff mode
+        // without a selection only needs the files changed since its base.
+        let selection = self
+            .git_selection
+            .clone()
+            .or_else(|| self.diff.as_ref().map(|diff| GitSelection::Since(diff.base.clone())))
+            .map(|selection| selection.resolve(&self.root_dir).map(Arc::new))
+            .transpose()?;
+
         let files = Arc::new(Mutex::new(Vec::new()));
         let errors = Arc::new(Mutex::new(Vec::new()));
         let stats = Arc::new(Mutex::new(ScanStats::default()));
@@ -77,12 +114,7 @@ impl Scanner {
         let scan_timeout = Duration::from_secs(30); // 30 секунд
         let scan_start = Instant::now();
 
-        let walker = WalkBuilder::new(&self.root_dir)
-            .git_ignore(true)
-            .git_global(true)
-            .git_exclude(true)
-            .hidden(true)
-            .follow_links(false)
+        let walker = walk_builder(&self.root_dir)
             .skip_stdout(true)
             .threads(num_cpus::get())
             .build_parallel();
@@ -96,6 +128,8 @@ impl Scanner {
             let code_filter = self.code_filter.clone();
             let include_binary = self.include_binary;
             let file_filter = file_filter.clone();
+            let selection = selection.clone();
+            let cache = self.cache.clone();
             let scan_start = scan_start;
             let scan_timeout = scan_timeout;
             Box::new(move |result| {
@@ -105,10 +139,16 @@ impl Scanner {
                 }
                 match result {
                     Ok(entry) if entry.file_type().map_or(false, |ft| ft.is_file()) => {
-                        if entry.file_name() == "Cargo.lock" {
-                            return WalkState::Continue;
+                        if let Some(ref selection) = selection {
+                            let selected = entry
+                                .path()
+                                .strip_prefix(&root)
+                                .is_ok_and(|relative| selection.contains(relative));
+                            if !selected {
+                                return WalkState::Continue;
+                            }
                         }
-                        if !file_filter.should_process(entry.path()) {
+                        if !is_scanned(entry.path(), &file_filter) {
                             return WalkState::Continue; // Пропускаем файл
                         }
 
@@ -121,6 +161,7 @@ impl Scanner {
                                 &root,
                                 tokenizer.as_ref(),
                                 &code_filter,
+                                cache.as_deref(),
                                 include_binary,
                                 &mut *stats_guard,
                             )
@@ -184,6 +225,10 @@ impl Scanner {
             );
         }
 
+        if let Some(ref diff) = self.diff {
+            files = self.apply_diff(files, diff)?;
+        }
+
         if files.is_empty() {
             return Err(Error::no_files(&self.root_dir));
         }
@@ -195,12 +240,188 @@ impl Scanner {
         Ok(files)
     }
 
+    /// Reads all files from a [`FileSource`] that is not a directory walk.
+    ///
+    /// Files go through the same file filters, binary detection and code
+    /// filters as files on disk, but are not cached.
+    fn scan_source(&self, source: &dyn FileSource) -> Result<Vec<FileData>> {
+        if self.git_selection.is_some() || self.diff.is_some() {
+            return Err(Error::config(
+                "Git selections and diff mode need files from a directory on disk",
+            ));
+        }
+
+        let mut stats = ScanStats::default();
+        let mut files = Vec::new();
+
+        for entry in source.files()? {
+            if !is_scanned(&entry.path, &self.file_filter) {
+                continue;
+            }
+
+            stats.total_files += 1;
+            match self.process_source_entry(source, entry, &mut stats) {
+                Ok(Some(file_data)) => files.push(file_data),
+                Ok(None) => stats.skipped_files += 1,
+                Err(e) => {
+                    warn!("Failed to process file: {}", e);
+                    stats.errors += 1;
+                }
+            }
+        }
+
+        debug!(
+            "Source scan complete: {} total, {} text, {} binary, {} skipped, {} errors",
+            stats.total_files,
+            stats.text_files,
+            stats.binary_files,
+            stats.skipped_files,
+            stats.errors
+        );
+
+        if files.is_empty() {
+            return Err(Error::no_files(&self.root_dir));
+        }
+
+        // Sort for deterministic ordering
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+        Ok(files)
+    }
+
+    /// Processes a single file from a [`FileSource`].
+    fn process_source_entry(
+        &self,
+        source: &dyn FileSource,
+        entry: SourceEntry,
+        stats: &mut ScanStats,
+    ) -> Result<Option<FileData>> {
+        trace!("Processing source file: {}", entry.relative_path);
+
+        if self.code_filter.is_test_file(&entry.path) {
+            debug!("Skipping test file: {}", entry.relative_path);
+            return Ok(None);
+        }
+
+        if has_binary_extension(&entry.path) {
+            stats.binary_files += 1;
+            return Ok(self
+                .include_binary
+                .then(|| FileData::new_binary(entry.path, entry.relative_path, entry.size)));
+        }
+
+        let content = source.read(&entry)?;
+        if !crate::file::has_text_extension(&entry.path) && is_binary_content(&content) {
+            stats.binary_files += 1;
+            return Ok(self
+                .include_binary
+                .then(|| FileData::new_binary(entry.path, entry.relative_path, entry.size)));
+        }
+
+        let content = String::from_utf8(content).map_err(|_| Error::invalid_utf8(&entry.path))?;
+        stats.text_files += 1;
+
+        let (filtered_content, source_lines) =
+            self.code_filter.filter_with_line_map(&content, &entry.path);
+        let token_count = self.tokenizer.estimate(&filtered_content);
+
+        Ok(Some(FileData {
+            source_lines: Some(source_lines),
+            ..FileData::new_text(entry.path, entry.relative_path, filtered_content, token_count)
+        }))
+    }
+
+    /// Replaces text contents with unified diffs against the base revision.
+    ///
+    /// The base version goes through the same code filter, so the diff shows
+    /// only changes to code that would be rendered. Unchanged files are dropped.
+    /// Unless the scan is restricted to staged, uncommitted or listed files,
+    /// text files deleted since the base are added as removal-only diffs.
+    fn apply_diff(&self, files: Vec<FileData>, options: &DiffOptions) -> Result<Vec<FileData>> {
+        let base = BaseRevision::resolve(&self.root_dir, &options.base)?;
+        let mut diffed = Vec::with_capacity(files.len());
+
+        for file in files {
+            let Some(new) = file.content_str() else {
+                diffed.push(file);
+                continue;
+            };
+
+            let old = base
+                .read(Path::new(&file.relative_path))?
+                .map(|old| self.code_filter.filter(&old, &file.absolute_path));
+            let Some((patch, summary)) =
+                unified_diff(&options.base, old.as_deref(), Some(new), &file.relative_path, options.context)
+            else {
+                trace!("No filtered changes in {}", file.relative_path);
+                continue;
+            };
+
+            let token_count = self.tokenizer.estimate(&patch);
+            diffed.push(
+                FileData {
+                    diff: Some(summary),
+                    ..FileData::new_text(file.absolute_path, file.relative_path, patch, token_count)
+                },
+            );
+        }
+
+        if matches!(self.git_selection, None | Some(GitSelection::Since(_))) {
+            for relative in base.deleted()? {
+                if let Some(file) = self.deleted_file(&base, &relative, options)? {
+                    diffed.push(file);
+                }
+            }
+        }
+
+        debug!("Diff mode kept {} changed files", diffed.len());
+        Ok(diffed)
+    }
+
+    /// Renders a file deleted since the base as a removal-only diff, or
+    /// returns `None` if a scan of the base would have skipped it.
+    fn deleted_file(&self, base: &BaseRevision, relative: &Path, options: &DiffOptions) -> Result<Option<FileData>> {
+        let absolute_path = self.root_dir.join(relative);
+        let hidden = relative
+            .components()
+            .any(|component| component.as_os_str().to_string_lossy().starts_with('.'));
+        if hidden
+            || has_binary_extension(relative)
+            || !is_scanned(&absolute_path, &self.file_filter)
+            || self.code_filter.is_test_file(relative)
+        {
+            return Ok(None);
+        }
+
+        let Some(old) = base.read(relative)? else {
+            return Ok(None);
+        };
+        if is_binary_content(old.as_bytes()) {
+            return Ok(None);
+        }
+
+        let old = self.code_filter.filter(&old, relative);
+        let relative_path = relative.to_string_lossy().replace('\\', "/");
+        let Some((patch, summary)) =
+            unified_diff(&options.base, Some(&old), None, &relative_path, options.context)
+        else {
+            return Ok(None);
+        };
+
+        trace!("Deleted since {}: {}", options.base, relative_path);
+        let token_count = self.tokenizer.estimate(&patch);
+        Ok(Some(FileData {
+            diff: Some(summary),
+            ..FileData::new_text(absolute_path, relative_path, patch, token_count)
+        }))
+    }
+
     /// Processes a single directory entry.
     fn process_entry(
         entry: &DirEntry,
         root: &Path,
         tokenizer: &dyn TokenEstimator,
         code_filter: &CodeFilter,
+        cache: Option<&FileCache>,
         include_binary: bool,
         stats: &mut ScanStats,
     ) -> Result<Option<FileData>> {
@@ -214,6 +435,11 @@ impl Scanner {
             .to_string_lossy()
             .to_string();
 
+        if code_filter.is_test_file(path) {
+            debug!("Skipping test file: {}", relative_path);
+            return Ok(None);
+        }
+
         // Quick check for known binary extensions
         if has_binary_extension(path) {
             stats.binary_files += 1;
@@ -243,7 +469,7 @@ impl Scanner {
         }
 
         // Process as text file
-        Self::create_text_file_data(path, relative_path, tokenizer, code_filter, stats)
+        Self::create_text_file_data(path, relative_path, tokenizer, code_filter, cache, stats)
     }
 
     /// Creates file data for a binary file.
@@ -273,7 +499,23 @@ impl Scanner {
         let reader = BufReader::with_capacity(CHUNK_SIZE, file);
 
         let mut filtered_content = String::with_capacity(CHUNK_SIZE);
+        let mut source_lines = Vec::new();
         let mut lines_buffer = Vec::with_capacity(1000);
+        let mut lines_read = 0;
+
+        // Appends a filtered batch, offsetting its line numbers by the lines
+        // already read
+        let mut flush = |batch: &[String], lines_read: usize| {
+            let (filtered, line_map) = code_filter.filter_with_line_map(&batch.join("\n"), path);
+            if line_map.is_empty() {
+                return;
+            }
+            if !filtered_content.is_empty() {
+                filtered_content.push('\n');
+            }
+            filtered_content.push_str(&filtered);
+            source_lines.extend(line_map.into_iter().map(|line| line + lines_read));
+        };
 
         // Читаем файл построчно
         for line in reader.lines() {
@@ -289,43 +531,47 @@ impl Scanner {
 
             // Обрабатываем батчами для эффективности
             if lines_buffer.len() >= 1000 {
-                let batch = lines_buffer.join("\n");
-                let filtered = code_filter.filter(&batch, path);
-                filtered_content.push_str(&filtered);
-                filtered_content.push('\n');
+                flush(&lines_buffer, lines_read);
+                lines_read += lines_buffer.len();
                 lines_buffer.clear();
             }
         }
 
         // Обработка оставшихся строк
         if !lines_buffer.is_empty() {
-            let batch = lines_buffer.join("\n");
-            let filtered = code_filter.filter(&batch, path);
-            filtered_content.push_str(&filtered);
+            flush(&lines_buffer, lines_read);
         }
 
         let token_count = tokenizer.estimate(&filtered_content);
 
-        Ok(Some(FileData::new_text(
-            path.to_path_buf(),
-            relative_path,
-            filtered_content,
-            token_count,
-        )))
+        Ok(Some(FileData {
+            source_lines: Some(source_lines),
+            ..FileData::new_text(path.to_path_buf(), relative_path, filtered_content, token_count)
+        }))
     }
 
     /// Умный выбор между обычной и потоковой обработкой
+    ///
+    /// With a cache, unchanged files are taken from it instead. Files above
+    /// the streaming threshold are never cached.
     fn create_text_file_data(
         path: &Path,
         relative_path: String,
         tokenizer: &dyn TokenEstimator,
         code_filter: &CodeFilter,
+        cache: Option<&FileCache>,
         stats: &mut ScanStats,
     ) -> Result<Option<FileData>> {
         const STREAMING_THRESHOLD: u64 = 10 * 1024 * 1024; // 10MB
 
         let metadata = std::fs::metadata(path).map_err(|e| Error::io(path, e))?;
 
+        if let Some(cached) = cache.and_then(|cache| cache.get_unchanged(&relative_path, path, &metadata)) {
+            trace!("Cache hit: {}", relative_path);
+            stats.text_files += 1;
+            return Ok(Some(cached));
+        }
+
         // Для больших файлов используем потоковую обработку
         if metadata.len() > STREAMING_THRESHOLD {
             trace!("Using streaming mode for large file: {}", relative_path);
@@ -340,17 +586,28 @@ impl Scanner {
                 }
             })?;
 
-            let filtered_content = code_filter.filter(&content, path);
+            stats.text_files += 1;
+
+            let hash = cache.map(|_| content_hash(&content));
+            if let (Some(cache), Some(hash)) = (cache, &hash) {
+                if let Some(cached) = cache.get_same_content(&relative_path, path, &metadata, hash) {
+                    trace!("Cache hit (same content): {}", relative_path);
+                    return Ok(Some(cached));
+                }
+            }
+
+            let (filtered_content, source_lines) = code_filter.filter_with_line_map(&content, path);
             let token_count = tokenizer.estimate(&filtered_content);
 
-            stats.text_files += 1;
+            let file = FileData {
+                source_lines: Some(source_lines),
+                ..FileData::new_text(path.to_path_buf(), relative_path, filtered_content, token_count)
+            };
+            if let (Some(cache), Some(hash)) = (cache, hash) {
+                cache.insert(&file, &metadata, hash);
+            }
 
-            Ok(Some(FileData::new_text(
-                path.to_path_buf(),
-                relative_path,
-                filtered_content,
-                token_count,
-            )))
+            Ok(Some(file))
         }
     }
 }
@@ -358,6 +615,7 @@ impl Scanner {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ContentMode;
     use assert_fs::prelude::*;
 
     fn create_test_config(root: &Path) -> Config {
@@ -375,7 +633,7 @@ mod tests {
         temp.child("file2.rs").write_str("pub fn test() {}").unwrap();
 
         let config = create_test_config(temp.path());
-        let scanner = Scanner::new(&config);
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
         let files = scanner.scan().unwrap();
 
         assert_eq!(files.len(), 2);
@@ -391,7 +649,7 @@ mod tests {
         temp.child("binary.exe").write_binary(&[0u8; 100]).unwrap();
 
         let config = create_test_config(temp.path());
-        let scanner = Scanner::new(&config);
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
         let files = scanner.scan().unwrap();
 
         assert_eq!(files.len(), 1);
@@ -411,7 +669,7 @@ mod tests {
             .build()
             .unwrap();
 
-        let scanner = Scanner::new(&config);
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
         let files = scanner.scan().unwrap();
 
         assert_eq!(files.len(), 2);
@@ -425,7 +683,7 @@ mod tests {
         temp.child("ignored.rs").write_str("fn test() {}").unwrap();
 
         let config = create_test_config(temp.path());
-        let scanner = Scanner::new(&config);
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
         let files = scanner.scan().unwrap();
 
         assert_eq!(files.len(), 2);
@@ -436,7 +694,7 @@ mod tests {
         let temp = assert_fs::TempDir::new().unwrap();
 
         let config = create_test_config(temp.path());
-        let scanner = Scanner::new(&config);
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
         let result = scanner.scan();
 
         assert!(result.is_err());
@@ -450,9 +708,138 @@ mod tests {
         temp.child("tests/test.rs").write_str("#[test]\nfn test() {}").unwrap();
 
         let config = create_test_config(temp.path());
-        let scanner = Scanner::new(&config);
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
         let files = scanner.scan().unwrap();
 
         assert_eq!(files.len(), 3);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_scanner_git_selection() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("src/main.rs").write_str("fn main() {}").unwrap();
+        temp.child("src/lib.rs").write_str("pub fn lib() {}").unwrap();
+        temp.child("vendor/dep.rs").write_str("pub fn dep() {}").unwrap();
+        temp.child("files.txt").write_str("src/lib.rs\nvendor/dep.rs\n").unwrap();
+
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .git_selection(GitSelection::FilesFrom(temp.path().join("files.txt")))
+            .file_filter_config(
+                crate::FileFilterConfig::new().exclude_directories(vec!["**/vendor".to_string()]),
+            )
+            .build()
+            .unwrap();
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
+        let files = scanner.scan().unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "src/lib.rs");
+    }
+
+    #[test]
+    fn test_scanner_diff_mode() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("same.rs").write_str("fn same() {}\n").unwrap();
+        temp.child("changed.rs").write_str("fn changed() {}\n").unwrap();
+        temp.child("removed.rs").write_str("fn removed() {}\n").unwrap();
+        let git = |args: &[&str]| {
+            let output = std::process::Command::new("git")
+                .arg("-C")
+                .arg(temp.path())
+                .args(["-c", "user.name=Test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .output()
+                .unwrap();
+            assert!(output.status.success(), "git {args:?}: {output:?}");
+        };
+        git(&["init", "-q", "-b", "main"]);
+        git(&["add", "."]);
+        git(&["commit", "-q", "-m", "initial"]);
+        // Blank lines vanish after filtering, so this edit produces no diff
+        temp.child("same.rs").write_str("\n\nfn same() {}\n\n").unwrap();
+        temp.child("changed.rs").write_str("fn changed() { 1 }\n").unwrap();
+        temp.child("new.rs").write_str("fn new() {}\n").unwrap();
+        git(&["add", "new.rs"]);
+        std::fs::remove_file(temp.child("removed.rs").path()).unwrap();
+
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .content_mode(ContentMode::Diff)
+            .build()
+            .unwrap();
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
+        let mut files = scanner.scan().unwrap();
+        files.sort_by(|a, b| a.relative_path.cmp(&b.relative_path));
+
+        let paths: Vec<&str> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, ["changed.rs", "new.rs", "removed.rs"]);
+        assert_eq!(
+            files[0].content_str(),
+            Some("--- a/changed.rs\n+++ b/changed.rs\n@@ -1 +1 @@\n-fn changed() {}\n+fn changed() { 1 }")
+        );
+        let diff = files[1].diff.as_ref().unwrap();
+        assert_eq!((diff.base.as_str(), diff.is_new, diff.added), ("HEAD", true, 1));
+        assert_eq!(
+            files[2].content_str(),
+            Some("--- a/removed.rs\n+++ /dev/null\n@@ -1 +0,0 @@\n-fn removed() {}")
+        );
+        let diff = files[2].diff.as_ref().unwrap();
+        assert_eq!((diff.is_deleted, diff.removed), (true, 1));
+    }
+
+    #[test]
+    fn test_scanner_reads_memory_source() {
+        let source = crate::MemorySource::new()
+            .file("src/main.rs", "fn main() {}\n")
+            .file("vendor/dep.rs", "pub fn dep() {}")
+            .file("data.bin", vec![0u8, 159, 146, 150])
+            .file("Cargo.lock", "# lock");
+
+        let config = Config::builder()
+            .source(source)
+            .file_filter_config(
+                crate::FileFilterConfig::new().exclude_directories(vec!["**/vendor".to_string()]),
+            )
+            .build()
+            .unwrap();
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
+        let files = scanner.scan().unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "src/main.rs");
+        assert_eq!(files[0].content_str(), Some("fn main() {}"));
+    }
+
+    #[test]
+    fn test_scanner_skips_test_files() {
+        let source = crate::MemorySource::new()
+            .file("src/app.ts", "export const x = 1;")
+            .file("src/app.test.ts", "test('x', () => {});")
+            .file("src/app.spec.jsx", "it('x', () => {});")
+            .file("main.go", "package main")
+            .file("main_test.go", "package main");
+
+        let config = Config::builder().source(source).build().unwrap();
+        let scanner = Scanner::new(&config, config.tokenizer.create().unwrap());
+        let paths: Vec<_> = scanner
+            .scan()
+            .unwrap()
+            .into_iter()
+            .map(|file| file.relative_path)
+            .collect();
+
+        assert_eq!(paths, ["main.go", "src/app.ts"]);
+    }
+
+    #[test]
+    fn test_scanner_rejects_diff_mode_for_memory_source() {
+        let source = crate::MemorySource::new().file("main.rs", "fn main() {}");
+        let config = Config::builder()
+            .source(source)
+            .content_mode(ContentMode::Diff)
+            .build();
+
+        assert!(config.is_err());
+    }
+}
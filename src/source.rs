@@ -0,0 +1,444 @@
+//! Where the scanner gets its files from.
+//!
+//! A [`FileSource`] lists files and reads their contents. The scanner walks
+//! the root directory by default ([`FilesystemSource`]); other sources feed
+//! it content from memory ([`MemorySource`]), a tar archive ([`TarSource`]),
+//! a git revision ([`GitRevSource`](crate::GitRevSource)) or an explicit
+//! list of paths ([`PathListSource`]).
+//!
+//! # Examples
+//!
+//! ```
+//! use llm_utl::{Config, MemorySource, Pipeline};
+//!
+//! # fn main() -> anyhow::Result<()> {
+//! let source = MemorySource::new()
+//!     .file("src/main.rs", "fn main() {}")
+//!     .file("src/lib.rs", "pub fn lib() {}");
+//!
+//! let config = Config::builder().source(source).build()?;
+//! let output = Pipeline::new(config)?.build()?;
+//! assert_eq!(output.chunks[0].files, ["src/lib.rs", "src/main.rs"]);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::filter::FileFilter;
+use ignore::WalkBuilder;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path, PathBuf};
+use tracing::{debug, warn};
+
+/// A file listed by a [`FileSource`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceEntry {
+    /// Path relative to the source root, with `/` separators
+    pub relative_path: String,
+
+    /// Path used to detect the language and match file filters; the file's
+    /// location on disk when it has one
+    pub path: PathBuf,
+
+    /// Size in bytes
+    pub size: u64,
+}
+
+/// Lists files and reads their contents for the scanner.
+///
+/// Implement this to feed llm-utl content from your own storage. Files go
+/// through the same file filters, binary detection and code filters as
+/// files on disk.
+pub trait FileSource: fmt::Debug + Send + Sync {
+    /// Lists the files to scan.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the files cannot be listed.
+    fn files(&self) -> Result<Vec<SourceEntry>>;
+
+    /// Reads the content of a listed file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read.
+    fn read(&self, entry: &SourceEntry) -> Result<Vec<u8>>;
+
+    /// Returns the directory the files live in when the source is a plain
+    /// directory walk.
+    ///
+    /// The scanner then walks and reads the directory itself, which enables
+    /// parallel scanning, streaming of large files, the cache, git
+    /// selections and diff mode. Other sources return `None`.
+    fn local_root(&self) -> Option<&Path> {
+        None
+    }
+}
+
+/// Files under a directory on disk, honoring `.gitignore` (the default).
+#[derive(Debug, Clone)]
+pub struct FilesystemSource {
+    root: PathBuf,
+}
+
+impl FilesystemSource {
+    /// Creates a source for the files under `root`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+}
+
+impl FileSource for FilesystemSource {
+    fn files(&self) -> Result<Vec<SourceEntry>> {
+        let walker = walk_builder(&self.root).build();
+
+        let mut entries = Vec::new();
+        for entry in walker {
+            let entry = match entry {
+                Ok(entry) => entry,
+                Err(e) => {
+                    warn!("Walk error: {}", e);
+                    continue;
+                }
+            };
+            if !entry.file_type().is_some_and(|ft| ft.is_file()) {
+                continue;
+            }
+            let size = entry.metadata().map_or(0, |metadata| metadata.len());
+            entries.push(SourceEntry {
+                relative_path: relative_to(entry.path(), &self.root),
+                path: entry.into_path(),
+                size,
+            });
+        }
+        Ok(entries)
+    }
+
+    fn read(&self, entry: &SourceEntry) -> Result<Vec<u8>> {
+        fs::read(&entry.path).map_err(|e| Error::io(&entry.path, e))
+    }
+
+    fn local_root(&self) -> Option<&Path> {
+        Some(&self.root)
+    }
+}
+
+/// Files held in memory, keyed by relative path.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    files: BTreeMap<String, Vec<u8>>,
+}
+
+impl MemorySource {
+    /// Creates an empty source.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, replacing any file with the same path.
+    #[must_use]
+    pub fn file(mut self, path: impl Into<String>, content: impl Into<Vec<u8>>) -> Self {
+        self.insert(path, content);
+        self
+    }
+
+    /// Adds a file, replacing any file with the same path.
+    pub fn insert(&mut self, path: impl Into<String>, content: impl Into<Vec<u8>>) {
+        self.files.insert(normalize(&path.into()), content.into());
+    }
+
+    /// Returns the number of files.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.files.len()
+    }
+
+    /// Returns `true` if the source holds no files.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty()
+    }
+}
+
+impl<P: Into<String>, C: Into<Vec<u8>>> FromIterator<(P, C)> for MemorySource {
+    fn from_iter<I: IntoIterator<Item = (P, C)>>(iter: I) -> Self {
+        let mut source = Self::new();
+        for (path, content) in iter {
+            source.insert(path, content);
+        }
+        source
+    }
+}
+
+impl FileSource for MemorySource {
+    fn files(&self) -> Result<Vec<SourceEntry>> {
+        Ok(self
+            .files
+            .iter()
+            .map(|(path, content)| SourceEntry {
+                relative_path: path.clone(),
+                path: PathBuf::from(path),
+                size: content.len() as u64,
+            })
+            .collect())
+    }
+
+    fn read(&self, entry: &SourceEntry) -> Result<Vec<u8>> {
+        self.files.get(&entry.relative_path).cloned().ok_or_else(|| {
+            Error::io(
+                &entry.path,
+                std::io::Error::new(std::io::ErrorKind::NotFound, "not in memory source"),
+            )
+        })
+    }
+}
+
+/// Largest buffer reserved up front for a tar entry.
+const MAX_PREALLOCATION: usize = 1 << 20;
+
+/// Regular files of a tar archive, loaded into memory.
+#[derive(Debug, Clone)]
+pub struct TarSource {
+    files: MemorySource,
+}
+
+impl TarSource {
+    /// Reads an uncompressed tar archive from disk.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive cannot be opened or read.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let file = fs::File::open(path).map_err(|e| Error::io(path, e))?;
+        Self::from_reader(file).map_err(|e| match e {
+            Error::Io { message, .. } => Error::Io {
+                path: path.to_path_buf(),
+                message,
+            },
+            e => e,
+        })
+    }
+
+    /// Reads an uncompressed tar archive from a reader.
+    ///
+    /// Directories, links and other special entries are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the archive is malformed.
+    pub fn from_reader(reader: impl Read) -> Result<Self> {
+        let tar_err = |e| Error::io("<tar archive>", e);
+        let mut archive = tar::Archive::new(reader);
+        let mut files = MemorySource::new();
+
+        for entry in archive.entries().map_err(tar_err)? {
+            let mut entry = entry.map_err(tar_err)?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path().map_err(tar_err)?.to_string_lossy().to_string();
+            // The header size is untrusted, so it only sizes a bounded
+            // initial buffer
+            let capacity = usize::try_from(entry.size()).map_or(0, |size| size.min(MAX_PREALLOCATION));
+            let mut content = Vec::with_capacity(capacity);
+            entry.read_to_end(&mut content).map_err(tar_err)?;
+            files.insert(path, content);
+        }
+
+        debug!("Loaded {} files from tar archive", files.len());
+        Ok(Self { files })
+    }
+}
+
+impl FileSource for TarSource {
+    fn files(&self) -> Result<Vec<SourceEntry>> {
+        self.files.files()
+    }
+
+    fn read(&self, entry: &SourceEntry) -> Result<Vec<u8>> {
+        self.files.read(entry)
+    }
+}
+
+/// An explicit list of files on disk.
+///
+/// Paths are resolved against the root and used as given: `.gitignore`
+/// rules do not apply, but file filters do.
+#[derive(Debug, Clone)]
+pub struct PathListSource {
+    root: PathBuf,
+    paths: Vec<PathBuf>,
+}
+
+impl PathListSource {
+    /// Creates a source for `paths`, relative to `root` unless absolute.
+    pub fn new<I, P>(root: impl Into<PathBuf>, paths: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        Self {
+            root: root.into(),
+            paths: paths.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl FileSource for PathListSource {
+    fn files(&self) -> Result<Vec<SourceEntry>> {
+        self.paths
+            .iter()
+            .map(|path| {
+                let path = self.root.join(path);
+                let metadata = fs::metadata(&path).map_err(|e| Error::io(&path, e))?;
+                Ok(SourceEntry {
+                    relative_path: relative_to(&path, &self.root),
+                    path,
+                    size: metadata.len(),
+                })
+            })
+            .collect()
+    }
+
+    fn read(&self, entry: &SourceEntry) -> Result<Vec<u8>> {
+        fs::read(&entry.path).map_err(|e| Error::io(&entry.path, e))
+    }
+}
+
+/// Returns a walker over the files under `root` with the rules every
+/// directory walk shares: `.gitignore` files are honored, hidden files are
+/// skipped and symlinks are not followed.
+///
+/// The scanner, [`FilesystemSource`] and the watcher all walk with it, so
+/// they see the same files.
+pub(crate) fn walk_builder(root: &Path) -> WalkBuilder {
+    let mut builder = WalkBuilder::new(root);
+    builder
+        .git_ignore(true)
+        .git_global(true)
+        .git_exclude(true)
+        .hidden(true)
+        .follow_links(false);
+    builder
+}
+
+/// Returns `true` if the scanner processes a listed file: `Cargo.lock` is
+/// always left out, other files go through the file filter.
+pub(crate) fn is_scanned(path: &Path, file_filter: &FileFilter) -> bool {
+    path.file_name().is_none_or(|name| name != "Cargo.lock") && file_filter.should_process(path)
+}
+
+/// Returns `path` relative to `root` with `/` separators.
+fn relative_to(path: &Path, root: &Path) -> String {
+    let relative = pathdiff::diff_paths(path, root).unwrap_or_else(|| path.to_path_buf());
+    normalize(&relative.to_string_lossy())
+}
+
+/// Drops `.` components and leading slashes and uses `/` separators.
+fn normalize(path: &str) -> String {
+    Path::new(&path.replace('\\', "/"))
+        .components()
+        .filter_map(|component| match component {
+            Component::Normal(part) => Some(part.to_string_lossy().to_string()),
+            Component::ParentDir => Some("..".to_string()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    #[test]
+    fn test_memory_source_lists_files() {
+        let source: MemorySource = [("./src/b.rs", "fn b() {}"), ("src/a.rs", "fn a() {}")]
+            .into_iter()
+            .collect();
+
+        let files = source.files().unwrap();
+        let paths: Vec<_> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, ["src/a.rs", "src/b.rs"]);
+        assert_eq!(files[0].size, 9);
+        assert_eq!(source.read(&files[1]).unwrap(), b"fn b() {}");
+    }
+
+    #[test]
+    fn test_tar_source_reads_regular_files() {
+        let mut builder = tar::Builder::new(Vec::new());
+        for (path, content) in [("project/src/main.rs", "fn main() {}"), ("project/README.md", "# Hi")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            header.set_cksum();
+            builder.append_data(&mut header, path, content.as_bytes()).unwrap();
+        }
+        let mut dir = tar::Header::new_gnu();
+        dir.set_entry_type(tar::EntryType::Directory);
+        dir.set_size(0);
+        dir.set_cksum();
+        builder.append_data(&mut dir, "project/src/", std::io::empty()).unwrap();
+        let archive = builder.into_inner().unwrap();
+
+        let source = TarSource::from_reader(archive.as_slice()).unwrap();
+        let files = source.files().unwrap();
+
+        let paths: Vec<_> = files.iter().map(|f| f.relative_path.as_str()).collect();
+        assert_eq!(paths, ["project/README.md", "project/src/main.rs"]);
+        assert_eq!(source.read(&files[1]).unwrap(), b"fn main() {}");
+    }
+
+    #[test]
+    fn test_tar_source_rejects_oversized_header() {
+        let mut header = tar::Header::new_gnu();
+        header.set_path("huge.bin").unwrap();
+        header.set_size(8 << 30);
+        header.set_entry_type(tar::EntryType::Regular);
+        header.set_cksum();
+
+        let mut archive = header.as_bytes().to_vec();
+        archive.extend_from_slice(&[0; 512]);
+
+        assert!(TarSource::from_reader(archive.as_slice()).unwrap_err().is_io());
+    }
+
+    #[test]
+    fn test_path_list_source() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("src/main.rs").write_str("fn main() {}").unwrap();
+        temp.child("src/other.rs").write_str("fn other() {}").unwrap();
+
+        let source = PathListSource::new(temp.path(), ["src/main.rs"]);
+        let files = source.files().unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "src/main.rs");
+        assert_eq!(source.read(&files[0]).unwrap(), b"fn main() {}");
+
+        let missing = PathListSource::new(temp.path(), ["src/missing.rs"]);
+        assert!(missing.files().unwrap_err().is_io());
+    }
+
+    #[test]
+    fn test_filesystem_source_respects_gitignore() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child(".git").create_dir_all().unwrap();
+        temp.child(".gitignore").write_str("ignored.rs\n").unwrap();
+        temp.child("main.rs").write_str("fn main() {}").unwrap();
+        temp.child("ignored.rs").write_str("fn ignored() {}").unwrap();
+
+        let source = FilesystemSource::new(temp.path());
+        let files = source.files().unwrap();
+
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].relative_path, "main.rs");
+        assert_eq!(source.local_root(), Some(temp.path()));
+    }
+}
@@ -0,0 +1,321 @@
+//! Maps lines of the generated prompt files back to the scanned sources.
+//!
+//! The writer saves a [`SourceMap`] as `sourcemap.json` next to
+//! `summary.json`. For every file section of every chunk it records where
+//! the file's content sits in the output and which source lines the output
+//! lines came from, after code filtering and splitting.
+//!
+//! # Examples
+//!
+//! ```no_run
+//! // The model said "line 42 of prompt_003.md"
+//! if let Some((path, line)) = llm_utl::resolve("out/prompt_003.md", 42)? {
+//!     println!("{path}:{line}");
+//! }
+//! # Ok::<(), llm_utl::Error>(())
+//! ```
+
+use crate::{
+    error::{Error, Result},
+    file::{FileData, LineRange},
+    splitter::Chunk,
+};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File name of the source map in the output directory.
+pub const SOURCE_MAP_FILE: &str = "sourcemap.json";
+
+/// Where the content of every rendered file sits in the output files.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceMap {
+    /// One entry per output file, in chunk order
+    pub chunks: Vec<ChunkMap>,
+}
+
+/// File sections of one output file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChunkMap {
+    /// Chunk index (1-based, as in `summary.json`)
+    pub index: usize,
+    /// Output file name
+    pub filename: String,
+    /// Rendered files, in output order
+    pub files: Vec<FileSection>,
+}
+
+/// The content of one file (or file part) in an output file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileSection {
+    /// Path of the source file, relative to the scanned directory
+    pub path: String,
+    /// Part number when the file was split
+    pub part: Option<usize>,
+    /// Byte range of the content in the output file (end exclusive)
+    pub output_bytes: ByteRange,
+    /// Lines of the output file holding the content
+    pub output_lines: LineRange,
+    /// Runs of consecutive output lines that come from consecutive source
+    /// lines. Empty for diffs and for content rendered on a single line,
+    /// such as JSON strings.
+    pub source_lines: Vec<LineSegment>,
+}
+
+/// A half-open range of byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ByteRange {
+    /// First byte
+    pub start: usize,
+    /// One past the last byte
+    pub end: usize,
+}
+
+/// `count` output lines starting at `output_start` that map to source
+/// lines starting at `source_start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LineSegment {
+    /// First output line
+    pub output_start: usize,
+    /// Source line of the first output line
+    pub source_start: usize,
+    /// Number of lines
+    pub count: usize,
+}
+
+impl SourceMap {
+    /// Reads the source map written to an output directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `sourcemap.json` cannot be read or parsed.
+    pub fn load(output_dir: impl AsRef<Path>) -> Result<Self> {
+        let path = output_dir.as_ref().join(SOURCE_MAP_FILE);
+        let json = std::fs::read_to_string(&path).map_err(|e| Error::io(&path, e))?;
+        Ok(serde_json::from_str(&json)?)
+    }
+
+    /// Returns the source path and line of a 1-based line of an output file.
+    ///
+    /// Returns `None` for lines outside file contents, such as headers, and
+    /// for lines of diffs.
+    #[must_use]
+    pub fn resolve(&self, filename: &str, line: usize) -> Option<(&str, usize)> {
+        let chunk = self.chunks.iter().find(|chunk| chunk.filename == filename)?;
+        chunk.files.iter().find_map(|file| {
+            file.source_lines
+                .iter()
+                .find(|segment| {
+                    (segment.output_start..segment.output_start + segment.count).contains(&line)
+                })
+                .map(|segment| {
+                    (file.path.as_str(), segment.source_start + (line - segment.output_start))
+                })
+        })
+    }
+}
+
+impl ChunkMap {
+    /// Locates the files of `chunk` in its rendered output.
+    ///
+    /// `content` returns a file's content as it was passed to the template.
+    /// Each file is searched after its path and after the previous file, as
+    /// written verbatim, inside split CDATA sections, XML-escaped or as a
+    /// JSON string. Files that cannot be found are left out.
+    pub(crate) fn locate<'a>(
+        filename: String,
+        chunk: &'a Chunk,
+        rendered: &str,
+        content: impl Fn(&'a FileData) -> Option<std::borrow::Cow<'a, str>>,
+    ) -> Self {
+        let mut files = Vec::with_capacity(chunk.files.len());
+        let mut cursor = 0;
+
+        for file in &chunk.files {
+            let Some(text) = content(file).filter(|text| !text.is_empty()) else {
+                continue;
+            };
+            let after_path = rendered[cursor..]
+                .find(&file.relative_path)
+                .map_or(cursor, |offset| cursor + offset + file.relative_path.len());
+
+            let Some((start, len, multiline)) = find_content(&rendered[after_path..], &text)
+            else {
+                continue;
+            };
+            let start = after_path + start;
+            let end = start + len;
+            cursor = end;
+
+            let first_line = rendered[..start].matches('\n').count() + 1;
+            let last_line = first_line + rendered[start..end].matches('\n').count();
+            let source_lines = if multiline && file.diff.is_none() {
+                segments(file, first_line)
+            } else {
+                Vec::new()
+            };
+
+            files.push(FileSection {
+                path: file.relative_path.clone(),
+                part: file.part.map(|part| part.index),
+                output_bytes: ByteRange { start, end },
+                output_lines: LineRange { start: first_line, end: last_line },
+                source_lines,
+            });
+        }
+
+        Self { index: chunk.index + 1, filename, files }
+    }
+}
+
+/// Reads the source map next to `output_file` and returns the source path
+/// and line of one of its 1-based lines.
+///
+/// Returns `Ok(None)` when the line is not part of a file's content.
+///
+/// # Errors
+///
+/// Returns an error if the output file has no name or the source map in its
+/// directory cannot be read.
+pub fn resolve(output_file: impl AsRef<Path>, line: usize) -> Result<Option<(String, usize)>> {
+    let output_file = output_file.as_ref();
+    let filename = output_file
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| Error::config(format!("Invalid output file: {}", output_file.display())))?;
+    let dir = output_file.parent().unwrap_or_else(|| Path::new(""));
+
+    let map = SourceMap::load(dir)?;
+    Ok(map
+        .resolve(filename, line)
+        .map(|(path, line)| (path.to_string(), line)))
+}
+
+/// Finds `content` in `haystack` in one of the encodings the templates use.
+///
+/// Returns the offset, the length of the match and whether lines of the
+/// content stay separate lines in the output.
+fn find_content(haystack: &str, content: &str) -> Option<(usize, usize, bool)> {
+    let cdata = content.replace("]]>", "]]]]><![CDATA[>");
+    let escaped = content
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;");
+    let json = serde_json::to_string(content).ok()?;
+    let json = &json[1..json.len() - 1];
+
+    [(content, true), (cdata.as_str(), true), (escaped.as_str(), true), (json, false)]
+        .into_iter()
+        .find_map(|(needle, multiline)| {
+            haystack
+                .find(needle)
+                .map(|offset| (offset, needle.len(), multiline))
+        })
+}
+
+/// Groups the content lines of `file`, starting at output line
+/// `first_line`, into runs of consecutive source lines.
+fn segments(file: &FileData, first_line: usize) -> Vec<LineSegment> {
+    let mut segments: Vec<LineSegment> = Vec::new();
+
+    for line in 1..=file.line_count().unwrap_or(0) {
+        let Some(source) = file.source_line(line) else {
+            continue;
+        };
+        let output = first_line + line - 1;
+        match segments.last_mut() {
+            Some(last)
+                if last.output_start + last.count == output
+                    && last.source_start + last.count == source =>
+            {
+                last.count += 1;
+            }
+            _ => segments.push(LineSegment { output_start: output, source_start: source, count: 1 }),
+        }
+    }
+
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::borrow::Cow;
+    use std::path::PathBuf;
+
+    fn chunk() -> Chunk {
+        Chunk::new(
+            2,
+            vec![
+                FileData {
+                    source_lines: Some(vec![1, 2, 5, 6]),
+                    ..FileData::new_text(
+                        PathBuf::from("src/a.rs"),
+                        "src/a.rs".to_string(),
+                        "fn a() {\n}\nfn b() {\n}".to_string(),
+                        8,
+                    )
+                },
+                FileData::new_text(
+                    PathBuf::from("src/b.rs"),
+                    "src/b.rs".to_string(),
+                    "fn a() {\n}".to_string(),
+                    4,
+                ),
+            ],
+            12,
+        )
+    }
+
+    #[test]
+    fn test_locate_markdown() {
+        let chunk = chunk();
+        let rendered = "# Chunk\n\n## src/a.rs\n```rust\nfn a() {\n}\nfn b() {\n}\n```\n\n## src/b.rs\n```rust\nfn a() {\n}\n```\n";
+
+        let map = ChunkMap::locate("prompt_003.md".to_string(), &chunk, rendered, |f| {
+            f.content_str().map(Cow::Borrowed)
+        });
+
+        assert_eq!(map.index, 3);
+        assert_eq!(map.files.len(), 2);
+        let a = &map.files[0];
+        assert_eq!(a.output_lines, LineRange { start: 5, end: 8 });
+        assert_eq!(&rendered[a.output_bytes.start..a.output_bytes.end], "fn a() {\n}\nfn b() {\n}");
+        assert_eq!(
+            a.source_lines,
+            [
+                LineSegment { output_start: 5, source_start: 1, count: 2 },
+                LineSegment { output_start: 7, source_start: 5, count: 2 },
+            ]
+        );
+        // The second file's identical content is found after its own path
+        assert_eq!(map.files[1].output_lines, LineRange { start: 13, end: 14 });
+
+        let source_map = SourceMap { chunks: vec![map] };
+        assert_eq!(source_map.resolve("prompt_003.md", 7), Some(("src/a.rs", 5)));
+        assert_eq!(source_map.resolve("prompt_003.md", 14), Some(("src/b.rs", 2)));
+        assert_eq!(source_map.resolve("prompt_003.md", 1), None);
+        assert_eq!(source_map.resolve("prompt_001.md", 7), None);
+    }
+
+    #[test]
+    fn test_locate_json_has_no_line_segments() {
+        let chunk = chunk();
+        let rendered = r#"{
+  "files": [
+    { "path": "src/a.rs", "content": "fn a() {\n}\nfn b() {\n}" },
+    { "path": "src/b.rs", "content": "fn a() {\n}" }
+  ]
+}"#;
+
+        let map = ChunkMap::locate("prompt_001.json".to_string(), &chunk, rendered, |f| {
+            f.content_str().map(Cow::Borrowed)
+        });
+
+        assert_eq!(map.files.len(), 2);
+        assert_eq!(map.files[0].output_lines, LineRange { start: 3, end: 3 });
+        assert_eq!(map.files[1].output_lines, LineRange { start: 4, end: 4 });
+        assert!(map.files.iter().all(|file| file.source_lines.is_empty()));
+    }
+}
@@ -1,9 +1,13 @@
 use crate::{
+    boundary::{self, Boundary},
     config::Config,
     error::{Error, Result},
-    file::{FileContent, FileData},
+    file::{FileContent, FileData, FilePart, LineRange},
+    template::{line_number_prefix, number_lines},
     token::TokenEstimator,
 };
+use std::collections::BTreeMap;
+use std::path::Path;
 use std::sync::Arc;
 use tracing::{debug, trace, warn};
 
@@ -53,23 +57,63 @@ impl Chunk {
     }
 }
 
+/// How files are assigned to chunks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum PackingStrategy {
+    /// Fill chunks in path order, starting a new chunk when a file doesn't fit
+    #[default]
+    Sequential,
+    /// Place files largest first into the first chunk with room
+    FirstFitDecreasing,
+    /// Like first-fit-decreasing, but keep the files of a directory together
+    /// whenever they fit in one chunk
+    DirectoryAffinity,
+}
+
+impl PackingStrategy {
+    /// Returns the ID string for this strategy, as used on the command line
+    /// and in config files.
+    #[must_use]
+    pub const fn id(self) -> &'static str {
+        match self {
+            Self::Sequential => "sequential",
+            Self::FirstFitDecreasing => "first-fit-decreasing",
+            Self::DirectoryAffinity => "directory-affinity",
+        }
+    }
+
+    /// Parse a packing strategy from its string ID.
+    #[must_use]
+    pub fn from_id(id: &str) -> Option<Self> {
+        match id {
+            "sequential" => Some(Self::Sequential),
+            "first-fit-decreasing" => Some(Self::FirstFitDecreasing),
+            "directory-affinity" => Some(Self::DirectoryAffinity),
+            _ => None,
+        }
+    }
+}
+
 /// Splits files into optimally-sized chunks based on token limits.
 pub(crate) struct Splitter {
     max_chunk_tokens: usize,
     overlap_tokens: usize,
-    #[allow(dead_code)]
     prefer_line_boundaries: bool,
+    packing: PackingStrategy,
+    line_numbers: bool,
     tokenizer: Arc<dyn TokenEstimator>,
 }
 
 impl Splitter {
-    /// Creates a new splitter from configuration.
-    pub(crate) fn new(config: &Config) -> Self {
+    /// Creates a new splitter from configuration and a shared tokenizer.
+    pub(crate) fn new(config: &Config, tokenizer: Arc<dyn TokenEstimator>) -> Self {
         Self {
             max_chunk_tokens: config.effective_chunk_size(),
             overlap_tokens: config.overlap_tokens,
             prefer_line_boundaries: config.prefer_line_boundaries,
-            tokenizer: config.tokenizer.create(),
+            packing: config.packing,
+            line_numbers: config.line_numbers,
+            tokenizer,
         }
     }
 
@@ -77,18 +121,42 @@ impl Splitter {
     ///
     /// # Algorithm
     ///
-    /// 1. Files that fit within limits are grouped together
-    /// 2. Large files are split across multiple chunks with overlap
+    /// 1. Large files are split into parts with overlap
+    /// 2. Files and parts are assigned to chunks by the [`PackingStrategy`]
     /// 3. Chunks are optimized to maximize token utilization
     ///
     /// # Errors
     ///
     /// Returns an error if a binary file exceeds token limits.
-    pub(crate) fn split(&self, files: Vec<FileData>) -> Result<Vec<Chunk>> {
+    pub(crate) fn split(&self, mut files: Vec<FileData>) -> Result<Vec<Chunk>> {
         if files.is_empty() {
             return Ok(Vec::new());
         }
 
+        // Line numbers are added when rendering, but count against the budget
+        if self.line_numbers {
+            for file in files.iter_mut().filter(|file| file.diff.is_none()) {
+                if let Some(content) = file.content_str() {
+                    let numbered = number_lines(content, &file.source_line_numbers());
+                    file.token_count = self.tokenizer.estimate(&numbered);
+                }
+            }
+        }
+
+        let chunks = match self.packing {
+            PackingStrategy::Sequential => self.split_sequential(files)?,
+            PackingStrategy::FirstFitDecreasing | PackingStrategy::DirectoryAffinity => {
+                self.split_packed(files)?
+            }
+        };
+
+        self.log_split_results(&chunks);
+
+        Ok(chunks)
+    }
+
+    /// Fills chunks in input order.
+    fn split_sequential(&self, files: Vec<FileData>) -> Result<Vec<Chunk>> {
         let mut chunks = Vec::new();
         let mut current_builder = ChunkBuilder::new(0, self.max_chunk_tokens);
 
@@ -101,11 +169,56 @@ impl Splitter {
             chunks.push(chunk);
         }
 
-        self.log_split_results(&chunks);
-
         Ok(chunks)
     }
 
+    /// Bin-packs files and the parts of large files into as few chunks as
+    /// possible.
+    ///
+    /// Files keep their input order within a chunk, and chunks are ordered
+    /// by their first file.
+    fn split_packed(&self, files: Vec<FileData>) -> Result<Vec<Chunk>> {
+        let mut items = Vec::with_capacity(files.len());
+        for file in files {
+            if file.token_count > self.max_chunk_tokens {
+                items.extend(self.split_large_file(&file)?);
+            } else {
+                items.push(file);
+            }
+        }
+
+        let groups = if self.packing == PackingStrategy::DirectoryAffinity {
+            let mut by_directory: BTreeMap<&Path, Vec<usize>> = BTreeMap::new();
+            for (i, item) in items.iter().enumerate() {
+                let directory = Path::new(&item.relative_path)
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""));
+                by_directory.entry(directory).or_default().push(i);
+            }
+            by_directory.into_values().collect()
+        } else {
+            (0..items.len()).map(|i| vec![i]).collect()
+        };
+
+        let sizes: Vec<usize> = items.iter().map(|item| item.token_count).collect();
+        let bins = pack_groups(&sizes, groups, self.max_chunk_tokens);
+
+        let mut items: Vec<Option<FileData>> = items.into_iter().map(Some).collect();
+        Ok(bins
+            .into_iter()
+            .enumerate()
+            .filter_map(|(index, bin)| {
+                let mut builder = ChunkBuilder::new(index, self.max_chunk_tokens);
+                for i in bin {
+                    if let Some(item) = items[i].take() {
+                        builder.add_file(item);
+                    }
+                }
+                builder.build()
+            })
+            .collect())
+    }
+
     /// Processes a single file, adding it to chunks.
     fn process_file(
         &self,
@@ -180,6 +293,11 @@ impl Splitter {
     }
 
     /// Splits a large file into multiple parts with overlap.
+    ///
+    /// Each part is cut at the best [`Boundary`] in the back half of the
+    /// window that fits, then re-measured and shrunk until it fits
+    /// `max_chunk_tokens`. Only a single line larger than the limit can
+    /// produce an oversized part.
     fn split_large_file(&self, file: &FileData) -> Result<Vec<FileData>> {
         let content = match &file.content {
             FileContent::Text(text) => text,
@@ -199,116 +317,173 @@ impl Splitter {
             return Ok(vec![file.clone()]);
         }
 
-        let params = self.calculate_split_parameters(&lines, file.token_count);
-
-        // Pre-allocate с запасом
-        let mut parts = Vec::with_capacity(params.estimated_parts + 1);
-
-        // Переиспользуем буфер для всех частей
-        let estimated_chunk_size = content.len() / params.estimated_parts.max(1);
-        let mut chunk_buffer = String::with_capacity(estimated_chunk_size + 1024);
-
-        let mut start_line = 0;
-        let mut part_number = 1;
-
-        while start_line < total_lines {
-            let end_line = (start_line + params.lines_per_chunk).min(total_lines);
-
-            // Очищаем буфер вместо создания новой строки
-            chunk_buffer.clear();
+        let boundaries = boundary::boundaries(&lines, &file.absolute_path, file.diff.is_some());
+
+        // Source line numbers, when they are rendered before each line
+        let numbers =
+            (self.line_numbers && file.diff.is_none()).then(|| file.source_line_numbers());
+        let width = numbers
+            .as_ref()
+            .and_then(|numbers| numbers.iter().max())
+            .map_or(0, |max| max.to_string().len());
+
+        // Token estimate of lines[..i], used to find candidate windows
+        let mut prefix_tokens = Vec::with_capacity(total_lines + 1);
+        prefix_tokens.push(0);
+        for (i, line) in lines.iter().enumerate() {
+            let tokens = numbers.as_ref().map_or_else(
+                || self.tokenizer.estimate(line),
+                |numbers| {
+                    let prefix = line_number_prefix(numbers[i], width);
+                    self.tokenizer.estimate(&format!("{prefix}{line}"))
+                },
+            );
+            prefix_tokens.push(prefix_tokens[i] + tokens);
+        }
 
-            // Эффективное добавление строк
-            for (i, line) in lines[start_line..end_line].iter().enumerate() {
-                if i > 0 {
-                    chunk_buffer.push('\n');
-                }
-                chunk_buffer.push_str(line);
+        let mut chunk_buffer = String::with_capacity(content.len().min(self.max_chunk_tokens * 8));
+        let mut texts = Vec::new();
+        let mut start = 0;
+        let mut covered = 0;
+
+        while covered < total_lines {
+            let window = PartWindow {
+                lines: &lines,
+                boundaries: &boundaries,
+                prefix_tokens: &prefix_tokens,
+                numbers: numbers.as_deref(),
+                start,
+                min_end: covered + 1,
+            };
+            let (end, token_count) = self.fit_part(&window, &mut chunk_buffer);
+
+            // Overlap is dropped when it leaves no room for new lines
+            if token_count > self.max_chunk_tokens && start < covered {
+                start = covered;
+                continue;
             }
-
-            let token_count = self.tokenizer.estimate(&chunk_buffer);
-
             if token_count > self.max_chunk_tokens {
                 warn!(
-                    "Part {}/{} of '{}' has {} tokens (exceeds limit of {})",
-                    part_number,
-                    params.estimated_parts,
+                    "Line {} of '{}' alone has {} tokens (exceeds limit of {})",
+                    start + 1,
                     file.relative_path,
                     token_count,
                     self.max_chunk_tokens
                 );
             }
 
-            // Клонируем только финальный результат
-            parts.push(FileData::new_text(
-                file.absolute_path.clone(),
-                format!(
-                    "{} [Part {}/{}]",
-                    file.relative_path, part_number, params.estimated_parts
-                ),
-                chunk_buffer.clone(),
-                token_count,
-            ));
-
-            if end_line >= total_lines {
-                break;
-            }
-
-            start_line = end_line.saturating_sub(params.overlap_lines);
-            part_number += 1;
+            let overlap = (start < covered).then(|| LineRange {
+                start: start + 1,
+                end: covered,
+            });
+            texts.push((chunk_buffer.clone(), token_count, start + 1, end, overlap));
+            covered = end;
+            start = self.overlap_start(&lines, start, end);
         }
 
-        trace!(
-            "Split '{}' into {} parts (estimated {})",
-            file.relative_path,
-            parts.len(),
-            params.estimated_parts
-        );
+        let total = texts.len();
+        let parts: Vec<FileData> = texts
+            .into_iter()
+            .enumerate()
+            .map(|(i, (text, token_count, start_line, end_line, overlap))| FileData {
+                diff: file.diff.clone(),
+                source_lines: file.diff.is_none().then(|| {
+                    file.source_lines.as_ref().map_or_else(
+                        || (start_line..=end_line).collect(),
+                        |lines| lines[start_line - 1..end_line].to_vec(),
+                    )
+                }),
+                part: Some(FilePart {
+                    index: i + 1,
+                    total,
+                    start_line,
+                    end_line,
+                    overlap,
+                }),
+                ..FileData::new_text(
+                    file.absolute_path.clone(),
+                    file.relative_path.clone(),
+                    text,
+                    token_count,
+                )
+            })
+            .collect();
+
+        trace!("Split '{}' into {} parts", file.relative_path, parts.len());
 
         Ok(parts)
     }
 
-    /// Оптимизированный расчет параметров разбиения
-    fn calculate_split_parameters(&self, lines: &[&str], _total_tokens: usize) -> SplitParameters {
-        let total_lines = lines.len();
+    /// Chooses the end of the part starting at `window.start`, writes its
+    /// text to `buffer` and returns the end line and measured token count,
+    /// including line numbers when they are rendered.
+    ///
+    /// The returned token count only exceeds the limit when the part is
+    /// already down to `window.min_end`.
+    fn fit_part(&self, window: &PartWindow<'_>, buffer: &mut String) -> (usize, usize) {
+        let PartWindow { lines, prefix_tokens, numbers, start, min_end, .. } = *window;
+        let budget = prefix_tokens[start] + self.max_chunk_tokens;
+
+        // Furthest end whose estimate fits
+        let mut limit = prefix_tokens[min_end..]
+            .iter()
+            .rposition(|&tokens| tokens <= budget)
+            .map_or(min_end, |offset| min_end + offset);
 
-        // Используем адаптивный размер выборки
-        let sample_size = total_lines.min(100);
+        loop {
+            let end = self.pick_end(window, limit);
 
-        // Избегаем лишних аллокаций при подсчете токенов
-        let mut sample_buffer = String::with_capacity(sample_size * 80); // ~80 chars per line
+            buffer.clear();
+            for (i, line) in lines[start..end].iter().enumerate() {
+                if i > 0 {
+                    buffer.push('\n');
+                }
+                buffer.push_str(line);
+            }
 
-        for (i, line) in lines[..sample_size].iter().enumerate() {
-            if i > 0 {
-                sample_buffer.push('\n');
+            let token_count = numbers.map_or_else(
+                || self.tokenizer.estimate(buffer),
+                |numbers| self.tokenizer.estimate(&number_lines(buffer, &numbers[start..end])),
+            );
+            if token_count <= self.max_chunk_tokens || end == min_end {
+                return (end, token_count);
             }
-            sample_buffer.push_str(line);
+            limit = end - 1;
         }
+    }
 
-        let sample_tokens = self.tokenizer.estimate(&sample_buffer);
-
-        let avg_tokens_per_line = if sample_size > 0 {
-            (sample_tokens as f64 / sample_size as f64).max(1.0)
-        } else {
-            1.0
-        };
-
-        let lines_per_chunk = (self.max_chunk_tokens as f64 / avg_tokens_per_line) as usize;
-        let lines_per_chunk = lines_per_chunk.max(1);
+    /// Returns the best split point in `window.min_end..=limit`.
+    ///
+    /// Item boundaries win over blank lines, which win over plain lines,
+    /// but only within the back half of the window so parts stay well
+    /// filled. Without `prefer_line_boundaries` the window is filled up to
+    /// `limit`.
+    fn pick_end(&self, window: &PartWindow<'_>, limit: usize) -> usize {
+        let PartWindow { boundaries, prefix_tokens, start, min_end, .. } = *window;
+        if !self.prefer_line_boundaries || limit == boundaries.len() - 1 {
+            return limit;
+        }
 
-        let overlap_lines = (self.overlap_tokens as f64 / avg_tokens_per_line) as usize;
-        let overlap_lines = overlap_lines.min(lines_per_chunk / 2);
+        let half = prefix_tokens[start] + (prefix_tokens[limit] - prefix_tokens[start]) / 2;
+        let candidates = (min_end..=limit).filter(|&end| prefix_tokens[end] >= half);
 
-        let estimated_parts = if lines_per_chunk > 0 {
-            (total_lines + lines_per_chunk - overlap_lines - 1) / (lines_per_chunk - overlap_lines)
-        } else {
-            1
-        };
+        candidates
+            .max_by_key(|&end| (boundaries[end], end))
+            .unwrap_or(limit)
+    }
 
-        SplitParameters {
-            lines_per_chunk,
-            overlap_lines,
-            estimated_parts,
+    /// Returns where the part after `start..end` begins, repeating as many
+    /// trailing lines as the tokenizer fits in `overlap_tokens`, and at most
+    /// half of the previous part.
+    fn overlap_start(&self, lines: &[&str], start: usize, end: usize) -> usize {
+        let max_lines = (end - start) / 2;
+        let mut next = end;
+        while end - next < max_lines
+            && self.tokenizer.estimate(&lines[next - 1..end].join("\n")) <= self.overlap_tokens
+        {
+            next -= 1;
         }
+        next
     }
 
     /// Logs results of the splitting operation.
@@ -333,12 +508,62 @@ impl Splitter {
     }
 }
 
-/// Parameters for splitting a large file.
-#[derive(Debug)]
-struct SplitParameters {
-    lines_per_chunk: usize,
-    overlap_lines: usize,
-    estimated_parts: usize,
+/// First-fit-decreasing packing of groups of items into bins of `capacity`.
+///
+/// A group that fits in one bin is never split; larger groups are packed
+/// item by item. Returns the item indices of each bin in ascending order,
+/// with bins ordered by their first item.
+fn pack_groups(sizes: &[usize], mut groups: Vec<Vec<usize>>, capacity: usize) -> Vec<Vec<usize>> {
+    let total = |group: &[usize]| group.iter().map(|&i| sizes[i]).sum::<usize>();
+    // Stable sort: equal groups keep their input order
+    groups.sort_by_key(|group| std::cmp::Reverse(total(group)));
+
+    let mut bins: Vec<(usize, Vec<usize>)> = Vec::new();
+    let mut place = |items: &[usize], tokens: usize| {
+        match bins.iter_mut().find(|(load, _)| load + tokens <= capacity) {
+            Some((load, bin)) => {
+                *load += tokens;
+                bin.extend_from_slice(items);
+            }
+            None => bins.push((tokens, items.to_vec())),
+        }
+    };
+
+    for mut group in groups {
+        let tokens = total(&group);
+        if tokens <= capacity {
+            place(&group, tokens);
+        } else {
+            group.sort_by_key(|&i| std::cmp::Reverse(sizes[i]));
+            for i in group {
+                place(&[i], sizes[i]);
+            }
+        }
+    }
+
+    let mut bins: Vec<Vec<usize>> = bins
+        .into_iter()
+        .map(|(_, mut bin)| {
+            bin.sort_unstable();
+            bin
+        })
+        .collect();
+    bins.sort_by_key(|bin| bin.first().copied());
+    bins
+}
+
+/// Lines available to the part being cut from a large file.
+#[derive(Clone, Copy)]
+struct PartWindow<'a> {
+    lines: &'a [&'a str],
+    boundaries: &'a [Boundary],
+    prefix_tokens: &'a [usize],
+    /// Source line numbers of `lines`, when they are rendered
+    numbers: Option<&'a [usize]>,
+    /// First line of the part, including overlap
+    start: usize,
+    /// Smallest end that still adds new lines
+    min_end: usize,
 }
 
 /// Builder for constructing chunks incrementally.
@@ -401,7 +626,7 @@ mod tests {
     #[test]
     fn test_splitter_single_file() {
         let config = create_test_config(3000);
-        let splitter = Splitter::new(&config);
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
 
         let files = vec![FileData::new_text(
             PathBuf::from("test.rs"),
@@ -420,7 +645,7 @@ mod tests {
     #[test]
     fn test_splitter_multiple_files_single_chunk() {
         let config = create_test_config(3000);
-        let splitter = Splitter::new(&config);
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
 
         let files = vec![
             FileData::new_text(
@@ -447,7 +672,7 @@ mod tests {
     #[test]
     fn test_splitter_multiple_chunks() {
         let config = create_test_config(2500);
-        let splitter = Splitter::new(&config);
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
 
         let files = vec![
             FileData::new_text(
@@ -474,7 +699,7 @@ mod tests {
     #[test]
     fn test_splitter_large_file() {
         let config = create_test_config(2500);
-        let splitter = Splitter::new(&config);
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
 
         let large_content = (0..1000)
             .map(|i| format!("fn function_{}() {{}}", i))
@@ -503,6 +728,210 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_splitter_large_file_at_item_boundaries() {
+        let config = create_test_config(2500);
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
+        let tokenizer = config.tokenizer.create().unwrap();
+
+        let content = (0..200)
+            .map(|i| format!("/// Doc {i}\nfn function_{i}() -> u32 {{\n    let value = {i};\n    value * 2\n}}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let files = vec![FileData::new_text(
+            PathBuf::from("large.rs"),
+            "large.rs".to_string(),
+            content.clone(),
+            tokenizer.estimate(&content),
+        )];
+
+        let chunks = splitter.split(files).unwrap();
+        assert!(chunks.len() > 2, "Large file should be split");
+
+        let lines: Vec<&str> = content.lines().collect();
+        let mut previous_end = 0;
+        for (i, chunk) in chunks.iter().enumerate() {
+            let part = &chunk.files[0];
+            let text = part.content_str().unwrap();
+            let meta = part.part.unwrap();
+            assert_eq!(part.relative_path, "large.rs");
+            assert_eq!((meta.index, meta.total), (i + 1, chunks.len()));
+            assert_eq!(text, lines[meta.start_line - 1..meta.end_line].join("\n"));
+            assert_eq!(part.source_line(1), Some(meta.start_line));
+
+            // The overlap is exactly the lines already sent, within the budget
+            match meta.overlap {
+                Some(overlap) => {
+                    assert_eq!((overlap.start, overlap.end), (meta.start_line, previous_end));
+                    let repeated = lines[overlap.start - 1..overlap.end].join("\n");
+                    assert!(tokenizer.estimate(&repeated) <= config.overlap_tokens);
+                }
+                None => assert_eq!(meta.start_line, previous_end + 1),
+            }
+            previous_end = meta.end_line;
+
+            assert!(part.token_count <= config.effective_chunk_size());
+            assert_eq!(part.token_count, tokenizer.estimate(text));
+            assert!(text.ends_with('}'), "Part {i} ends mid-item: {text:?}");
+        }
+        assert_eq!(previous_end, lines.len());
+    }
+
+    #[test]
+    fn test_splitter_fills_parts_without_line_preference() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .max_tokens(2500)
+            .overlap_tokens(0)
+            .prefer_line_boundaries(false)
+            .build()
+            .unwrap();
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
+        let content = (0..400).map(|i| format!("line {i}")).collect::<Vec<_>>().join("\n");
+
+        let parts = splitter
+            .split_large_file(&FileData::new_text(
+                PathBuf::from("notes.txt"),
+                "notes.txt".to_string(),
+                content.clone(),
+                10_000,
+            ))
+            .unwrap();
+
+        let joined: Vec<&str> = parts.iter().map(|p| p.content_str().unwrap()).collect();
+        assert_eq!(joined.join("\n"), content);
+        for part in &parts[..parts.len() - 1] {
+            assert!(part.token_count > config.effective_chunk_size() * 9 / 10);
+        }
+    }
+
+    #[test]
+    fn test_splitter_parts_keep_source_lines() {
+        let config = create_test_config(2500);
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
+        let content = (0..400).map(|i| format!("let value_{i} = {i};")).collect::<Vec<_>>().join("\n");
+
+        // Every other source line was filtered out
+        let parts = splitter
+            .split_large_file(&FileData {
+                source_lines: Some((0..400).map(|i| i * 2 + 1).collect()),
+                ..FileData::new_text(PathBuf::from("big.rs"), "big.rs".to_string(), content, 10_000)
+            })
+            .unwrap();
+
+        assert!(parts.len() > 1);
+        for part in &parts {
+            let meta = part.part.unwrap();
+            let lines = part.line_count().unwrap();
+            assert_eq!(part.source_line(1), Some(meta.start_line * 2 - 1));
+            assert_eq!(part.source_line(lines), Some(meta.end_line * 2 - 1));
+        }
+    }
+
+    fn file(path: &str, tokens: usize) -> FileData {
+        FileData::new_text(PathBuf::from(path), path.to_string(), String::new(), tokens)
+    }
+
+    #[test]
+    fn test_splitter_counts_line_numbers() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .max_tokens(2500)
+            .overlap_tokens(100)
+            .line_numbers(true)
+            .build()
+            .unwrap();
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
+        let tokenizer = config.tokenizer.create().unwrap();
+
+        let file = |name: &str, lines: usize| {
+            let content = (0..lines).map(|i| format!("x{i}")).collect::<Vec<_>>().join("\n");
+            let tokens = tokenizer.estimate(&content);
+            FileData::new_text(PathBuf::from(name), name.to_string(), content, tokens)
+        };
+        let files = vec![file("a.rs", 300), file("b.rs", 300), file("large.rs", 3000)];
+
+        let chunks = splitter.split(files).unwrap();
+        assert!(chunks.len() > 2, "Large file should be split");
+
+        for chunk in &chunks {
+            let numbered: usize = chunk
+                .files
+                .iter()
+                .map(|file| {
+                    let content = file.content_str().unwrap();
+                    tokenizer.estimate(&number_lines(content, &file.source_line_numbers()))
+                })
+                .sum();
+            assert_eq!(chunk.total_tokens, numbered);
+            assert!(numbered <= config.effective_chunk_size(), "Chunk {} is over budget", chunk.index);
+        }
+    }
+
+    fn chunk_paths(chunks: &[Chunk]) -> Vec<Vec<&str>> {
+        chunks
+            .iter()
+            .map(|c| c.files.iter().map(|f| f.relative_path.as_str()).collect())
+            .collect()
+    }
+
+    fn packing_splitter(packing: PackingStrategy) -> Splitter {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .max_tokens(3000)
+            .packing(packing)
+            .build()
+            .unwrap();
+        Splitter::new(&config, config.tokenizer.create().unwrap())
+    }
+
+    #[test]
+    fn test_pack_groups() {
+        let singles = |n: usize| (0..n).map(|i| vec![i]).collect();
+        assert_eq!(pack_groups(&[6, 5, 4, 3, 2], singles(5), 10), [vec![0, 2], vec![1, 3, 4]]);
+
+        // Groups that fit stay together
+        let groups = vec![vec![0, 1], vec![2], vec![3]];
+        assert_eq!(pack_groups(&[3, 3, 5, 4], groups, 10), [vec![0, 1, 3], vec![2]]);
+
+        // Oversized groups are packed file by file
+        let groups = vec![vec![0, 1], vec![2]];
+        assert_eq!(pack_groups(&[6, 6, 2], groups, 10), [vec![0, 2], vec![1]]);
+    }
+
+    #[test]
+    fn test_splitter_packing_strategies() {
+        // Effective limit: 3000 - 2000 safety margin = 1000 tokens
+        let files = || {
+            vec![
+                file("a/one.rs", 400),
+                file("a/two.rs", 200),
+                file("b/three.rs", 500),
+                file("b/four.rs", 300),
+                file("c/five.rs", 400),
+            ]
+        };
+
+        let sequential = packing_splitter(PackingStrategy::Sequential).split(files()).unwrap();
+        assert_eq!(sequential.len(), 3);
+
+        let ffd = packing_splitter(PackingStrategy::FirstFitDecreasing).split(files()).unwrap();
+        assert_eq!(
+            chunk_paths(&ffd),
+            [vec!["a/one.rs", "b/three.rs"], vec!["a/two.rs", "b/four.rs", "c/five.rs"]]
+        );
+        assert_eq!(ffd.iter().map(|c| c.index).collect::<Vec<_>>(), [0, 1]);
+
+        let affinity = packing_splitter(PackingStrategy::DirectoryAffinity).split(files()).unwrap();
+        assert_eq!(
+            chunk_paths(&affinity),
+            [vec!["a/one.rs", "a/two.rs", "c/five.rs"], vec!["b/three.rs", "b/four.rs"]]
+        );
+    }
+
     #[test]
     fn test_chunk_utilization() {
         let chunk = Chunk::new(
@@ -518,7 +947,7 @@ mod tests {
     #[test]
     fn test_splitter_empty_files() {
         let config = create_test_config(3000);
-        let splitter = Splitter::new(&config);
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
 
         let chunks = splitter.split(vec![]).unwrap();
         assert!(chunks.is_empty());
@@ -527,7 +956,7 @@ mod tests {
     #[test]
     fn test_splitter_binary_file_too_large() {
         let config = create_test_config(2500);
-        let splitter = Splitter::new(&config);
+        let splitter = Splitter::new(&config, config.tokenizer.create().unwrap());
 
         let files = vec![FileData::new_binary(
             PathBuf::from("large.bin"),
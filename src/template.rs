@@ -1,10 +1,15 @@
 use crate::{
     config::{Config, OutputFormat},
+    diff::FileDiff,
     error::{Error, Result},
-    preset::LLMPreset,
+    file::{FileData, FilePart},
+    preset::{CodeBlockStyle, LLMPreset},
+    prompt::{PromptStats, PromptTemplate, PromptValues},
     splitter::Chunk,
+    tree::TreeNode,
 };
 use serde::Serialize;
+use std::borrow::Cow;
 use std::collections::HashMap;
 use tera::{Context, Tera, Value};
 
@@ -17,6 +22,8 @@ struct TemplateContext<'a> {
     files: Vec<FileView<'a>>,
     metadata: ContextMetadata,
     preset: Option<PresetContext>,
+    structure: Option<String>,
+    tree: Option<&'a TreeNode>,
     custom: Option<HashMap<String, serde_json::Value>>,
 }
 
@@ -24,10 +31,12 @@ struct TemplateContext<'a> {
 struct FileView<'a> {
     path: &'a str,
     relative_path: &'a str,
-    content: Option<&'a str>,
+    content: Option<Cow<'a, str>>,
     is_binary: bool,
     token_count: usize,
     lines: Option<usize>,
+    diff: Option<&'a FileDiff>,
+    part: Option<&'a FilePart>,
 }
 
 #[derive(Serialize)]
@@ -42,10 +51,33 @@ struct PresetContext {
     name: String,
     description: String,
     system_prompt: String,
+    user_prompt: String,
     user_prompt_template: String,
     suggested_model: String,
     max_tokens_hint: usize,
     temperature_hint: f32,
+    code_block_style: &'static str,
+}
+
+/// Project-wide data shared by every chunk of a run.
+pub(crate) struct RunContext {
+    /// Statistics for preset prompt placeholders
+    pub stats: PromptStats,
+    /// Tree of every file in the run
+    pub tree: TreeNode,
+    /// Generation timestamp shown in every chunk
+    pub generated_at: String,
+}
+
+impl RunContext {
+    /// Collects run-wide data from all chunks.
+    pub(crate) fn new(chunks: &[Chunk]) -> Self {
+        Self {
+            stats: PromptStats::for_run(chunks),
+            tree: TreeNode::from_chunks(chunks),
+            generated_at: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        }
+    }
 }
 
 /// Template engine for rendering chunks in different formats.
@@ -53,6 +85,9 @@ pub(crate) struct TemplateEngine {
     tera: Tera,
     format: OutputFormat,
     preset: Option<LLMPreset>,
+    user_prompt: Option<PromptTemplate>,
+    structure_directory_totals: bool,
+    line_numbers: bool,
     custom_data: HashMap<String, serde_json::Value>,
     custom_template_name: Option<String>,
 }
@@ -62,7 +97,8 @@ impl TemplateEngine {
     ///
     /// # Errors
     ///
-    /// Returns an error if template registration or filter setup fails.
+    /// Returns an error if template registration or filter setup fails,
+    /// or if the preset prompt uses an unknown placeholder.
     pub(crate) fn new(config: &Config) -> Result<Self> {
         let mut tera = Tera::default();
 
@@ -82,12 +118,19 @@ impl TemplateEngine {
             Self::load_external_template(&mut tera, template_path, config)?;
         }
 
-        let preset = config.preset.map(LLMPreset::for_kind);
+        let preset = config.preset.clone();
+        let user_prompt = preset
+            .as_ref()
+            .map(|preset| PromptTemplate::parse(&preset.id, &preset.user_prompt_template))
+            .transpose()?;
 
         Ok(Self {
             tera,
             format: config.format,
             preset,
+            user_prompt,
+            structure_directory_totals: config.structure_directory_totals,
+            line_numbers: config.line_numbers,
             custom_data: config.custom_data.clone(),
             custom_template_name: config.custom_format_name.clone(),
         })
@@ -152,6 +195,10 @@ impl TemplateEngine {
 
         // Language detection filter
         tera.register_filter("detect_language", Self::detect_language_filter);
+
+        // Code block wrapping, as a filter and as a function
+        tera.register_filter("code_block", Self::code_block_filter);
+        tera.register_function("code_block", Self::code_block_function);
     }
 
     /// Loads and registers an external template file.
@@ -288,63 +335,63 @@ impl TemplateEngine {
         value: &Value,
         _args: &HashMap<String, Value>,
     ) -> tera::Result<Value> {
-        if let Some(path) = value.as_str() {
-            let language = if let Some(ext) = path.rsplit('.').next() {
-                match ext {
-                    "rs" => "rust",
-                    "py" => "python",
-                    "js" => "javascript",
-                    "ts" => "typescript",
-                    "jsx" => "jsx",
-                    "tsx" => "tsx",
-                    "go" => "go",
-                    "java" => "java",
-                    "c" => "c",
-                    "h" => "c",
-                    "cpp" | "cc" | "cxx" => "cpp",
-                    "hpp" | "hh" | "hxx" => "cpp",
-                    "cs" => "csharp",
-                    "rb" => "ruby",
-                    "php" => "php",
-                    "swift" => "swift",
-                    "kt" => "kotlin",
-                    "scala" => "scala",
-                    "sh" | "bash" => "bash",
-                    "zsh" => "zsh",
-                    "fish" => "fish",
-                    "ps1" => "powershell",
-                    "html" | "htm" => "html",
-                    "css" => "css",
-                    "scss" => "scss",
-                    "sass" => "sass",
-                    "xml" => "xml",
-                    "json" => "json",
-                    "yaml" | "yml" => "yaml",
-                    "toml" => "toml",
-                    "ini" => "ini",
-                    "md" | "markdown" => "markdown",
-                    "sql" => "sql",
-                    "graphql" | "gql" => "graphql",
-                    "proto" => "protobuf",
-                    "dockerfile" => "dockerfile",
-                    "makefile" => "makefile",
-                    _ => "",
-                }
-            } else {
-                ""
-            };
-            Ok(Value::String(language.to_string()))
-        } else {
-            Ok(Value::String(String::new()))
-        }
+        let language = value.as_str().map_or("", detect_language);
+        Ok(Value::String(language.to_string()))
+    }
+
+    /// Wraps file contents in a code block.
+    ///
+    /// Arguments: `style` (`markdown`, `xml` or `inline`; default `markdown`),
+    /// `lang` for the fence language, or `path` to detect it from the extension.
+    fn code_block_filter(
+        value: &Value,
+        args: &HashMap<String, Value>,
+    ) -> tera::Result<Value> {
+        let Some(content) = value.as_str() else {
+            return Ok(value.clone());
+        };
+
+        let style = match args.get("style").and_then(Value::as_str) {
+            Some(id) => CodeBlockStyle::from_id(id).ok_or_else(|| {
+                tera::Error::msg(format!(
+                    "code_block: unknown style '{id}' (expected markdown, xml or inline)"
+                ))
+            })?,
+            None => CodeBlockStyle::Markdown,
+        };
+
+        let language = args
+            .get("lang")
+            .and_then(Value::as_str)
+            .or_else(|| args.get("path").and_then(Value::as_str).map(detect_language))
+            .unwrap_or("");
+
+        Ok(Value::String(code_block(content, style, language)))
+    }
+
+    /// Function form of the `code_block` filter, taking the text as `content`.
+    fn code_block_function(args: &HashMap<String, Value>) -> tera::Result<Value> {
+        let content = args
+            .get("content")
+            .ok_or_else(|| tera::Error::msg("code_block: missing `content` argument"))?;
+
+        Self::code_block_filter(content, args)
     }
 
     /// Renders a chunk using the configured template.
     ///
+    /// `run` covers every chunk of the run: it fills the project-wide
+    /// placeholders of the preset prompt and provides the structure tree.
+    ///
     /// # Errors
     ///
     /// Returns an error if template rendering fails.
-    pub(crate) fn render(&self, chunk: &Chunk, total_chunks: usize) -> Result<String> {
+    pub(crate) fn render(
+        &self,
+        chunk: &Chunk,
+        total_chunks: usize,
+        run: &RunContext,
+    ) -> Result<String> {
         // Choose template based on whether preset is used
         let template_name = if self.preset.is_some() {
             match self.format {
@@ -369,31 +416,51 @@ impl TemplateEngine {
             .files
             .iter()
             .map(|f| {
-                let content_str = f.content_str();
-                let lines = content_str.map(|s| s.lines().count());
+                let lines = f.content_str().map(|s| s.lines().count());
 
                 FileView {
                     path: f.absolute_path.to_str().unwrap_or(""),
                     relative_path: &f.relative_path,
-                    content: content_str,
+                    content: self.file_content(f),
                     is_binary: f.is_binary(),
                     token_count: f.token_count,
                     lines,
+                    diff: f.diff.as_ref(),
+                    part: f.part.as_ref(),
                 }
             })
             .collect();
 
+        let user_prompt = self.user_prompt.as_ref().map(|template| {
+            template.render(&PromptValues {
+                run: &run.stats,
+                chunk: &PromptStats::for_chunk(chunk),
+                chunk_index: chunk.index + 1,
+                total_chunks,
+                code_content: self.code_content_reference(),
+            })
+        });
+
         let preset_context = self.preset.as_ref().map(|preset| PresetContext {
             id: preset.id.clone(),
             name: preset.name.clone(),
             description: preset.description.clone(),
             system_prompt: preset.system_prompt.clone(),
+            user_prompt: user_prompt.unwrap_or_default(),
             user_prompt_template: preset.user_prompt_template.clone(),
             suggested_model: preset.suggested_model.clone(),
             max_tokens_hint: preset.max_tokens_hint,
             temperature_hint: preset.temperature_hint,
+            code_block_style: preset.code_block_style.id(),
         });
 
+        // The project tree goes into the first chunk only
+        let tree = self
+            .preset
+            .as_ref()
+            .filter(|preset| preset.include_structure && chunk.index == 0)
+            .map(|_| &run.tree);
+
         // Prepare custom data (only include if non-empty)
         let custom_data = if self.custom_data.is_empty() {
             None
@@ -408,12 +475,12 @@ impl TemplateEngine {
             total_tokens: chunk.total_tokens,
             files,
             metadata: ContextMetadata {
-                generated_at: chrono::Local::now()
-                    .format("%Y-%m-%d %H:%M:%S")
-                    .to_string(),
+                generated_at: run.generated_at.clone(),
                 format: format!("{:?}", self.format),
             },
             preset: preset_context,
+            structure: tree.map(|tree| tree.render(self.structure_directory_totals)),
+            tree,
             custom: custom_data,
         };
 
@@ -424,12 +491,139 @@ impl TemplateEngine {
             .render(template_name, &tera_context)
             .map_err(|e| Error::template(template_name, e))
     }
+
+    /// Returns a file's content as passed to templates, with line numbers
+    /// when enabled.
+    pub(crate) fn file_content<'a>(&self, file: &'a FileData) -> Option<Cow<'a, str>> {
+        let content = file.content_str()?;
+        if self.line_numbers && file.diff.is_none() {
+            Some(Cow::Owned(number_lines(content, &file.source_line_numbers())))
+        } else {
+            Some(Cow::Borrowed(content))
+        }
+    }
+
+    /// Text substituted for `{code_content}` in preset prompts.
+    ///
+    /// The code is rendered once after the prompt, so the prompt points to it
+    /// instead of repeating every file.
+    const fn code_content_reference(&self) -> &'static str {
+        match self.format {
+            OutputFormat::Markdown => "See the files in the **Codebase Content** section below.",
+            OutputFormat::Xml => "See the files in the <codebase> element below.",
+            OutputFormat::Json => "See the files in the \"files\" array below.",
+            OutputFormat::Custom => "See the files included below.",
+        }
+    }
+}
+
+/// Prefixes each line of `content` with its source line number from
+/// `numbers`, right-aligned to the widest number.
+pub(crate) fn number_lines(content: &str, numbers: &[usize]) -> String {
+    let width = numbers.iter().max().map_or(1, |max| max.to_string().len());
+
+    content
+        .lines()
+        .zip(numbers)
+        .map(|(line, &number)| format!("{}{line}", line_number_prefix(number, width)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns the prefix [`number_lines`] puts before a line.
+pub(crate) fn line_number_prefix(number: usize, width: usize) -> String {
+    format!("{number:>width$} | ")
+}
+
+/// Wraps `content` in a code block of the given style.
+///
+/// Markdown fences are one backtick longer than the longest backtick run in
+/// the content, and `]]>` is split across CDATA sections, so the content can
+/// never terminate its own block.
+pub(crate) fn code_block(content: &str, style: CodeBlockStyle, language: &str) -> String {
+    match style {
+        CodeBlockStyle::Markdown => {
+            let fence = "`".repeat(longest_backtick_run(content).max(2) + 1);
+            format!("{fence}{language}\n{content}\n{fence}")
+        }
+        CodeBlockStyle::Xml => {
+            format!("<![CDATA[\n{}\n]]>", content.replace("]]>", "]]]]><![CDATA[>"))
+        }
+        CodeBlockStyle::Inline => {
+            let fence = "`".repeat(longest_backtick_run(content) + 1);
+            // Padding keeps a leading or trailing backtick out of the delimiter
+            if content.starts_with('`') || content.ends_with('`') {
+                format!("{fence} {content} {fence}")
+            } else {
+                format!("{fence}{content}{fence}")
+            }
+        }
+    }
+}
+
+fn longest_backtick_run(content: &str) -> usize {
+    content
+        .split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Returns the code-fence language for a path based on its extension.
+///
+/// Returns an empty string for unknown extensions.
+pub(crate) fn detect_language(path: &str) -> &'static str {
+    let Some(ext) = path.rsplit('.').next() else {
+        return "";
+    };
+
+    match ext {
+        "rs" => "rust",
+        "py" => "python",
+        "js" => "javascript",
+        "ts" => "typescript",
+        "jsx" => "jsx",
+        "tsx" => "tsx",
+        "go" => "go",
+        "java" => "java",
+        "c" => "c",
+        "h" => "c",
+        "cpp" | "cc" | "cxx" => "cpp",
+        "hpp" | "hh" | "hxx" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "swift" => "swift",
+        "kt" | "kts" => "kotlin",
+        "scala" => "scala",
+        "sh" | "bash" | "bats" => "bash",
+        "zsh" => "zsh",
+        "fish" => "fish",
+        "ps1" => "powershell",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "scss" => "scss",
+        "sass" => "sass",
+        "xml" => "xml",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "toml" => "toml",
+        "ini" => "ini",
+        "md" | "markdown" => "markdown",
+        "sql" => "sql",
+        "lua" => "lua",
+        "graphql" | "gql" => "graphql",
+        "proto" => "protobuf",
+        "dockerfile" => "dockerfile",
+        "makefile" => "makefile",
+        _ => "",
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::file::FileData;
+    use crate::file::{FileData, LineRange};
     use std::path::PathBuf;
 
     fn create_test_config(format: OutputFormat) -> Config {
@@ -477,7 +671,7 @@ mod tests {
         let engine = TemplateEngine::new(&config).unwrap();
         let chunk = create_test_chunk();
 
-        let result = engine.render(&chunk, 1);
+        let result = engine.render(&chunk, 1, &RunContext::new(std::slice::from_ref(&chunk)));
         assert!(result.is_ok());
 
         let rendered = result.unwrap();
@@ -492,7 +686,7 @@ mod tests {
         let engine = TemplateEngine::new(&config).unwrap();
         let chunk = create_test_chunk();
 
-        let result = engine.render(&chunk, 1);
+        let result = engine.render(&chunk, 1, &RunContext::new(std::slice::from_ref(&chunk)));
         assert!(result.is_ok());
 
         let rendered = result.unwrap();
@@ -508,7 +702,7 @@ mod tests {
         let engine = TemplateEngine::new(&config).unwrap();
         let chunk = create_test_chunk();
 
-        let result = engine.render(&chunk, 1);
+        let result = engine.render(&chunk, 1, &RunContext::new(std::slice::from_ref(&chunk)));
         assert!(result.is_ok());
 
         let rendered = result.unwrap();
@@ -517,6 +711,243 @@ mod tests {
         assert!(rendered.contains("test.rs"));
     }
 
+    #[test]
+    fn test_render_preset_prompt_placeholders() {
+        use crate::preset::PresetKind;
+        use assert_fs::TempDir;
+
+        let temp = TempDir::new().unwrap();
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .output_dir(temp.path().join("out"))
+            .preset(PresetKind::CodeReview)
+            .build()
+            .unwrap();
+        let engine = TemplateEngine::new(&config).unwrap();
+        let chunk = create_test_chunk();
+
+        let rendered = engine
+            .render(&chunk, 1, &RunContext::new(std::slice::from_ref(&chunk)))
+            .unwrap();
+
+        assert!(rendered.contains("- Total Files: 2"));
+        assert!(rendered.contains("- Total Lines: 3"));
+        assert!(rendered.contains("- Languages: rust (1 file)"));
+        assert!(rendered.contains("See the files in the **Codebase Content** section below."));
+        assert!(!rendered.contains("{languages}"));
+        assert!(!rendered.contains("{code_content}"));
+    }
+
+    #[test]
+    fn test_structure_only_in_first_chunk() {
+        use crate::preset::PresetKind;
+        use assert_fs::TempDir;
+
+        let temp = TempDir::new().unwrap();
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .output_dir(temp.path().join("out"))
+            .format(OutputFormat::Json)
+            .preset(PresetKind::ArchitectureReview)
+            .build()
+            .unwrap();
+        let engine = TemplateEngine::new(&config).unwrap();
+        let first = create_test_chunk();
+        let mut second = create_test_chunk();
+        second.index = 1;
+        let run = RunContext::new(&[first.clone(), second.clone()]);
+
+        let rendered = engine.render(&first, 2, &run).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert!(parsed["structure"].as_str().unwrap().contains("└── test.rs (20 tokens)"));
+        assert_eq!(parsed["tree"]["files"], 2);
+
+        let rendered = engine.render(&second, 2, &run).unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&rendered).unwrap();
+        assert!(parsed.get("structure").is_none());
+    }
+
+    #[test]
+    fn test_code_block_markdown_fence_length() {
+        assert_eq!(
+            code_block("fn main() {}", CodeBlockStyle::Markdown, "rust"),
+            "```rust\nfn main() {}\n```"
+        );
+
+        let content = "Example:\n```rust\nlet x = 1;\n```";
+        assert_eq!(
+            code_block(content, CodeBlockStyle::Markdown, "markdown"),
+            format!("````markdown\n{content}\n````")
+        );
+    }
+
+    #[test]
+    fn test_code_block_xml_splits_cdata_end() {
+        let block = code_block("a[i[j]]>b", CodeBlockStyle::Xml, "");
+
+        assert_eq!(block, "<![CDATA[\na[i[j]]]]><![CDATA[>b\n]]>");
+        assert_eq!(block.matches("]]>").count(), 2);
+    }
+
+    #[test]
+    fn test_code_block_inline() {
+        assert_eq!(code_block("x + 1", CodeBlockStyle::Inline, ""), "`x + 1`");
+        assert_eq!(code_block("a `b` c", CodeBlockStyle::Inline, ""), "``a `b` c``");
+        assert_eq!(code_block("`tick", CodeBlockStyle::Inline, ""), "`` `tick ``");
+    }
+
+    #[test]
+    fn test_code_block_filter_args() {
+        let value = Value::String("print(1)".to_string());
+
+        let mut args = HashMap::new();
+        args.insert("path".to_string(), Value::String("main.py".to_string()));
+        let result = TemplateEngine::code_block_filter(&value, &args).unwrap();
+        assert_eq!(result.as_str().unwrap(), "```python\nprint(1)\n```");
+
+        args.insert("style".to_string(), Value::String("html".to_string()));
+        assert!(TemplateEngine::code_block_filter(&value, &args).is_err());
+    }
+
+    #[test]
+    fn test_render_preset_code_block_style() {
+        use crate::preset::PresetKind;
+        use assert_fs::TempDir;
+
+        let temp = TempDir::new().unwrap();
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .output_dir(temp.path().join("out"))
+            .format(OutputFormat::Xml)
+            .preset(PresetKind::SecurityAudit)
+            .build()
+            .unwrap();
+        let engine = TemplateEngine::new(&config).unwrap();
+        let chunk = Chunk::new(
+            0,
+            vec![FileData::new_text(
+                PathBuf::from("lib.rs"),
+                "lib.rs".to_string(),
+                "let s = \"]]>\";".to_string(),
+                5,
+            )],
+            5,
+        );
+
+        let rendered = engine
+            .render(&chunk, 1, &RunContext::new(std::slice::from_ref(&chunk)))
+            .unwrap();
+
+        assert!(rendered.contains("<code_block_style>markdown</code_block_style>"));
+        assert!(rendered.contains("<content><![CDATA[\n```rust\nlet s = \"]]]]><![CDATA[>\";\n```\n]]></content>"));
+    }
+
+    #[test]
+    fn test_render_diff_sections() {
+        let patch = "--- a/lib.rs\n+++ b/lib.rs\n@@ -1 +1 @@\n-fn a() {}\n+fn b() {}";
+        let chunk = Chunk::new(
+            0,
+            vec![FileData {
+                diff: Some(FileDiff {
+                    base: "main".to_string(),
+                    is_new: false,
+                    is_deleted: false,
+                    added: 1,
+                    removed: 1,
+                }),
+                ..FileData::new_text(PathBuf::from("lib.rs"), "lib.rs".to_string(), patch.to_string(), 8)
+            }],
+            8,
+        );
+        let render = |format| {
+            let engine = TemplateEngine::new(&create_test_config(format)).unwrap();
+            engine
+                .render(&chunk, 1, &RunContext::new(std::slice::from_ref(&chunk)))
+                .unwrap()
+        };
+
+        let markdown = render(OutputFormat::Markdown);
+        assert!(markdown.contains("**Diff against** `main` | **+1 -1**"));
+        assert!(markdown.contains(&format!("```diff\n{patch}\n```")));
+
+        let xml = render(OutputFormat::Xml);
+        assert!(xml.contains(r#"<diff base="main" added="1" removed="1" new_file="false" deleted_file="false"><![CDATA["#));
+        assert!(!xml.contains("<content>"));
+
+        let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        assert_eq!(json["files"][0]["diff"]["base"], "main");
+        assert_eq!(json["files"][0]["diff"]["added"], 1);
+        assert_eq!(json["files"][0]["content"], patch);
+    }
+
+    #[test]
+    fn test_render_part_metadata() {
+        let chunk = Chunk::new(
+            0,
+            vec![FileData {
+                part: Some(FilePart {
+                    index: 2,
+                    total: 3,
+                    start_line: 40,
+                    end_line: 90,
+                    overlap: Some(LineRange { start: 40, end: 45 }),
+                }),
+                ..FileData::new_text(PathBuf::from("big.rs"), "big.rs".to_string(), "fn a() {}".to_string(), 3)
+            }],
+            3,
+        );
+        let render = |format| {
+            let engine = TemplateEngine::new(&create_test_config(format)).unwrap();
+            engine
+                .render(&chunk, 1, &RunContext::new(std::slice::from_ref(&chunk)))
+                .unwrap()
+        };
+
+        let markdown = render(OutputFormat::Markdown);
+        assert!(markdown.contains("## 📄 `big.rs` (part 2/3)"));
+        assert!(markdown.contains("*Part 2/3: lines 40-90, lines 40-45 repeat the previous part*"));
+        assert!(markdown.contains("```rust\nfn a() {}"));
+
+        let xml = render(OutputFormat::Xml);
+        assert!(xml.contains(
+            r#"<part index="2" total="3" start_line="40" end_line="90" overlap_start="40" overlap_end="45"/>"#
+        ));
+
+        let json: serde_json::Value = serde_json::from_str(&render(OutputFormat::Json)).unwrap();
+        assert_eq!(json["files"][0]["part"]["end_line"], 90);
+        assert_eq!(json["files"][0]["part"]["overlap"]["start"], 40);
+    }
+
+    #[test]
+    fn test_render_line_numbers() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .line_numbers(true)
+            .build()
+            .unwrap();
+        let engine = TemplateEngine::new(&config).unwrap();
+        let chunk = Chunk::new(
+            0,
+            vec![FileData {
+                source_lines: Some(vec![3, 9, 10]),
+                ..FileData::new_text(
+                    PathBuf::from("lib.rs"),
+                    "lib.rs".to_string(),
+                    "fn a() {\n    1\n}".to_string(),
+                    5,
+                )
+            }],
+            5,
+        );
+
+        let rendered = engine
+            .render(&chunk, 1, &RunContext::new(std::slice::from_ref(&chunk)))
+            .unwrap();
+        assert!(rendered.contains("```rust\n 3 | fn a() {\n 9 |     1\n10 | }\n```"));
+        assert!(rendered.contains("**Lines:** 3"));
+    }
+
     #[test]
     fn test_xml_escape_filter() {
         let value = Value::String("<test & \"quotes\">".to_string());
@@ -1,3 +1,6 @@
+use crate::bpe::BpeTokenizer;
+use crate::error::Result;
+use std::path::PathBuf;
 use std::sync::Arc;
 
 const SIMPLE_CHARS_PER_TOKEN: usize = 4;
@@ -5,22 +8,35 @@ const ENHANCED_WORD_MULTIPLIER: f64 = 1.3;
 const ENHANCED_SPECIAL_DIVISOR: usize = 10;
 
 /// Type of tokenizer to use for estimation.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum TokenizerKind {
     /// Simple character-based tokenizer (~4 chars per token)
     Simple,
     /// Enhanced tokenizer with word and special character analysis
     Enhanced,
+    /// Exact byte-pair encoding with a vocabulary loaded from disk.
+    ///
+    /// Accepts a tiktoken rank file (e.g. `cl100k_base.tiktoken`,
+    /// `o200k_base.tiktoken`) or a Hugging Face `tokenizer.json` with a
+    /// byte-level BPE model.
+    Bpe {
+        /// Path to the vocabulary file
+        vocab_path: PathBuf,
+    },
 }
 
 impl TokenizerKind {
     /// Creates a new tokenizer instance of this kind.
-    #[must_use]
-    pub fn create(self) -> Arc<dyn TokenEstimator> {
-        match self {
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if a BPE vocabulary cannot be read or parsed.
+    pub fn create(&self) -> Result<Arc<dyn TokenEstimator>> {
+        Ok(match self {
             Self::Simple => Arc::new(SimpleTokenizer),
             Self::Enhanced => Arc::new(EnhancedTokenizer),
-        }
+            Self::Bpe { vocab_path } => Arc::new(BpeTokenizer::from_file(vocab_path)?),
+        })
     }
 }
 
@@ -219,6 +235,14 @@ mod tests {
         assert_eq!(count_special_chars("fn main() {}"), 4);
     }
 
+    #[test]
+    fn test_bpe_kind_missing_vocabulary() {
+        let kind = TokenizerKind::Bpe {
+            vocab_path: PathBuf::from("/nonexistent/vocab.tiktoken"),
+        };
+        assert!(kind.create().is_err());
+    }
+
     #[test]
     fn test_estimate_slice() {
         let tokenizer = SimpleTokenizer;
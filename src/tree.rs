@@ -0,0 +1,243 @@
+//! Directory tree of the files included in a run.
+//!
+//! Presets with `include_structure` render this tree in the first chunk so the
+//! LLM sees the project layout before any code.
+
+use crate::splitter::Chunk;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+/// A file or directory in the project tree.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub(crate) struct TreeNode {
+    /// File or directory name
+    pub name: String,
+    /// Path relative to the scan root
+    pub path: String,
+    /// Whether this node is a directory
+    pub is_dir: bool,
+    /// Whether this node is a binary file
+    pub is_binary: bool,
+    /// Token count of the file, or the total of all files below a directory
+    pub tokens: usize,
+    /// Number of files at or below this node
+    pub files: usize,
+    /// Child nodes, directories first, then files, each sorted by name
+    pub children: Vec<Self>,
+}
+
+#[derive(Default)]
+struct NodeBuilder {
+    file: Option<(usize, bool)>,
+    children: BTreeMap<String, Self>,
+}
+
+impl NodeBuilder {
+    fn insert(&mut self, components: &[&str], tokens: usize, is_binary: bool) {
+        let Some((first, rest)) = components.split_first() else {
+            let (total, _) = self.file.get_or_insert((0, is_binary));
+            *total += tokens;
+            return;
+        };
+
+        self.children
+            .entry((*first).to_string())
+            .or_default()
+            .insert(rest, tokens, is_binary);
+    }
+
+    fn build(self, name: String, path: String) -> TreeNode {
+        if let Some((tokens, is_binary)) = self.file {
+            if self.children.is_empty() {
+                return TreeNode {
+                    name,
+                    path,
+                    is_dir: false,
+                    is_binary,
+                    tokens,
+                    files: 1,
+                    children: Vec::new(),
+                };
+            }
+        }
+
+        let mut children: Vec<TreeNode> = self
+            .children
+            .into_iter()
+            .map(|(child_name, child)| {
+                let child_path = if path.is_empty() {
+                    child_name.clone()
+                } else {
+                    format!("{path}/{child_name}")
+                };
+                child.build(child_name, child_path)
+            })
+            .collect();
+        // Directories before files; BTreeMap already sorted names
+        children.sort_by_key(|child| !child.is_dir);
+
+        TreeNode {
+            name,
+            path,
+            is_dir: true,
+            is_binary: false,
+            tokens: children.iter().map(|child| child.tokens).sum(),
+            files: children.iter().map(|child| child.files).sum(),
+            children,
+        }
+    }
+}
+
+impl TreeNode {
+    /// Builds the tree of every file in the given chunks.
+    ///
+    /// Parts of a split file are merged into one entry whose token count is
+    /// the sum of its parts.
+    pub(crate) fn from_chunks(chunks: &[Chunk]) -> Self {
+        let mut root = NodeBuilder::default();
+
+        for file in chunks.iter().flat_map(|chunk| &chunk.files) {
+            let components: Vec<&str> = file
+                .relative_path
+                .split(['/', '\\'])
+                .filter(|component| !component.is_empty())
+                .collect();
+            root.insert(&components, file.token_count, file.is_binary());
+        }
+
+        root.build(".".to_string(), String::new())
+    }
+
+    /// Renders the tree as indented text with per-file token counts.
+    ///
+    /// When `directory_totals` is set, directories show their file count and
+    /// token total as well.
+    pub(crate) fn render(&self, directory_totals: bool) -> String {
+        let mut output = String::new();
+        let _ = write!(output, "{}", self.name);
+        if directory_totals {
+            let _ = write!(output, " ({})", self.summary());
+        }
+        output.push('\n');
+
+        self.render_children("", directory_totals, &mut output);
+        output.truncate(output.trim_end().len());
+        output
+    }
+
+    fn render_children(&self, prefix: &str, directory_totals: bool, output: &mut String) {
+        let count = self.children.len();
+
+        for (index, child) in self.children.iter().enumerate() {
+            let last = index + 1 == count;
+            let branch = if last { "└── " } else { "├── " };
+            let _ = write!(output, "{prefix}{branch}{}", child.name);
+
+            if child.is_dir {
+                output.push('/');
+                if directory_totals {
+                    let _ = write!(output, " ({})", child.summary());
+                }
+            } else if child.is_binary {
+                output.push_str(" (binary)");
+            } else {
+                let _ = write!(output, " ({} tokens)", child.tokens);
+            }
+            output.push('\n');
+
+            if child.is_dir {
+                let nested = format!("{prefix}{}", if last { "    " } else { "│   " });
+                child.render_children(&nested, directory_totals, output);
+            }
+        }
+    }
+
+    fn summary(&self) -> String {
+        let files = if self.files == 1 { "file" } else { "files" };
+        format!("{} {files}, {} tokens", self.files, self.tokens)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::file::FileData;
+    use std::path::PathBuf;
+
+    fn text_file(path: &str, tokens: usize) -> FileData {
+        FileData::new_text(
+            PathBuf::from("/project").join(path),
+            path.to_string(),
+            "x".to_string(),
+            tokens,
+        )
+    }
+
+    fn sample_tree() -> TreeNode {
+        let chunks = vec![
+            Chunk::new(
+                0,
+                vec![
+                    text_file("src/main.rs", 10),
+                    text_file("src/util/io.rs", 5),
+                    text_file("Cargo.toml", 3),
+                ],
+                18,
+            ),
+            Chunk::new(
+                1,
+                vec![
+                    text_file("src/big.rs", 40),
+                    FileData::new_binary(PathBuf::from("/project/logo.png"), "logo.png".to_string(), 64),
+                ],
+                40,
+            ),
+            Chunk::new(2, vec![text_file("src/big.rs", 30)], 30),
+        ];
+
+        TreeNode::from_chunks(&chunks)
+    }
+
+    #[test]
+    fn test_tree_totals() {
+        let tree = sample_tree();
+
+        assert!(tree.is_dir);
+        assert_eq!(tree.files, 5);
+        assert_eq!(tree.tokens, 88);
+
+        let src = &tree.children[0];
+        assert_eq!(src.path, "src");
+        assert_eq!(src.files, 3);
+        assert_eq!(src.tokens, 85);
+
+        let big = src.children.iter().find(|c| c.name == "big.rs").unwrap();
+        assert_eq!(big.path, "src/big.rs");
+        assert_eq!(big.tokens, 70);
+    }
+
+    #[test]
+    fn test_render_without_directory_totals() {
+        let expected = "\
+.
+├── src/
+│   ├── util/
+│   │   └── io.rs (5 tokens)
+│   ├── big.rs (70 tokens)
+│   └── main.rs (10 tokens)
+├── Cargo.toml (3 tokens)
+└── logo.png (binary)";
+
+        assert_eq!(sample_tree().render(false), expected);
+    }
+
+    #[test]
+    fn test_render_with_directory_totals() {
+        let rendered = sample_tree().render(true);
+
+        assert!(rendered.starts_with(". (5 files, 88 tokens)\n"));
+        assert!(rendered.contains("├── src/ (3 files, 85 tokens)"));
+        assert!(rendered.contains("│   ├── util/ (1 file, 5 tokens)"));
+    }
+}
@@ -0,0 +1,303 @@
+//! Watch mode: regenerates the prompts whenever files under the root change.
+//!
+//! The [`Watcher`] polls the root directory, so it behaves the same on every
+//! platform and file system. It sees the same files as the scanner (honoring
+//! `.gitignore` and the [`FileFilterConfig`](crate::FileFilterConfig)
+//! rules), waits until changes settle, then re-runs the pipeline with the
+//! cache enabled so only changed files are processed and only changed
+//! chunks are rewritten.
+
+use crate::{
+    config::Config,
+    error::{Error, Result},
+    filter::FileFilter,
+    pipeline::{Pipeline, PipelineStats},
+    source::{is_scanned, walk_builder},
+};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+use tracing::{debug, info, warn};
+
+/// Default time between two polls of the root directory.
+pub const DEFAULT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Default time without changes before the pipeline is re-run.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Size and modification time of every watched file, by path.
+type Snapshot = BTreeMap<PathBuf, (u64, Option<SystemTime>)>;
+
+/// Re-runs the pipeline when watched files change.
+///
+/// # Examples
+///
+/// ```no_run
+/// use llm_utl::{Config, Watcher};
+///
+/// # fn main() -> anyhow::Result<()> {
+/// let config = Config::builder().root_dir("./src").build()?;
+///
+/// Watcher::new(config)?.run(|stats| {
+///     println!("{} chunk(s) changed", stats.changed_chunks.len());
+///     true // keep watching
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Watcher {
+    config: Config,
+    file_filter: FileFilter,
+    root_dir: PathBuf,
+    output_dir: PathBuf,
+    poll_interval: Duration,
+    debounce: Duration,
+}
+
+impl Watcher {
+    /// Creates a watcher for the given configuration.
+    ///
+    /// The cache is always enabled, and existing output files are not backed
+    /// up, since every change would otherwise leave a backup behind.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if configuration validation fails, or if the
+    /// configured [`FileSource`](crate::FileSource) is not a directory on disk.
+    pub fn new(mut config: Config) -> Result<Self> {
+        config.validate()?;
+        config.cache = true;
+        config.backup_existing = false;
+
+        let root_dir = match &config.source {
+            Some(source) => source
+                .local_root()
+                .ok_or_else(|| Error::config("Watch mode needs files from a directory on disk"))?,
+            None => &config.root_dir,
+        };
+
+        Ok(Self {
+            file_filter: FileFilter::new(config.file_filter_config.clone()),
+            root_dir: absolute(root_dir),
+            output_dir: absolute(&config.output_dir),
+            config,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+            debounce: DEFAULT_DEBOUNCE,
+        })
+    }
+
+    /// Sets the time between two polls of the root directory.
+    #[must_use]
+    pub const fn poll_interval(mut self, interval: Duration) -> Self {
+        self.poll_interval = interval;
+        self
+    }
+
+    /// Sets how long files must stay unchanged before the pipeline re-runs.
+    #[must_use]
+    pub const fn debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Runs the pipeline, then again after every batch of changes.
+    ///
+    /// `on_run` is called with the statistics of every successful run and
+    /// returns whether to keep watching. Failed runs are logged and the
+    /// watcher waits for the next change.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the pipeline cannot be created, e.g. because the
+    /// tokenizer vocabulary or template cannot be loaded.
+    pub fn run(&self, mut on_run: impl FnMut(&PipelineStats) -> bool) -> Result<()> {
+        // Taken before each run, so edits made while it runs trigger another
+        let mut snapshot = self.snapshot();
+        if !self.run_once(&mut on_run)? {
+            return Ok(());
+        }
+
+        loop {
+            info!("Watching {} for changes...", self.root_dir.display());
+            let changed = self.wait_for_changes(&mut snapshot);
+            info!("{} file(s) changed: {}", changed.len(), changed.join(", "));
+
+            if !self.run_once(&mut on_run)? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Runs the pipeline once and reports the chunks it rewrote.
+    fn run_once(&self, on_run: &mut impl FnMut(&PipelineStats) -> bool) -> Result<bool> {
+        match Pipeline::new(self.config.clone())?.run() {
+            Ok(stats) => {
+                if stats.changed_chunks.is_empty() {
+                    info!("No chunks changed");
+                } else {
+                    info!("Changed chunks: {}", stats.changed_chunks.join(", "));
+                }
+                Ok(on_run(&stats))
+            }
+            Err(e) => {
+                warn!("Pipeline execution failed: {}", e);
+                Ok(true)
+            }
+        }
+    }
+
+    /// Polls until watched files change and stay unchanged for the debounce
+    /// time, updates `snapshot` and returns the changed relative paths.
+    fn wait_for_changes(&self, snapshot: &mut Snapshot) -> Vec<String> {
+        loop {
+            thread::sleep(self.poll_interval);
+            let mut latest = self.snapshot();
+            if latest == *snapshot {
+                continue;
+            }
+
+            let mut quiet_since = Instant::now();
+            while quiet_since.elapsed() < self.debounce {
+                thread::sleep(self.poll_interval.min(self.debounce));
+                let next = self.snapshot();
+                if next != latest {
+                    latest = next;
+                    quiet_since = Instant::now();
+                }
+            }
+
+            let changed = self.changes(snapshot, &latest);
+            *snapshot = latest;
+            if !changed.is_empty() {
+                return changed;
+            }
+        }
+    }
+
+    /// Records the size and modification time of every file the scanner
+    /// would see, leaving out the output directory.
+    fn snapshot(&self) -> Snapshot {
+        walk_builder(&self.root_dir)
+            .build()
+            .filter_map(|entry| {
+                entry
+                    .map_err(|e| debug!("Walk error while watching: {}", e))
+                    .ok()
+            })
+            .filter(|entry| entry.file_type().is_some_and(|ft| ft.is_file()))
+            .filter(|entry| !entry.path().starts_with(&self.output_dir))
+            .filter(|entry| is_scanned(entry.path(), &self.file_filter))
+            .filter_map(|entry| {
+                let metadata = entry.metadata().ok()?;
+                Some((
+                    entry.into_path(),
+                    (metadata.len(), metadata.modified().ok()),
+                ))
+            })
+            .collect()
+    }
+
+    /// Returns the paths added, modified or removed between two snapshots,
+    /// relative to the root.
+    fn changes(&self, before: &Snapshot, after: &Snapshot) -> Vec<String> {
+        let modified = after
+            .iter()
+            .filter(|(path, state)| before.get(*path) != Some(state))
+            .map(|(path, _)| path);
+        let removed = before.keys().filter(|path| !after.contains_key(*path));
+
+        let mut changed: Vec<String> = modified
+            .chain(removed)
+            .map(|path| {
+                path.strip_prefix(&self.root_dir)
+                    .unwrap_or(path)
+                    .display()
+                    .to_string()
+            })
+            .collect();
+        changed.sort();
+        changed
+    }
+}
+
+/// Returns `path` as an absolute path, resolving symlinks when it exists.
+fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize()
+        .or_else(|_| std::path::absolute(path))
+        .unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use assert_fs::prelude::*;
+
+    fn watcher(root: &Path, out: &Path) -> Watcher {
+        let config = Config::builder()
+            .root_dir(root)
+            .output_dir(out)
+            .build()
+            .unwrap();
+        Watcher::new(config)
+            .unwrap()
+            .poll_interval(Duration::from_millis(20))
+            .debounce(Duration::from_millis(60))
+    }
+
+    #[test]
+    fn test_snapshot_skips_ignored_and_output_files() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child(".gitignore").write_str("ignored.rs\n").unwrap();
+        temp.child(".git").create_dir_all().unwrap();
+        temp.child("main.rs").write_str("fn main() {}").unwrap();
+        temp.child("ignored.rs").write_str("fn ignored() {}").unwrap();
+        temp.child("out/prompt_001.md").write_str("# Chunk").unwrap();
+
+        let watcher = watcher(temp.path(), &temp.path().join("out"));
+        let files = watcher.changes(&Snapshot::new(), &watcher.snapshot());
+
+        assert_eq!(files, ["main.rs"]);
+    }
+
+    #[test]
+    fn test_changes_reports_added_modified_and_removed() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        temp.child("a.rs").write_str("fn a() {}").unwrap();
+        temp.child("b.rs").write_str("fn b() {}").unwrap();
+        let watcher = watcher(temp.path(), &temp.path().join("out"));
+        let before = watcher.snapshot();
+
+        temp.child("a.rs").write_str("fn a() { changed(); }").unwrap();
+        std::fs::remove_file(temp.child("b.rs").path()).unwrap();
+        temp.child("c.rs").write_str("fn c() {}").unwrap();
+
+        assert_eq!(watcher.changes(&before, &watcher.snapshot()), ["a.rs", "b.rs", "c.rs"]);
+    }
+
+    #[test]
+    fn test_watcher_reruns_on_change() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let out = assert_fs::TempDir::new().unwrap();
+        temp.child("a.rs").write_str("fn a() {}").unwrap();
+        let root = temp.path().to_path_buf();
+
+        let mut runs = Vec::new();
+        watcher(temp.path(), out.path())
+            .run(|stats| {
+                runs.push((stats.cache_hits, stats.cache_misses, stats.changed_chunks.clone()));
+                if runs.len() == 1 {
+                    let root = root.clone();
+                    thread::spawn(move || {
+                        thread::sleep(Duration::from_millis(50));
+                        std::fs::write(root.join("b.rs"), "fn b() {}").unwrap();
+                    });
+                }
+                runs.len() < 2
+            })
+            .unwrap();
+
+        assert_eq!(runs[0], (0, 1, vec!["prompt_001.md".to_string()]));
+        assert_eq!(runs[1], (1, 1, vec!["prompt_001.md".to_string()]));
+    }
+}
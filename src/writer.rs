@@ -1,153 +1,124 @@
 use crate::{
+    cache::{content_hash, ChunkCache},
     config::Config,
     error::{Error, Result},
-    splitter::Chunk,
-    template::TemplateEngine,
+    render::{RenderedChunk, RunSummary},
+    sourcemap::{SourceMap, SOURCE_MAP_FILE},
 };
-use serde::Serialize;
 use std::{
     fs,
     io::Write,
     path::{Path, PathBuf},
     time::{Duration, SystemTime},
 };
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-/// Summary of written output files.
-#[derive(Debug, Serialize)]
-pub(crate) struct WriteSummary {
-    /// Total number of chunks written
-    pub total_chunks: usize,
-
-    /// Total number of files across all chunks
-    pub total_files: usize,
-
-    /// Total token count across all chunks
-    pub total_tokens: usize,
-
-    /// Execution duration in seconds
-    pub duration_secs: f64,
-
-    /// Output directory path
-    pub output_directory: String,
-
-    /// Output format used
-    pub format: String,
-
-    /// Individual chunk summaries
-    pub chunks: Vec<ChunkSummary>,
-
-    /// Generation timestamp
-    pub generated_at: String,
-}
-
-/// Summary of a single chunk.
-#[derive(Debug, Serialize)]
-pub(crate) struct ChunkSummary {
-    /// Chunk index (1-based for user display)
-    pub index: usize,
-
-    /// Number of files in chunk
-    pub files: usize,
-
-    /// Token count in chunk
-    pub tokens: usize,
-
-    /// Output filename
-    pub filename: String,
-}
-
-/// Writes chunks to output files with atomic operations.
+/// Writes rendered chunks to output files with atomic operations.
 pub(crate) struct Writer {
     output_dir: PathBuf,
-    output_pattern: String,
-    format: crate::config::OutputFormat,
     backup_existing: bool,
-    template_engine: TemplateEngine,
-    custom_extension: Option<String>,
+    cache: Option<ChunkCache>,
+}
+
+/// Result of writing the chunk files.
+#[derive(Debug)]
+pub(crate) struct WrittenChunks {
+    /// Output files left alone because their content was unchanged
+    pub unchanged: usize,
+    /// Names of the output files that were (re)written
+    pub changed: Vec<String>,
 }
 
 impl Writer {
     /// Creates a new writer from configuration.
-    ///
-    /// # Errors
-    ///
-    /// Returns an error if template engine initialization fails.
-    pub(crate) fn new(config: &Config) -> Result<Self> {
-        Ok(Self {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
             output_dir: config.output_dir.clone(),
-            output_pattern: config.output_pattern.clone(),
-            format: config.format,
             backup_existing: config.backup_existing,
-            template_engine: TemplateEngine::new(config)?,
-            custom_extension: config.custom_extension.clone(),
-        })
+            cache: config.cache.then(|| ChunkCache::load(&config.output_dir)),
+        }
     }
 
-    /// Writes all chunks to output files.
+    /// Writes rendered chunks to output files, followed by `summary.json`
+    /// and `sourcemap.json`.
+    ///
+    /// With the cache enabled, output files whose content only differs in
+    /// the generation timestamp are not rewritten.
     ///
     /// # Errors
     ///
     /// Returns an error if:
     /// - Output directory cannot be created
-    /// - Template rendering fails
     /// - File write operations fail
-    pub(crate) fn write_chunks(&self, chunks: &[Chunk]) -> Result<()> {
+    pub(crate) fn write(
+        &self,
+        chunks: &[RenderedChunk],
+        summary: &RunSummary,
+    ) -> Result<WrittenChunks> {
         // Create output directory
         fs::create_dir_all(&self.output_dir)
             .map_err(|e| Error::io(&self.output_dir, e))?;
 
         info!("Writing {} chunks to {}", chunks.len(), self.output_dir.display());
 
-        // Write each chunk
+        let mut written = WrittenChunks {
+            unchanged: 0,
+            changed: Vec::new(),
+        };
         for chunk in chunks {
-            self.write_chunk(chunk, chunks.len())?;
+            if self.write_chunk(chunk, &summary.generated_at)? {
+                written.changed.push(chunk.filename.clone());
+            } else {
+                written.unchanged += 1;
+            }
         }
 
-        info!("Successfully wrote {} chunk files", chunks.len());
-        Ok(())
-    }
-
-    /// Writes a single chunk to file.
-    fn write_chunk(&self, chunk: &Chunk, total_chunks: usize) -> Result<()> {
-        let content = self.template_engine.render(chunk, total_chunks)?;
-        let path = self.get_output_path(chunk.index);
-
-        self.write_file_atomic(&path, &content)?;
+        if let Some(cache) = &self.cache {
+            if let Err(e) = cache.save() {
+                warn!("Failed to save output cache: {}", e);
+            }
+        }
 
-        debug!(
-            "Wrote chunk {}/{} ({} files, {} tokens) to {}",
-            chunk.index + 1,
-            total_chunks,
-            chunk.files.len(),
-            chunk.total_tokens,
-            path.display()
+        info!(
+            "Successfully wrote {} chunk files ({} unchanged)",
+            written.changed.len(),
+            written.unchanged
         );
 
-        Ok(())
-    }
+        self.write_summary(summary)?;
+        self.write_source_map(&SourceMap {
+            chunks: chunks.iter().map(|chunk| chunk.source_map.clone()).collect(),
+        })?;
 
-    /// Generates the output file path for a chunk.
-    fn get_output_path(&self, index: usize) -> PathBuf {
-        use crate::config::OutputFormat;
-
-        // Determine extension based on format
-        let extension = match self.format {
-            OutputFormat::Custom => self
-                .custom_extension
-                .as_deref()
-                .unwrap_or("txt"),
-            _ => self.format.extension(),
-        };
+        Ok(written)
+    }
 
-        let filename = self
-            .output_pattern
-            .replace("{index:03}", &format!("{:03}", index + 1))
-            .replace("{index:02}", &format!("{:02}", index + 1))
-            .replace("{index}", &(index + 1).to_string())
-            .replace("{ext}", extension);
+    /// Writes a single chunk to its file.
+    ///
+    /// Returns `false` when the file was left unchanged.
+    fn write_chunk(&self, chunk: &RenderedChunk, generated_at: &str) -> Result<bool> {
+        let path = self.output_dir.join(&chunk.filename);
+
+        // The timestamp changes on every run, so it is left out of the hash
+        let unchanged = self.cache.as_ref().is_some_and(|cache| {
+            let hash = content_hash(&chunk.content.replace(generated_at, ""));
+            cache.is_unchanged(&chunk.filename, hash) && path.exists()
+        });
+
+        if unchanged {
+            debug!("Chunk {} unchanged, keeping {}", chunk.index, path.display());
+        } else {
+            self.write_file_atomic(&path, &chunk.content)?;
+            debug!(
+                "Wrote chunk {} ({} files, {} tokens) to {}",
+                chunk.index,
+                chunk.files.len(),
+                chunk.total_tokens,
+                path.display()
+            );
+        }
 
-        self.output_dir.join(filename)
+        Ok(!unchanged)
     }
 
     /// Writes a file atomically with optional backup.
@@ -218,44 +189,33 @@ impl Writer {
     /// # Errors
     ///
     /// Returns an error if the summary file cannot be written.
-    pub(crate) fn write_summary(&self, chunks: &[Chunk], duration: Duration) -> Result<()> {
-        let summary = WriteSummary {
-            total_chunks: chunks.len(),
-            total_files: chunks.iter().map(|c| c.files.len()).sum(),
-            total_tokens: chunks.iter().map(|c| c.total_tokens).sum(),
-            duration_secs: duration.as_secs_f64(),
-            output_directory: self.output_dir.display().to_string(),
-            format: format!("{:?}", self.format),
-            chunks: chunks
-                .iter()
-                .map(|c| ChunkSummary {
-                    index: c.index + 1,
-                    files: c.files.len(),
-                    tokens: c.total_tokens,
-                    filename: self
-                        .get_output_path(c.index)
-                        .file_name()
-                        .unwrap()
-                        .to_string_lossy()
-                        .to_string(),
-                })
-                .collect(),
-            generated_at: chrono::Local::now()
-                .format("%Y-%m-%d %H:%M:%S")
-                .to_string(),
-        };
-
+    fn write_summary(&self, summary: &RunSummary) -> Result<()> {
         let summary_path = self.output_dir.join("summary.json");
         let file = fs::File::create(&summary_path)
             .map_err(|e| Error::io(&summary_path, e))?;
 
-        serde_json::to_writer_pretty(file, &summary)
+        serde_json::to_writer_pretty(file, summary)
             .map_err(Error::from)?;
 
         info!("Wrote summary to {}", summary_path.display());
         Ok(())
     }
 
+    /// Writes the source map that resolves output lines to source lines.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source map file cannot be written.
+    fn write_source_map(&self, source_map: &SourceMap) -> Result<()> {
+        let path = self.output_dir.join(SOURCE_MAP_FILE);
+        let file = fs::File::create(&path).map_err(|e| Error::io(&path, e))?;
+
+        serde_json::to_writer_pretty(file, source_map).map_err(Error::from)?;
+
+        info!("Wrote source map to {}", path.display());
+        Ok(())
+    }
+
     /// Cleans up old backup files (optional utility method).
     ///
     /// Removes backup files older than the specified duration.
@@ -301,6 +261,9 @@ impl Writer {
 mod tests {
     use super::*;
     use crate::file::FileData;
+    use crate::render::Renderer;
+    use crate::splitter::Chunk;
+    use crate::template::RunContext;
     use assert_fs::prelude::*;
     use std::path::PathBuf;
 
@@ -328,16 +291,22 @@ mod tests {
         )
     }
 
+    /// Renders chunks and writes them with a writer for `config`.
+    fn write(config: &Config, chunks: &[Chunk]) -> WrittenChunks {
+        let renderer = Renderer::new(config).unwrap();
+        let run = RunContext::new(chunks);
+        let prompts = renderer.render(chunks, &run).unwrap();
+        let summary = renderer.summary(&prompts, &run, Duration::from_secs(1));
+        Writer::new(config).write(&prompts, &summary).unwrap()
+    }
+
     #[test]
     fn test_writer_creates_output_directory() {
         let temp = assert_fs::TempDir::new().unwrap();
         let output_dir = temp.child("output");
 
         let config = create_test_config(output_dir.path());
-        let writer = Writer::new(&config).unwrap();
-
-        let chunks = vec![create_test_chunk(0)];
-        writer.write_chunks(&chunks).unwrap();
+        write(&config, &[create_test_chunk(0)]);
 
         assert!(output_dir.exists());
     }
@@ -348,10 +317,7 @@ mod tests {
         let output_dir = temp.child("output");
 
         let config = create_test_config(output_dir.path());
-        let writer = Writer::new(&config).unwrap();
-
-        let chunks = vec![create_test_chunk(0), create_test_chunk(1)];
-        writer.write_chunks(&chunks).unwrap();
+        write(&config, &[create_test_chunk(0), create_test_chunk(1)]);
 
         assert!(output_dir.child("prompt_001.md").exists());
         assert!(output_dir.child("prompt_002.md").exists());
@@ -363,13 +329,57 @@ mod tests {
         let output_dir = temp.child("output");
 
         let config = create_test_config(output_dir.path());
-        let writer = Writer::new(&config).unwrap();
+        write(&config, &[create_test_chunk(0)]);
+
+        let summary = fs::read_to_string(output_dir.child("summary.json").path()).unwrap();
+        let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+        assert_eq!(summary["total_chunks"], 1);
+        assert_eq!(summary["chunks"][0]["filename"], "prompt_001.md");
+    }
+
+    #[test]
+    fn test_writer_creates_source_map() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let output_dir = temp.child("output");
+
+        let config = create_test_config(output_dir.path());
+        write(&config, &[create_test_chunk(0)]);
+        let source_map = SourceMap::load(output_dir.path()).unwrap();
 
-        let chunks = vec![create_test_chunk(0)];
-        writer.write_chunks(&chunks).unwrap();
-        writer.write_summary(&chunks, Duration::from_secs(1)).unwrap();
+        let output = fs::read_to_string(output_dir.child("prompt_001.md").path()).unwrap();
+        let line = output.lines().position(|l| l == "fn main() {}").unwrap() + 1;
 
-        assert!(output_dir.child("summary.json").exists());
+        assert_eq!(source_map.chunks[0].filename, "prompt_001.md");
+        assert_eq!(
+            crate::sourcemap::resolve(output_dir.child("prompt_001.md").path(), line).unwrap(),
+            Some(("test.rs".to_string(), 1))
+        );
+        assert_eq!(source_map.resolve("prompt_001.md", 1), None);
+    }
+
+    #[test]
+    fn test_writer_skips_unchanged_chunks() {
+        let temp = assert_fs::TempDir::new().unwrap();
+        let output_dir = temp.child("output");
+        let config = Config::builder()
+            .root_dir(temp.path())
+            .output_dir(output_dir.path())
+            .backup_existing(false)
+            .cache(true)
+            .build()
+            .unwrap();
+
+        let written = write(&config, &[create_test_chunk(0), create_test_chunk(1)]);
+        assert_eq!(written.unchanged, 0);
+
+        let mut changed = create_test_chunk(1);
+        changed.files[0].content = crate::file::FileContent::Text("fn changed() {}".to_string());
+        let written = write(&config, &[create_test_chunk(0), changed]);
+        assert_eq!(written.unchanged, 1);
+        assert_eq!(written.changed, ["prompt_002.md"]);
+
+        let second = fs::read_to_string(output_dir.child("prompt_002.md").path()).unwrap();
+        assert!(second.contains("fn changed() {}"));
     }
 
     #[test]
@@ -382,10 +392,7 @@ mod tests {
         existing_file.write_str("old content").unwrap();
 
         let config = create_test_config(output_dir.path());
-        let writer = Writer::new(&config).unwrap();
-
-        let chunks = vec![create_test_chunk(0)];
-        writer.write_chunks(&chunks).unwrap();
+        write(&config, &[create_test_chunk(0)]);
 
         // Check backup was created
         let entries: Vec<_> = fs::read_dir(output_dir.path())
@@ -396,19 +403,6 @@ mod tests {
         assert!(entries.iter().any(|name| name.contains(".backup.")));
     }
 
-    #[test]
-    fn test_get_output_path() {
-        let temp = assert_fs::TempDir::new().unwrap();
-        let config = create_test_config(temp.path());
-        let writer = Writer::new(&config).unwrap();
-
-        let path = writer.get_output_path(0);
-        assert!(path.to_string_lossy().contains("prompt_001.md"));
-
-        let path = writer.get_output_path(9);
-        assert!(path.to_string_lossy().contains("prompt_010.md"));
-    }
-
     #[test]
     fn test_cleanup_old_backups() {
         use std::thread;
@@ -430,7 +424,7 @@ mod tests {
         new_backup.write_str("new").unwrap();
 
         let config = create_test_config(output_dir.path());
-        let writer = Writer::new(&config).unwrap();
+        let writer = Writer::new(&config);
 
         // Clean up backups older than 50ms
         let removed = writer